@@ -0,0 +1,79 @@
+// cue_sheet
+// Copyright (C) 2017  Leonardo Schwarz <mail@leoschwarz.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Parser throughput on large cue sheets.
+//!
+//! Run with `cargo bench`. The sheets are generated, so the numbers are comparable between
+//! revisions without any files on disk.
+
+#[macro_use]
+extern crate criterion;
+extern crate cue_sheet;
+
+use criterion::{Criterion, Throughput};
+use cue_sheet::parser::{tokenize, Time};
+use cue_sheet::tracklist::Tracklist;
+use std::fmt::Write;
+
+/// A single file disc with 99 tracks, each with a pregap and some metadata.
+fn full_disc() -> String {
+    let mut s = String::from(
+        "REM GENRE \"Shoegaze\"\nREM DATE 1991\nPERFORMER \"My Bloody Valentine\"\n\
+         TITLE \"Loveless\"\nFILE \"Loveless.wav\" WAVE\n",
+    );
+    for i in 0..99 {
+        let start = Time::from_frames(i * 45 * 75);
+        writeln!(s, "  TRACK {:02} AUDIO", i + 1).unwrap();
+        writeln!(s, "    TITLE \"Track {}\"", i + 1).unwrap();
+        writeln!(s, "    PERFORMER \"My Bloody Valentine\"").unwrap();
+        writeln!(s, "    ISRC GBAAA9100{:03}", i).unwrap();
+        if i > 0 {
+            writeln!(
+                s,
+                "    INDEX 00 {}",
+                Time::from_frames(start.total_frames() - 150)
+            )
+            .unwrap();
+        }
+        writeln!(s, "    INDEX 01 {}", start).unwrap();
+    }
+    s
+}
+
+/// A compilation of 200 files with one track each.
+fn compilation() -> String {
+    let mut s = String::from("TITLE \"Compilation\"\n");
+    for i in 0..200 {
+        writeln!(s, "FILE \"{:03} - Artist {}.flac\" WAVE", i + 1, i).unwrap();
+        writeln!(s, "  TRACK {:02} AUDIO", i % 99 + 1).unwrap();
+        writeln!(s, "    TITLE \"Song {}\"", i).unwrap();
+        writeln!(s, "    INDEX 01 00:00:00").unwrap();
+    }
+    s
+}
+
+fn parse(c: &mut Criterion) {
+    for (name, sheet) in &[("full_disc", full_disc()), ("compilation", compilation())] {
+        let mut group = c.benchmark_group(*name);
+        group.throughput(Throughput::Bytes(sheet.len() as u64));
+        group.bench_function("tokenize", |b| b.iter(|| tokenize(sheet).unwrap()));
+        group.bench_function("tracklist", |b| b.iter(|| Tracklist::parse(sheet).unwrap()));
+        group.finish();
+    }
+}
+
+criterion_group!(benches, parse);
+criterion_main!(benches);
@@ -28,11 +28,10 @@ use cue_sheet::errors::Error;
 use cue_sheet::tracklist::Tracklist;
 
 use std::env;
-use std::fs::File;
-use std::io::Read;
+use std::path::Path;
 
-fn perform_conversion(source: &str) -> Result<(), Error> {
-    let mut tracklist = Tracklist::parse(source)?;
+fn perform_conversion(path: &Path) -> Result<(), Error> {
+    let mut tracklist = Tracklist::from_path(path)?;
     // TODO support multi-cds
     assert_eq!(tracklist.files.len(), 1);
 
@@ -58,12 +57,7 @@ fn perform_conversion(source: &str) -> Result<(), Error> {
 
 fn main() {
     if let Some(path) = env::args().nth(1) {
-        // Try reading the file provided by the path.
-        let mut file = File::open(path).expect("Failed reading file.");
-        let mut content = String::new();
-        file.read_to_string(&mut content).unwrap();
-
-        perform_conversion(content.as_str()).expect("Conversion failed.");
+        perform_conversion(Path::new(&path)).expect("Conversion failed.");
     } else {
         println!(
             "provide a path to a .cue file to be converted into a MusicBrainz compatible tracklist."
@@ -0,0 +1,163 @@
+// cue_sheet
+// Copyright (C) 2017  Leonardo Schwarz <mail@leoschwarz.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! A simplified view of an album, for taggers which don't care about the details of cue sheets.
+
+use errors::Error;
+use parser::Time;
+use tracklist::Tracklist;
+
+/// An album described by a cue sheet, with the `REM` comments commonly written by rippers
+/// resolved into typed fields.
+///
+/// ```
+/// use cue_sheet::album::Album;
+/// use cue_sheet::tracklist::Tracklist;
+///
+/// let tracklist = Tracklist::parse(
+///     "REM GENRE Shoegaze\nREM DATE 1991\nREM DISCID 860B640B\n\
+///      PERFORMER \"My Bloody Valentine\"\nTITLE Loveless\nFILE a.wav WAVE\n\
+///      TRACK 01 AUDIO\nTITLE \"Only Shallow\"\nINDEX 01 00:00:00\n\
+///      TRACK 02 AUDIO\nTITLE Loomer\nPERFORMER \"MBV\"\nINDEX 01 04:17:52",
+/// )
+/// .unwrap();
+///
+/// let album = Album::from_tracklist(&tracklist).unwrap();
+/// assert_eq!(album.year, Some(1991));
+/// assert_eq!(album.genre, Some("Shoegaze".to_string()));
+/// assert_eq!(album.discid, Some("860B640B".to_string()));
+/// assert_eq!(album.tracks[0].artist, Some("My Bloody Valentine".to_string()));
+/// assert_eq!(album.tracks[1].artist, Some("MBV".to_string()));
+/// ```
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Album {
+    /// Performer of the album.
+    pub artist: Option<String>,
+
+    /// Title of the album.
+    pub title: Option<String>,
+
+    /// Release year, from the `REM DATE` comment.
+    pub year: Option<u16>,
+
+    /// Genre, from the `REM GENRE` comment.
+    pub genre: Option<String>,
+
+    /// The freedb disc id, from the `REM DISCID` comment.
+    pub discid: Option<String>,
+
+    /// Tracks of the album in the order they appear.
+    pub tracks: Vec<AlbumTrack>,
+}
+
+/// One track of an `Album`.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct AlbumTrack {
+    /// Track number.
+    pub number: u32,
+
+    /// Performer of the track, falling back to the performer of the album like CD-Text does.
+    pub artist: Option<String>,
+
+    /// Title of the track.
+    pub title: Option<String>,
+
+    /// Start of the track relative to the start of the album, see `Track::album_start`.
+    pub start: Option<Time>,
+
+    /// Length of the track, see `Track::duration`.
+    pub length: Option<Time>,
+
+    /// Name of the file containing the track.
+    pub file: String,
+}
+
+/// Returns `value` with surrounding whitespace removed, or `None` if nothing is left.
+fn clean(value: Option<&String>) -> Option<String> {
+    value
+        .map(|v| v.trim())
+        .filter(|v| !v.is_empty())
+        .map(|v| v.to_string())
+}
+
+impl Album {
+    /// Build an album from `tracklist`.
+    ///
+    /// Empty strings are treated as missing values. An error is returned if track numbers are
+    /// not increasing, since taggers can't tell which track is meant then.
+    pub fn from_tracklist(tracklist: &Tracklist) -> Result<Album, Error> {
+        let artist = clean(tracklist.performer.as_ref());
+        let mut tracks: Vec<AlbumTrack> = Vec::new();
+
+        for file in &tracklist.files {
+            for track in &file.tracks {
+                if let Some(previous) = tracks.last() {
+                    if track.number <= previous.number {
+                        return Err(format!(
+                            "Track {:02} follows track {:02}",
+                            track.number, previous.number
+                        )
+                        .into());
+                    }
+                }
+                tracks.push(AlbumTrack {
+                    number: track.number,
+                    artist: clean(track.performer.as_ref()).or_else(|| artist.clone()),
+                    title: clean(track.title.as_ref()),
+                    start: track.album_start(),
+                    length: track.duration.clone(),
+                    file: file.name.clone(),
+                });
+            }
+        }
+
+        Ok(Album {
+            artist,
+            title: clean(tracklist.title.as_ref()),
+            year: tracklist.date_parsed().map(|date| date.year),
+            genre: clean(tracklist.rem_value("GENRE").as_ref()),
+            discid: clean(tracklist.rem_value("DISCID").as_ref()),
+            tracks,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sanity_checks() {
+        let tracklist = Tracklist::parse(
+            "PERFORMER \"\"\nTITLE \" \"\nREM DATE unknown\nFILE a.wav WAVE\n\
+             TRACK 01 AUDIO\nTITLE \" Only Shallow \"\nINDEX 01 00:00:00",
+        )
+        .unwrap();
+        let album = Album::from_tracklist(&tracklist).unwrap();
+        assert_eq!(album.artist, None);
+        assert_eq!(album.title, None);
+        assert_eq!(album.year, None);
+        assert_eq!(album.tracks[0].title, Some("Only Shallow".to_string()));
+        assert_eq!(album.tracks[0].start, Some(Time::new(0, 0, 0)));
+
+        let tracklist = Tracklist::parse(
+            "FILE a.wav WAVE\nTRACK 02 AUDIO\nINDEX 01 00:00:00\n\
+             FILE b.wav WAVE\nTRACK 01 AUDIO\nINDEX 01 00:00:00",
+        )
+        .unwrap();
+        assert!(Album::from_tracklist(&tracklist).is_err());
+    }
+}
@@ -0,0 +1,108 @@
+// cue_sheet
+// Copyright (C) 2017  Leonardo Schwarz <mail@leoschwarz.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Removal of personal information from cue sheets, e.g. before sharing them in bug reports.
+//!
+//! `REM COMMENT` lines (which also contain the signatures of ripping programs) are removed and
+//! file names are stripped of their directories, which often contain user names. Everything
+//! else, especially the track data, is kept as it is.
+
+use parser::Token;
+use sheet::CueSheet;
+use tracklist::{normalize_file_name, TrackFile, Tracklist};
+
+fn anonymize_rem(rem: &mut Vec<(String, Token)>) {
+    rem.retain(|(key, _)| !key.eq_ignore_ascii_case("COMMENT"));
+}
+
+fn anonymize_files(files: &mut [TrackFile]) {
+    for file in files {
+        file.name = normalize_file_name(&file.name);
+        for track in &mut file.tracks {
+            anonymize_rem(&mut track.rem);
+        }
+    }
+}
+
+impl Tracklist {
+    /// Returns a copy of the tracklist with personal information removed, see the `anonymize`
+    /// module.
+    ///
+    /// ```
+    /// use cue_sheet::tracklist::Tracklist;
+    ///
+    /// let tracklist = Tracklist::parse(
+    ///     "REM COMMENT \"ExactAudioCopy v1.6\"\nREM DATE 1991\n\
+    ///      FILE \"C:\\Users\\jane\\Music\\Loveless.wav\" WAVE",
+    /// )
+    /// .unwrap()
+    /// .anonymized();
+    ///
+    /// assert_eq!(tracklist.rem.len(), 1);
+    /// assert_eq!(tracklist.files[0].name, "Loveless.wav");
+    /// ```
+    pub fn anonymized(&self) -> Tracklist {
+        let mut tracklist = self.clone();
+        anonymize_rem(&mut tracklist.rem);
+        anonymize_files(&mut tracklist.files);
+        tracklist
+    }
+}
+
+impl CueSheet {
+    /// Returns a copy of the cue sheet with personal information removed, see the `anonymize`
+    /// module.
+    pub fn anonymized(&self) -> CueSheet {
+        let mut sheet = self.clone();
+        anonymize_rem(&mut sheet.rem);
+        anonymize_files(&mut sheet.files);
+        sheet.cdtextfile = sheet.cdtextfile.as_ref().map(|c| normalize_file_name(c));
+        // Replaced values aren't written anyway, but e.g. a replaced `CDTEXTFILE` has a path.
+        sheet.superseded.clear();
+        sheet
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn anonymized() {
+        let src = r#"REM COMMENT "CUERipper v2.1.6"
+                     REM GENRE Shoegaze
+                     CDTEXTFILE "/home/jane/cdtext.cdt"
+                     CDTEXTFILE "/home/jane/other.cdt"
+                     FILE "/home/jane/rips/a.wav" WAVE
+                       TRACK 01 AUDIO
+                         TITLE "Only Shallow"
+                         REM COMMENT "ripped by jane"
+                         REM COMPOSER "Kevin Shields"
+                         INDEX 01 00:00:00"#;
+        let sheet = CueSheet::parse(src).unwrap();
+        let anonymized = sheet.anonymized();
+
+        assert_eq!(anonymized.cdtextfile, Some("other.cdt".to_string()));
+        assert_eq!(sheet.superseded.len(), 1);
+        assert!(anonymized.superseded.is_empty());
+        assert_eq!(anonymized.rem, vec![sheet.rem[1].clone()]);
+        let track = &anonymized.files[0].tracks[0];
+        assert_eq!(track.rem, vec![sheet.files[0].tracks[0].rem[1].clone()]);
+        assert_eq!(track.index, sheet.files[0].tracks[0].index);
+        assert_eq!(track.title, sheet.files[0].tracks[0].title);
+        assert!(!anonymized.to_cue_string().contains("jane"));
+    }
+}
@@ -0,0 +1,64 @@
+// cue_sheet
+// Copyright (C) 2017  Leonardo Schwarz <mail@leoschwarz.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Parsing of many cue sheets at once.
+//!
+//! With the `rayon` feature enabled the files are read and parsed in parallel.
+
+use errors::Error;
+use std::path::PathBuf;
+use tracklist::Tracklist;
+
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+
+/// Read and parse all files in `paths`, detecting their encoding.
+///
+/// The results are returned in the same order as `paths`.
+pub fn parse_paths(paths: &[PathBuf]) -> Vec<(PathBuf, Result<Tracklist, Error>)> {
+    #[cfg(feature = "rayon")]
+    let iter = paths.par_iter();
+    #[cfg(not(feature = "rayon"))]
+    let iter = paths.iter();
+
+    iter.map(|path| (path.clone(), Tracklist::from_path(path)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs};
+
+    #[test]
+    fn parse_paths() {
+        let dir = env::temp_dir().join(format!("cue_sheet_batch_{}", ::std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+
+        let good = dir.join("good.cue");
+        fs::write(&good, b"TITLE \"Caf\xe9\"\nFILE \"a.wav\" WAVE\n").unwrap();
+        let missing = dir.join("missing.cue");
+
+        let results = super::parse_paths(&[good.clone(), missing.clone()]);
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].0, good);
+        let tracklist = results[0].1.as_ref().unwrap();
+        assert_eq!(tracklist.title, Some("Café".to_string()));
+        assert_eq!(results[1].0, missing);
+        assert!(results[1].1.is_err());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
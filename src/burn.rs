@@ -0,0 +1,475 @@
+// cue_sheet
+// Copyright (C) 2017  Leonardo Schwarz <mail@leoschwarz.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Preparation of burning a tracklist with cdrdao.
+//!
+//! `cdrdao_toc` describes the disc in the TOC format of cdrdao, after checking that the files
+//! can be found and that cdrdao can write the tracks. `cdrdao_command` returns the invocation
+//! writing such a TOC file to a disc.
+
+use errors::Error;
+use filesystem::FileSystem;
+use parser::{FileFormat, Time, TrackFlag, TrackType};
+use std::path::{Path, PathBuf};
+use tracklist::{Track, TrackFile, Tracklist};
+
+/// The name cdrdao uses for the mode of a track, `None` if it can't write such tracks.
+fn track_mode(track_type: &TrackType) -> Option<&'static str> {
+    match *track_type {
+        TrackType::Audio => Some("AUDIO"),
+        TrackType::Mode(1, 2048) => Some("MODE1"),
+        TrackType::Mode(1, 2352) => Some("MODE1_RAW"),
+        TrackType::Mode(2, 2048) => Some("MODE2_FORM1"),
+        TrackType::Mode(2, 2324) => Some("MODE2_FORM2"),
+        TrackType::Mode(2, 2336) => Some("MODE2"),
+        TrackType::Mode(2, 2352) => Some("MODE2_RAW"),
+        _ => None,
+    }
+}
+
+/// Extensions of compressed audio files, which cdrdao can't read even if they are declared as
+/// `WAVE`.
+const COMPRESSED_EXTENSIONS: [&str; 9] = [
+    "ape", "flac", "m4a", "mp3", "ogg", "opus", "tak", "tta", "wv",
+];
+
+/// Quote a string for a TOC file.
+fn quote(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// A track which cdrdao can write, see `check_track`.
+struct Burnable<'a> {
+    track: &'a Track,
+    mode: &'static str,
+    /// Where cdrdao starts reading the track, the start of its pregap if it has one.
+    begin: Time,
+    /// The start of the track itself (`INDEX 01`).
+    start: Time,
+}
+
+/// Check that cdrdao can write `track` from `file`.
+fn check_track<'a>(file: &TrackFile, track: &'a Track) -> Result<Burnable<'a>, Error> {
+    let mode = match track_mode(&track.track_type) {
+        Some(mode) => mode,
+        None => {
+            return Err(format!(
+                "Track {:02} has type {}, which can't be burned.",
+                track.number, track.track_type
+            )
+            .into())
+        }
+    };
+    let compressed = Path::new(&file.name)
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| {
+            COMPRESSED_EXTENSIONS.contains(&extension.to_lowercase().as_str())
+        });
+    let burnable = match file.format {
+        FileFormat::Binary => true,
+        FileFormat::Wave => track.track_type.is_audio() && !compressed,
+        FileFormat::Motorola => track.track_type.is_audio(),
+        _ => false,
+    };
+    if !burnable {
+        return Err(format!(
+            "Track {:02} can't be burned from {} file {:?}, it has to be decoded first.",
+            track.number, file.format, file.name
+        )
+        .into());
+    }
+    let start = track
+        .start()
+        .ok_or_else(|| format!("Track {:02} has no index.", track.number))?;
+    Ok(Burnable {
+        track,
+        mode,
+        begin: track.index00().unwrap_or_else(|| start.clone()),
+        start,
+    })
+}
+
+/// Append a `LANGUAGE` block of CD-Text with the given `(keyword, value)` pairs to `out`.
+fn push_language(out: &mut String, texts: &[(&str, &Option<String>)]) {
+    out.push_str("  LANGUAGE 0 {\n");
+    for &(keyword, value) in texts {
+        if let Some(ref value) = *value {
+            out.push_str(&format!("    {} {}\n", keyword, quote(value)));
+        }
+    }
+    out.push_str("  }\n");
+}
+
+/// Describe `tracklist` as a TOC file for cdrdao.
+///
+/// The files are looked up relative to `base` in `fs` (see `TrackFile::resolve`), and referred
+/// to by the paths they were found at. An error is returned if a file can't be found, or if a
+/// track can't be burned as it is: only `WAVE`, `MOTOROLA` and `BINARY` files can be written
+/// directly, compressed audio has to be decoded first (even if it is declared as `WAVE`), and
+/// `CDG` and `CDI` tracks are not supported by cdrdao. Compressed audio is recognized by the
+/// extension of the file, and with the `audio-probe` feature also by its header.
+///
+/// Audio in `BINARY` files is little endian, so it is marked with `SWAP` for cdrdao. Tracks of
+/// `BINARY` files are located by their byte offsets from `TrackFile::layout`, which accounts
+/// for the sector sizes of the preceding tracks.
+///
+/// Titles, performers and songwriters are written as English CD-Text.
+///
+/// ```
+/// use cue_sheet::burn::cdrdao_toc;
+/// use cue_sheet::filesystem::MemoryFileSystem;
+/// use cue_sheet::tracklist::Tracklist;
+/// use std::path::Path;
+///
+/// let tracklist = Tracklist::parse(
+///     "FILE a.wav WAVE\nTRACK 01 AUDIO\nINDEX 01 00:00:00\nTRACK 02 AUDIO\nINDEX 01 04:17:52",
+/// )
+/// .unwrap();
+/// let mut fs = MemoryFileSystem::new();
+/// assert!(cdrdao_toc(&tracklist, "rip", &fs).is_err());
+///
+/// fs.insert(Path::new("rip").join("a.wav"), Vec::new());
+/// let toc = cdrdao_toc(&tracklist, "rip", &fs).unwrap();
+/// assert!(toc.starts_with("CD_DA\n"));
+/// assert!(toc.contains("AUDIOFILE \"rip/a.wav\" 00:00:00 04:17:52\n"));
+/// ```
+pub fn cdrdao_toc<P: AsRef<Path>, F: FileSystem + ?Sized>(
+    tracklist: &Tracklist,
+    base: P,
+    fs: &F,
+) -> Result<String, Error> {
+    let tracks = tracklist
+        .files
+        .iter()
+        .map(|file| file.tracks.iter().map(|t| check_track(file, t)).collect())
+        .collect::<Result<Vec<Vec<Burnable>>, Error>>()?;
+    let paths = resolve_files(tracklist, base, fs)?;
+    #[cfg(feature = "audio-probe")]
+    for (file, path) in tracklist.files.iter().zip(&paths) {
+        if file.format == FileFormat::Wave && !::probe::is_uncompressed_in(path, fs)? {
+            return Err(format!(
+                "File {:?} isn't an uncompressed WAVE file, it has to be decoded first.",
+                file.name
+            )
+            .into());
+        }
+    }
+
+    let modes: Vec<&str> = tracks.iter().flatten().map(|t| t.mode).collect();
+    let disc_type = if modes.iter().any(|mode| mode.starts_with("MODE2")) {
+        "CD_ROM_XA"
+    } else if modes.iter().any(|mode| mode.starts_with("MODE1")) {
+        "CD_ROM"
+    } else {
+        "CD_DA"
+    };
+    let has_text = tracklist.title.is_some()
+        || tracklist.performer.is_some()
+        || tracklist
+            .files
+            .iter()
+            .flat_map(|f| &f.tracks)
+            .any(|t| t.title.is_some() || t.performer.is_some() || t.songwriter.is_some());
+
+    let mut out = format!("{}\n", disc_type);
+    if has_text {
+        out.push_str("\nCD_TEXT {\n  LANGUAGE_MAP {\n    0 : EN\n  }\n");
+        push_language(
+            &mut out,
+            &[
+                ("TITLE", &tracklist.title),
+                ("PERFORMER", &tracklist.performer),
+            ],
+        );
+        out.push_str("}\n");
+    }
+
+    for ((file, path), tracks) in tracklist.files.iter().zip(&paths).zip(&tracks) {
+        let layout = file.layout();
+        for (i, track) in tracks.iter().enumerate() {
+            let offset = layout.get(i).map(|l| l.offset);
+            let next = tracks.get(i + 1).map(|next| &next.begin);
+            push_track(&mut out, path, file, track, next, offset)?;
+        }
+    }
+
+    Ok(out)
+}
+
+/// Append the description of `burnable`, a track of `file`, to `out`. `next` is where cdrdao
+/// starts reading the next track of the file, `offset` the position of the track in bytes
+/// according to `TrackFile::layout` if known.
+fn push_track(
+    out: &mut String,
+    path: &Path,
+    file: &TrackFile,
+    burnable: &Burnable,
+    next: Option<&Time>,
+    offset: Option<u64>,
+) -> Result<(), Error> {
+    let (track, mode) = (burnable.track, burnable.mode);
+    out.push_str(&format!("\n// Track {:02}\nTRACK {}\n", track.number, mode));
+    for flag in &track.flags {
+        match *flag {
+            TrackFlag::Dcp => out.push_str("COPY\n"),
+            TrackFlag::FourChannel => out.push_str("FOUR_CHANNEL_AUDIO\n"),
+            TrackFlag::Pre => out.push_str("PRE_EMPHASIS\n"),
+            TrackFlag::Scms => {}
+        }
+    }
+    if let Some(ref isrc) = track.isrc {
+        out.push_str(&format!("ISRC {}\n", quote(isrc)));
+    }
+    let texts = [
+        ("TITLE", &track.title),
+        ("PERFORMER", &track.performer),
+        ("SONGWRITER", &track.songwriter),
+    ];
+    if texts.iter().any(|&(_, value)| value.is_some()) {
+        out.push_str("CD_TEXT {\n");
+        push_language(out, &texts);
+        out.push_str("}\n");
+    }
+
+    // A pregap which isn't part of the file is generated as silence.
+    if let Some(ref pregap) = track.pregap {
+        out.push_str(&format!("PREGAP {}\n", pregap));
+    }
+
+    // The track is read from the start of its pregap up to the pregap of the next track.
+    let begin = &burnable.begin;
+    let length = next
+        .map(|next| difference(track, begin, next))
+        .transpose()?;
+    let path = quote(&path.display().to_string());
+    let sector_size = u64::from(track.sector_size());
+    let offset = offset.unwrap_or(begin.total_frames().max(0) as u64 * sector_size);
+    if !track.track_type.is_audio() {
+        out.push_str(&format!("DATAFILE {} #{}", path, offset));
+    } else if file.format == FileFormat::Binary {
+        // Preceding data tracks might have smaller sectors, so the track is located by its
+        // byte offset rather than its time.
+        out.push_str(&format!("AUDIOFILE {} SWAP", path));
+        if offset == begin.total_frames().max(0) as u64 * sector_size {
+            out.push_str(&format!(" {}", begin));
+        } else {
+            out.push_str(&format!(" #{} 00:00:00", offset));
+        }
+    } else {
+        out.push_str(&format!("AUDIOFILE {} {}", path, begin));
+    }
+    match length {
+        Some(length) => out.push_str(&format!(" {}\n", length)),
+        None => out.push('\n'),
+    }
+
+    let track_start = &burnable.start;
+    if track_start != begin {
+        let pregap = difference(track, begin, track_start)?;
+        out.push_str(&format!("START {}\n", pregap));
+    }
+    for &(number, ref time) in &track.index {
+        if number > 1 {
+            let offset = difference(track, track_start, time)?;
+            out.push_str(&format!("INDEX {}\n", offset));
+        }
+    }
+    if let Some(ref postgap) = track.postgap {
+        out.push_str(&format!("SILENCE {}\n", postgap));
+    }
+    Ok(())
+}
+
+/// Returns the time from `from` to `to` within `track`, an error if `to` is before `from`.
+fn difference(track: &Track, from: &Time, to: &Time) -> Result<Time, Error> {
+    let frames = to.total_frames() - from.total_frames();
+    if frames < 0 {
+        return Err(format!(
+            "Track {:02} ends before it starts, its times are out of order.",
+            track.number
+        )
+        .into());
+    }
+    Time::checked_from_frames(frames)
+        .ok_or_else(|| format!("The times of track {:02} are out of range.", track.number).into())
+}
+
+/// Returns the command line writing the TOC file at `toc` with cdrdao.
+///
+/// Without a `device` cdrdao picks the first drive it finds.
+///
+/// ```
+/// use cue_sheet::burn::cdrdao_command;
+/// use std::path::Path;
+///
+/// assert_eq!(
+///     cdrdao_command(Path::new("disc.toc"), Some("/dev/sr0")),
+///     vec!["cdrdao", "write", "--eject", "--device", "/dev/sr0", "disc.toc"]
+/// );
+/// ```
+pub fn cdrdao_command(toc: &Path, device: Option<&str>) -> Vec<String> {
+    let mut args = vec![
+        "cdrdao".to_string(),
+        "write".to_string(),
+        "--eject".to_string(),
+    ];
+    if let Some(device) = device {
+        args.push("--device".to_string());
+        args.push(device.to_string());
+    }
+    args.push(toc.display().to_string());
+    args
+}
+
+/// Returns the paths `cdrdao_toc` would refer to, or an error naming a file which can't be
+/// found.
+pub fn resolve_files<P: AsRef<Path>, F: FileSystem + ?Sized>(
+    tracklist: &Tracklist,
+    base: P,
+    fs: &F,
+) -> Result<Vec<PathBuf>, Error> {
+    tracklist
+        .files
+        .iter()
+        .map(|file| {
+            file.resolve(base.as_ref(), fs)
+                .ok_or_else(|| format!("File {:?} not found.", file.name).into())
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use filesystem::MemoryFileSystem;
+
+    #[test]
+    fn toc() {
+        let tracklist = Tracklist::parse(
+            "TITLE Loveless\nFILE a.wav WAVE\nTRACK 01 AUDIO\nTITLE \"Only Shallow\"\n\
+             FLAGS DCP PRE\nINDEX 01 00:00:00\nTRACK 02 AUDIO\nINDEX 00 04:15:00\n\
+             INDEX 01 04:17:52\nINDEX 02 05:00:00\nPOSTGAP 00:02:00",
+        )
+        .unwrap();
+        let mut fs = MemoryFileSystem::new();
+        fs.insert("a.wav", Vec::new());
+        let toc = cdrdao_toc(&tracklist, "", &fs).unwrap();
+        assert_eq!(
+            toc,
+            "CD_DA\n\nCD_TEXT {\n  LANGUAGE_MAP {\n    0 : EN\n  }\n  LANGUAGE 0 {\n    \
+             TITLE \"Loveless\"\n  }\n}\n\n\
+             // Track 01\nTRACK AUDIO\nCOPY\nPRE_EMPHASIS\nCD_TEXT {\n  LANGUAGE 0 {\n    \
+             TITLE \"Only Shallow\"\n  }\n}\nAUDIOFILE \"a.wav\" 00:00:00 04:15:00\n\n\
+             // Track 02\nTRACK AUDIO\nAUDIOFILE \"a.wav\" 04:15:00\nSTART 00:02:52\nINDEX 00:42:23\nSILENCE 00:02:00\n"
+        );
+    }
+
+    #[test]
+    fn generated_pregap() {
+        let tracklist = Tracklist::parse(
+            "FILE a.wav WAVE\nTRACK 01 AUDIO\nINDEX 01 00:00:00\nTRACK 02 AUDIO\n\
+             PREGAP 00:02:00\nINDEX 01 03:12:40",
+        )
+        .unwrap();
+        let mut fs = MemoryFileSystem::new();
+        fs.insert("a.wav", Vec::new());
+        let toc = cdrdao_toc(&tracklist, "", &fs).unwrap();
+        assert!(toc.contains("AUDIOFILE \"a.wav\" 00:00:00 03:12:40\n"));
+        assert!(toc.ends_with("TRACK AUDIO\nPREGAP 00:02:00\nAUDIOFILE \"a.wav\" 03:12:40\n"));
+    }
+
+    #[test]
+    fn data_track() {
+        let tracklist =
+            Tracklist::parse("FILE a.bin BINARY\nTRACK 01 MODE1/2352\nINDEX 01 00:00:00").unwrap();
+        let mut fs = MemoryFileSystem::new();
+        fs.insert("a.bin", Vec::new());
+        let toc = cdrdao_toc(&tracklist, "", &fs).unwrap();
+        assert!(toc.starts_with("CD_ROM\n"));
+        assert!(toc.contains("TRACK MODE1_RAW\nDATAFILE \"a.bin\" #0\n"));
+    }
+
+    #[test]
+    fn mixed_mode() {
+        let tracklist = Tracklist::parse(
+            "FILE game.bin BINARY\nTRACK 01 MODE1/2048\nINDEX 01 00:00:00\n\
+             TRACK 02 AUDIO\nINDEX 00 00:10:00\nINDEX 01 00:12:00\n\
+             TRACK 03 AUDIO\nINDEX 01 00:20:00",
+        )
+        .unwrap();
+        let mut fs = MemoryFileSystem::new();
+        fs.insert("game.bin", Vec::new());
+        let toc = cdrdao_toc(&tracklist, "", &fs).unwrap();
+        let offset = 750 * 2048;
+        assert!(toc.contains("TRACK MODE1\nDATAFILE \"game.bin\" #0 00:10:00\n"));
+        assert!(toc.contains(&format!(
+            "TRACK AUDIO\nAUDIOFILE \"game.bin\" SWAP #{} 00:00:00 00:10:00\nSTART 00:02:00\n",
+            offset
+        )));
+        assert!(toc.contains(&format!(
+            "TRACK AUDIO\nAUDIOFILE \"game.bin\" SWAP #{} 00:00:00\n",
+            offset + 750 * 2352
+        )));
+
+        let audio = Tracklist::parse(
+            "FILE a.bin BINARY\nTRACK 01 AUDIO\nINDEX 01 00:00:00\n\
+             TRACK 02 AUDIO\nINDEX 01 04:17:52",
+        )
+        .unwrap();
+        fs.insert("a.bin", Vec::new());
+        let toc = cdrdao_toc(&audio, "", &fs).unwrap();
+        assert!(toc.contains("AUDIOFILE \"a.bin\" SWAP 00:00:00 04:17:52\n"));
+        assert!(toc.ends_with("AUDIOFILE \"a.bin\" SWAP 04:17:52\n"));
+    }
+
+    #[test]
+    fn not_burnable() {
+        let mut fs = MemoryFileSystem::new();
+        fs.insert("a.flac", Vec::new());
+        fs.insert("a.bin", Vec::new());
+        let flac = Tracklist::parse("FILE a.flac WAVE\nTRACK 01 AUDIO\nINDEX 01 00:00:00");
+        assert!(cdrdao_toc(&flac.unwrap(), "", &fs).is_err());
+        let mp3 = Tracklist::parse("FILE a.bin MP3\nTRACK 01 AUDIO\nINDEX 01 00:00:00");
+        assert!(cdrdao_toc(&mp3.unwrap(), "", &fs).is_err());
+        let cdg = Tracklist::parse("FILE a.bin BINARY\nTRACK 01 CDG\nINDEX 01 00:00:00");
+        assert!(cdrdao_toc(&cdg.unwrap(), "", &fs).is_err());
+        let data = Tracklist::parse("FILE a.flac WAVE\nTRACK 01 MODE1/2048\nINDEX 01 00:00:00");
+        assert!(cdrdao_toc(&data.unwrap(), "", &fs).is_err());
+
+        let mut reordered = Tracklist::parse(
+            "FILE a.bin BINARY\nTRACK 01 AUDIO\nINDEX 01 00:00:00\nTRACK 02 AUDIO\nINDEX 01 04:17:52",
+        )
+        .unwrap();
+        reordered.files[0].tracks.swap(0, 1);
+        assert!(cdrdao_toc(&reordered, "", &fs).is_err());
+
+        let missing = Tracklist::parse("FILE d.wav WAVE").unwrap();
+        assert!(resolve_files(&missing, "", &fs).is_err());
+    }
+
+    #[cfg(feature = "audio-probe")]
+    #[test]
+    fn compressed_wave() {
+        let mut fs = MemoryFileSystem::new();
+        fs.insert("b.wav", b"fLaC\0\0\0\x22".to_vec());
+        fs.insert("c.wav", b"RIFF\0\0\0\0WAVE".to_vec());
+        let renamed = Tracklist::parse("FILE b.wav WAVE\nTRACK 01 AUDIO\nINDEX 01 00:00:00");
+        assert!(cdrdao_toc(&renamed.unwrap(), "", &fs).is_err());
+        let wave = Tracklist::parse("FILE c.wav WAVE\nTRACK 01 AUDIO\nINDEX 01 00:00:00");
+        assert!(cdrdao_toc(&wave.unwrap(), "", &fs).is_ok());
+    }
+}
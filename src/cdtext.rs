@@ -0,0 +1,305 @@
+// cue_sheet
+// Copyright (C) 2017  Leonardo Schwarz <mail@leoschwarz.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Generation of CD-Text from the metadata of a tracklist.
+//!
+//! CD-Text is stored in the lead-in of a disc as packs of 18 bytes. Burning programs like
+//! cdrecord (`textfile=`) accept these packs in a binary file, see `cdt_file`. Only the titles,
+//! performers and songwriters are written, in a single block declared as English.
+
+use errors::Error;
+use std::convert::TryFrom;
+use tracklist::Tracklist;
+
+/// Size of a pack including its CRC.
+pub const PACK_SIZE: usize = 18;
+
+/// Number of text bytes in a pack.
+const TEXT_SIZE: usize = 12;
+
+/// Pack type of the size information, which concludes the packs of a block.
+const SIZE_INFO: u8 = 0x8f;
+
+/// Language code of English, used for the only block.
+const LANGUAGE_ENGLISH: u8 = 0x09;
+
+/// Character set the text of CD-Text is encoded in.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum CharacterSet {
+    /// ISO-8859-1 (Latin-1), the usual choice for western languages.
+    #[default]
+    Iso8859_1,
+
+    /// 7-bit ASCII, for players which don't support anything else.
+    Ascii,
+}
+
+impl CharacterSet {
+    /// The value identifying the character set in the size information.
+    fn code(self) -> u8 {
+        match self {
+            CharacterSet::Iso8859_1 => 0x00,
+            CharacterSet::Ascii => 0x01,
+        }
+    }
+
+    /// Encode `s`, replacing characters which can't be represented with `?`.
+    ///
+    /// Null characters would terminate the string early and are replaced as well.
+    fn encode(self, s: &str) -> Vec<u8> {
+        let max = match self {
+            CharacterSet::Iso8859_1 => 0xff,
+            CharacterSet::Ascii => 0x7f,
+        };
+        s.chars()
+            .map(|c| match c as u32 {
+                0 => b'?',
+                n if n <= max => n as u8,
+                _ => b'?',
+            })
+            .collect()
+    }
+}
+
+/// Compute the CRC of the first 16 bytes of a pack (CRC-16/CCITT, stored inverted).
+fn crc(data: &[u8]) -> u16 {
+    let mut crc: u16 = 0;
+    for &byte in data {
+        crc ^= u16::from(byte) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 {
+                crc << 1 ^ 0x1021
+            } else {
+                crc << 1
+            };
+        }
+    }
+    !crc
+}
+
+/// Collects packs, numbering them consecutively.
+struct Packs {
+    packs: Vec<[u8; PACK_SIZE]>,
+}
+
+impl Packs {
+    fn push(&mut self, pack_type: u8, track: u8, position: u8, data: &[u8]) -> Result<(), Error> {
+        if self.packs.len() > usize::from(u8::MAX) {
+            return Err("The CD-Text exceeds the size of a block.".into());
+        }
+        let mut pack = [0; PACK_SIZE];
+        pack[0] = pack_type;
+        pack[1] = track;
+        pack[2] = self.packs.len() as u8;
+        pack[3] = position;
+        pack[4..4 + data.len()].copy_from_slice(data);
+        let crc = crc(&pack[..16]);
+        pack[16..].copy_from_slice(&crc.to_be_bytes());
+        self.packs.push(pack);
+        Ok(())
+    }
+
+    /// Add the packs of `pack_type` for `texts`, the texts of the disc (track 0) and of the
+    /// tracks labelled with their numbers.
+    fn push_texts(&mut self, pack_type: u8, texts: &[(u8, Vec<u8>)]) -> Result<(), Error> {
+        // Every byte is labelled with the track its text belongs to and its position in the text.
+        let mut bytes = Vec::new();
+        for &(track, ref text) in texts {
+            for (position, &byte) in text.iter().chain(&[0]).enumerate() {
+                bytes.push((byte, track, position));
+            }
+        }
+
+        for chunk in bytes.chunks(TEXT_SIZE) {
+            let (_, track, position) = chunk[0];
+            let data: Vec<u8> = chunk.iter().map(|&(byte, _, _)| byte).collect();
+            self.push(pack_type, track, position.min(15) as u8, &data)?;
+        }
+        Ok(())
+    }
+}
+
+/// Generate the CD-Text packs for `tracklist`.
+///
+/// Texts which are missing everywhere are left out, the size information is always added. An
+/// error is returned if the track numbers don't fit into a byte, or if there is more text than
+/// fits into a block of CD-Text.
+///
+/// ```
+/// use cue_sheet::cdtext::{packs, CharacterSet};
+/// use cue_sheet::tracklist::Tracklist;
+///
+/// let tracklist = Tracklist::parse(
+///     "TITLE Loveless\nFILE a.wav WAVE\nTRACK 01 AUDIO\nTITLE \"Only Shallow\"\n\
+///      INDEX 01 00:00:00",
+/// )
+/// .unwrap();
+/// let packs = packs(&tracklist, CharacterSet::Iso8859_1).unwrap();
+/// // Two packs of titles and three of size information.
+/// assert_eq!(packs.len(), 5);
+/// assert_eq!(&packs[0][4..16], b"Loveless\0Onl");
+/// ```
+pub fn packs(tracklist: &Tracklist, charset: CharacterSet) -> Result<Vec<[u8; PACK_SIZE]>, Error> {
+    let mut tracks: Vec<_> = tracklist.tracks().map(|(_, track)| track).collect();
+    tracks.sort_by_key(|track| track.number);
+    let numbers = tracks
+        .iter()
+        .map(|track| {
+            u8::try_from(track.number).map_err(|_| {
+                Error::from(format!(
+                    "Track {} can't be described by CD-Text.",
+                    track.number
+                ))
+            })
+        })
+        .collect::<Result<Vec<u8>, Error>>()?;
+    let first_track = numbers.first().cloned().unwrap_or(1);
+    let last_track = numbers.last().cloned().unwrap_or(1);
+
+    let encode = |s: &Option<String>| charset.encode(s.as_ref().map_or("", |s| s.as_str()));
+    let kinds = [
+        (
+            0x80,
+            encode(&tracklist.title),
+            tracks.iter().map(|t| encode(&t.title)).collect::<Vec<_>>(),
+        ),
+        (
+            0x81,
+            encode(&tracklist.performer),
+            tracks.iter().map(|t| encode(&t.performer)).collect(),
+        ),
+        (
+            0x82,
+            Vec::new(),
+            tracks.iter().map(|t| encode(&t.songwriter)).collect(),
+        ),
+    ];
+
+    let mut packs = Packs { packs: Vec::new() };
+    let mut counts = [0u8; 16];
+    for (pack_type, disc, track_texts) in kinds.iter() {
+        if disc.is_empty() && track_texts.iter().all(|t| t.is_empty()) {
+            continue;
+        }
+        let mut texts = vec![(0, disc.clone())];
+        texts.extend(numbers.iter().cloned().zip(track_texts.iter().cloned()));
+        let before = packs.packs.len();
+        packs.push_texts(*pack_type, &texts)?;
+        counts[usize::from(pack_type - 0x80)] = (packs.packs.len() - before) as u8;
+    }
+
+    counts[usize::from(SIZE_INFO - 0x80)] = 3;
+    let mut size_info = vec![charset.code(), first_track, last_track, 0];
+    size_info.extend_from_slice(&counts);
+    let mut last_sequence = [0u8; 8];
+    last_sequence[0] = (packs.packs.len() + 2) as u8;
+    size_info.extend_from_slice(&last_sequence);
+    let mut languages = [0u8; 8];
+    languages[0] = LANGUAGE_ENGLISH;
+    size_info.extend_from_slice(&languages);
+    for (i, data) in size_info.chunks(TEXT_SIZE).enumerate() {
+        packs.push(SIZE_INFO, i as u8, 0, data)?;
+    }
+
+    Ok(packs.packs)
+}
+
+/// Generate a CD-Text file for `tracklist`, as accepted by `cdrecord textfile=`.
+///
+/// The file consists of a header with the size of the data followed by the packs.
+pub fn cdt_file(tracklist: &Tracklist, charset: CharacterSet) -> Result<Vec<u8>, Error> {
+    let packs = packs(tracklist, charset)?;
+    let size = (packs.len() * PACK_SIZE + 2) as u16;
+    let mut file = size.to_be_bytes().to_vec();
+    file.extend_from_slice(&[0, 0]);
+    for pack in &packs {
+        file.extend_from_slice(pack);
+    }
+    Ok(file)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn crc_check() {
+        assert_eq!(crc(b"123456789"), !0x31c3);
+    }
+
+    #[test]
+    fn track_packs() {
+        let tracklist = Tracklist::parse(
+            "PERFORMER \"My Bloody Valentine\"\nTITLE Loveless\nFILE a.wav WAVE\n\
+             TRACK 01 AUDIO\nTITLE \"Only Shallow\"\nINDEX 01 00:00:00\n\
+             TRACK 02 AUDIO\nTITLE \"Loomer\"\nSONGWRITER \"Kevin Shields\"\nINDEX 01 04:17:52",
+        )
+        .unwrap();
+        let packs = packs(&tracklist, CharacterSet::Ascii).unwrap();
+
+        // "Loveless\0Only Shallow\0Loomer\0" takes three packs.
+        assert_eq!(packs[1][0], 0x80);
+        assert_eq!(packs[1][1], 1);
+        assert_eq!(packs[1][3], 3);
+        assert_eq!(&packs[1][4..16], b"y Shallow\0Lo");
+        assert_eq!(packs[2][1], 2);
+        assert_eq!(packs[2][3], 2);
+        assert_eq!(packs[3][0], 0x81);
+        assert!(packs.iter().any(|p| p[0] == 0x82));
+        for (i, pack) in packs.iter().enumerate() {
+            assert_eq!(usize::from(pack[2]), i);
+            assert_eq!(u16::from_be_bytes([pack[16], pack[17]]), crc(&pack[..16]));
+        }
+
+        let size_info: Vec<u8> = packs[packs.len() - 3..]
+            .iter()
+            .flat_map(|p| p[4..16].to_vec())
+            .collect();
+        assert_eq!(&size_info[..4], &[0x01, 1, 2, 0]);
+        assert_eq!(size_info[4], 3);
+        assert_eq!(size_info[19], 3);
+        assert_eq!(usize::from(size_info[20]), packs.len() - 1);
+        assert_eq!(size_info[28], LANGUAGE_ENGLISH);
+
+        let file = cdt_file(&tracklist, CharacterSet::Ascii).unwrap();
+        assert_eq!(file.len(), 4 + packs.len() * PACK_SIZE);
+        assert_eq!(&file[4..4 + PACK_SIZE], &packs[0][..]);
+    }
+
+    #[test]
+    fn track_numbers_with_gaps() {
+        let tracklist = Tracklist::parse(
+            "FILE a.wav WAVE\nTRACK 02 AUDIO\nTITLE \"Loomer\"\nINDEX 01 00:00:00\n\
+             TRACK 05 AUDIO\nTITLE \"Sometimes\"\nINDEX 01 04:17:52",
+        )
+        .unwrap();
+        let packs = packs(&tracklist, CharacterSet::Ascii).unwrap();
+        // "\0Loomer\0Sometimes\0" starts with the empty disc title.
+        assert_eq!(packs[0][1], 0);
+        assert_eq!(packs[1][1], 5);
+        assert_eq!(packs[1][3], 4);
+        assert_eq!(&packs[packs.len() - 3][5..7], &[2, 5]);
+    }
+
+    #[test]
+    fn character_sets() {
+        assert_eq!(
+            CharacterSet::Iso8859_1.encode("Café €"),
+            b"Caf\xe9 ?".to_vec()
+        );
+        assert_eq!(CharacterSet::Ascii.encode("Café"), b"Caf?".to_vec());
+    }
+}
@@ -0,0 +1,117 @@
+// cue_sheet
+// Copyright (C) 2017  Leonardo Schwarz <mail@leoschwarz.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Cleanup of metadata strings.
+//!
+//! Cue sheets from old rips frequently contain titles with trailing spaces, control characters or
+//! decomposed Unicode. The options here can be used to tidy them up.
+
+#[cfg(feature = "unicode-normalization")]
+use unicode_normalization::UnicodeNormalization;
+
+/// Sanitization applied to metadata strings (titles, performers, ...).
+///
+/// The default value leaves all strings untouched.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct MetadataCleanup {
+    /// Remove leading and trailing whitespace.
+    pub trim: bool,
+
+    /// Remove control characters.
+    pub strip_control: bool,
+
+    /// Normalize into Unicode normalization form C.
+    ///
+    /// This requires the `unicode-normalization` feature, without it the option is ignored.
+    pub nfc: bool,
+}
+
+/// Metadata strings of a tracklist as they were read, before a `MetadataCleanup` was applied,
+/// see `Tracklist::raw_metadata`.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct RawMetadata {
+    /// Title of the tracklist.
+    pub title: Option<String>,
+
+    /// Performer of the tracklist.
+    pub performer: Option<String>,
+
+    /// Metadata of the tracks, in the order they appeared.
+    pub tracks: Vec<RawTrackMetadata>,
+}
+
+/// Metadata strings of a track as they were read, see `RawMetadata`.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct RawTrackMetadata {
+    /// Number of the track.
+    pub number: u32,
+
+    /// Title of the track.
+    pub title: Option<String>,
+
+    /// Performer of the track.
+    pub performer: Option<String>,
+
+    /// Songwriter of the track.
+    pub songwriter: Option<String>,
+}
+
+impl MetadataCleanup {
+    /// Cleanup which enables all available options.
+    pub fn all() -> MetadataCleanup {
+        MetadataCleanup {
+            trim: true,
+            strip_control: true,
+            nfc: true,
+        }
+    }
+
+    /// Apply the cleanup to a single string.
+    ///
+    /// ```
+    /// use cue_sheet::cleanup::MetadataCleanup;
+    ///
+    /// let cleanup = MetadataCleanup::all();
+    /// assert_eq!(cleanup.apply(" Loveless\u{7} "), "Loveless");
+    /// ```
+    pub fn apply(&self, s: &str) -> String {
+        let mut result: String = if self.strip_control {
+            s.chars().filter(|c| !c.is_control()).collect()
+        } else {
+            s.to_string()
+        };
+
+        #[cfg(feature = "unicode-normalization")]
+        {
+            if self.nfc {
+                result = result.nfc().collect();
+            }
+        }
+
+        if self.trim {
+            result = result.trim().to_string();
+        }
+
+        result
+    }
+
+    /// Apply the cleanup to an optional string.
+    pub(crate) fn apply_opt(&self, s: &mut Option<String>) {
+        if let Some(ref mut s) = *s {
+            *s = self.apply(s);
+        }
+    }
+}
@@ -0,0 +1,149 @@
+// cue_sheet
+// Copyright (C) 2017  Leonardo Schwarz <mail@leoschwarz.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Computation of the TOC id used by the CUETools database (CTDB).
+//!
+//! CTDB stores checksums and recovery data of rips, which allows verifying a rip similar to
+//! AccurateRip. Discs are identified by a hash of the positions of their audio tracks relative
+//! to the start of the first track.
+
+use errors::Error;
+use musicbrainz::track_offsets;
+use parser::{Time, TrackType};
+use sha1_smol::Sha1;
+use tracklist::Tracklist;
+
+/// Number of track positions (including the lead-out) hashed into the TOC id.
+const TOC_ENTRIES: usize = 100;
+
+/// Compute the CTDB TOC id of `tracklist`.
+///
+/// `leadout` is the end of the last audio track relative to the start of the first track, i.e.
+/// the total length of the audio. Data tracks are not part of the id. Like for
+/// `musicbrainz::toc_string` the positions of all tracks have to be known.
+///
+/// ```
+/// use cue_sheet::ctdb::toc_id;
+/// use cue_sheet::parser::Time;
+/// use cue_sheet::tracklist::Tracklist;
+///
+/// let tracklist = Tracklist::parse(
+///     "FILE a.wav WAVE\nTRACK 01 AUDIO\nINDEX 01 00:00:00\nTRACK 02 AUDIO\nINDEX 01 04:17:52",
+/// )
+/// .unwrap();
+/// let id = toc_id(&tracklist, Time::new(10, 0, 0)).unwrap();
+/// assert_eq!(id.len(), 28);
+/// ```
+pub fn toc_id(tracklist: &Tracklist, leadout: Time) -> Result<String, Error> {
+    let types = tracklist.tracks().map(|(_, t)| &t.track_type);
+    let offsets: Vec<i64> = track_offsets(tracklist)?
+        .into_iter()
+        .zip(types)
+        .filter(|&(_, track_type)| *track_type == TrackType::Audio)
+        .map(|(offset, _)| offset)
+        .collect();
+    let first = *offsets
+        .first()
+        .ok_or("The tracklist doesn't contain any audio tracks.")?;
+    if offsets.len() >= TOC_ENTRIES {
+        return Err("The tracklist contains too many audio tracks.".into());
+    }
+
+    let mut toc = String::with_capacity(TOC_ENTRIES * 8);
+    for offset in &offsets[1..] {
+        toc.push_str(&format!("{:08X}", offset - first));
+    }
+    toc.push_str(&format!("{:08X}", leadout.total_frames()));
+    for _ in offsets.len()..TOC_ENTRIES {
+        toc.push_str("00000000");
+    }
+
+    let hash = Sha1::from(toc.as_bytes()).digest().bytes();
+    Ok(base64(&hash)
+        .replace('+', ".")
+        .replace('/', "_")
+        .replace('=', "-"))
+}
+
+/// Encode `bytes` as base64 (with padding).
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    let mut s = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, &b)| n | u32::from(b) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                s.push(ALPHABET[(n >> (18 - 6 * i) & 63) as usize] as char);
+            } else {
+                s.push('=');
+            }
+        }
+    }
+    s
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn base64_padding() {
+        assert_eq!(base64(b"f"), "Zg==");
+        assert_eq!(base64(b"fo"), "Zm8=");
+        assert_eq!(base64(b"foo"), "Zm9v");
+        assert_eq!(base64(b"foobar"), "Zm9vYmFy");
+    }
+
+    #[test]
+    fn known_toc() {
+        // The disc used as example by libdiscid, with lead-out at sector 95462.
+        let tracklist = Tracklist::parse(
+            "FILE a.wav WAVE\nTRACK 01 AUDIO\nINDEX 01 00:00:00\nTRACK 02 AUDIO\n\
+             INDEX 01 03:22:63\nTRACK 03 AUDIO\nINDEX 01 07:08:64\nTRACK 04 AUDIO\n\
+             INDEX 01 10:19:17\nTRACK 05 AUDIO\nINDEX 01 14:03:39\nTRACK 06 AUDIO\n\
+             INDEX 01 17:51:14",
+        )
+        .unwrap();
+        assert_eq!(
+            toc_id(&tracklist, Time::new(21, 10, 62)).unwrap(),
+            "iCHDkr.7dpqDbPy3ehdjqp8oRT0-"
+        );
+    }
+
+    #[test]
+    fn data_tracks() {
+        let audio = Tracklist::parse(
+            "FILE a.wav WAVE\nTRACK 01 AUDIO\nINDEX 01 00:00:00\nTRACK 02 AUDIO\nINDEX 01 04:17:52",
+        )
+        .unwrap();
+        let enhanced = Tracklist::parse(
+            "FILE a.bin BINARY\nTRACK 01 AUDIO\nINDEX 01 00:00:00\nTRACK 02 AUDIO\n\
+             INDEX 01 04:17:52\nTRACK 03 MODE1/2352\nINDEX 01 12:00:00",
+        )
+        .unwrap();
+        let leadout = Time::new(10, 0, 0);
+        let id = toc_id(&audio, leadout.clone()).unwrap();
+        assert_eq!(toc_id(&enhanced, leadout.clone()).unwrap(), id);
+        assert_ne!(toc_id(&audio, Time::new(10, 0, 1)).unwrap(), id);
+        assert!(id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "._-".contains(c)));
+    }
+}
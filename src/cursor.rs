@@ -0,0 +1,167 @@
+// cue_sheet
+// Copyright (C) 2017  Leonardo Schwarz <mail@leoschwarz.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Following the playback position of an audio player through a tracklist.
+
+use errors::Error;
+use parser::Time;
+use std::ptr;
+use tracklist::{Track, TrackFile, Tracklist};
+
+/// A change of the current track, returned by `CueCursor::advance`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CursorEvent<'a> {
+    /// Playback entered `track` of `file`, by reaching its start or by seeking into it.
+    Track(&'a TrackFile, &'a Track),
+
+    /// Playback left all tracks, e.g. after the end of the last track.
+    Outside,
+}
+
+/// Follows a playback position through a tracklist, reporting when the current track changes.
+///
+/// Positions are relative to the start of the first file, see `Tracklist::track_at`. They don't
+/// have to increase, seeking backwards is reported the same way.
+///
+/// ```
+/// use cue_sheet::cursor::{CueCursor, CursorEvent};
+/// use cue_sheet::tracklist::Tracklist;
+///
+/// let tracklist = Tracklist::parse(
+///     "FILE a.wav WAVE\nTRACK 01 AUDIO\nINDEX 01 00:00:00\nTRACK 02 AUDIO\nINDEX 01 00:01:00",
+/// )
+/// .unwrap();
+/// let mut cursor = CueCursor::new(&tracklist);
+///
+/// assert!(cursor.advance(0).is_some());
+/// assert_eq!(cursor.advance(10), None);
+/// match cursor.advance(75) {
+///     Some(CursorEvent::Track(_, track)) => assert_eq!(track.number, 2),
+///     event => panic!("unexpected event: {:?}", event),
+/// }
+/// assert_eq!(cursor.current().unwrap().number, 2);
+/// ```
+#[derive(Clone, Debug)]
+pub struct CueCursor<'a> {
+    tracklist: &'a Tracklist,
+    current: Option<(&'a TrackFile, &'a Track)>,
+    position: Option<i64>,
+}
+
+impl<'a> CueCursor<'a> {
+    /// Create a cursor for `tracklist`, not positioned yet.
+    pub fn new(tracklist: &'a Tracklist) -> CueCursor<'a> {
+        CueCursor {
+            tracklist,
+            current: None,
+            position: None,
+        }
+    }
+
+    /// Move to the position `frame` (in frames/sectors), returning an event if the current track
+    /// changed.
+    ///
+    /// The first call always returns an event, unless the position is outside of all tracks.
+    pub fn advance(&mut self, frame: i64) -> Option<CursorEvent<'a>> {
+        self.position = Some(frame);
+        let found = self.tracklist.track_at_frame(frame);
+        let changed = match (self.current, found) {
+            (Some((_, a)), Some((_, b))) => !ptr::eq(a, b),
+            (None, None) => false,
+            _ => true,
+        };
+        self.current = found;
+
+        if !changed {
+            None
+        } else if let Some((file, track)) = found {
+            Some(CursorEvent::Track(file, track))
+        } else {
+            Some(CursorEvent::Outside)
+        }
+    }
+
+    /// Move to the position `samples` at `sample_rate`, see `CueCursor::advance`.
+    ///
+    /// An error is returned if `sample_rate` is zero or the position is out of range.
+    pub fn advance_samples(
+        &mut self,
+        samples: i64,
+        sample_rate: u32,
+    ) -> Result<Option<CursorEvent<'a>>, Error> {
+        let time = Time::checked_from_samples(samples, sample_rate).ok_or_else(|| {
+            Error::from(format!(
+                "Position of {} samples at {} Hz is out of range.",
+                samples, sample_rate
+            ))
+        })?;
+        Ok(self.advance(time.total_frames()))
+    }
+
+    /// Returns the current track.
+    pub fn current(&self) -> Option<&'a Track> {
+        self.current.map(|(_, track)| track)
+    }
+
+    /// Returns the file containing the current track.
+    pub fn current_file(&self) -> Option<&'a TrackFile> {
+        self.current.map(|(file, _)| file)
+    }
+
+    /// Returns the position within the current track in frames, counted from its `INDEX 01`.
+    ///
+    /// The position is negative while the pregap of the track is playing.
+    pub fn position_in_track(&self) -> Option<i64> {
+        let track = self.current()?;
+        let start = track.start()?.total_frames() + track.file_offset()?;
+        Some(self.position? - start)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn events() {
+        let tracklist = Tracklist::parse(
+            "FILE a.wav WAVE\nTRACK 01 AUDIO\nINDEX 01 00:00:00\n\
+             TRACK 02 AUDIO\nINDEX 00 00:01:00\nINDEX 01 00:02:00",
+        )
+        .unwrap();
+        let mut cursor = CueCursor::new(&tracklist);
+        let number = |event: Option<CursorEvent>| match event {
+            Some(CursorEvent::Track(_, track)) => Some(track.number),
+            _ => None,
+        };
+
+        assert_eq!(number(cursor.advance_samples(0, 44100).unwrap()), Some(1));
+        assert_eq!(cursor.advance_samples(44099, 44100).unwrap(), None);
+        assert_eq!(
+            number(cursor.advance_samples(44100, 44100).unwrap()),
+            Some(2)
+        );
+        assert!(cursor.advance_samples(44100, 0).is_err());
+        assert_eq!(cursor.position_in_track(), Some(-75));
+        assert_eq!(cursor.current_file().unwrap().name, "a.wav");
+
+        // Seeking backwards.
+        assert_eq!(number(cursor.advance(10)), Some(1));
+        assert_eq!(cursor.advance(-1), Some(CursorEvent::Outside));
+        assert_eq!(cursor.current(), None);
+        assert_eq!(cursor.advance(-2), None);
+    }
+}
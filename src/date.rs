@@ -0,0 +1,115 @@
+// cue_sheet
+// Copyright (C) 2017  Leonardo Schwarz <mail@leoschwarz.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Release dates as found in `REM DATE` comments.
+
+use errors::Error;
+use std::fmt;
+use std::str::FromStr;
+
+/// A calendar date of which only the year is guaranteed to be known.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash, Ord, PartialOrd)]
+pub struct Date {
+    /// The year.
+    pub year: u16,
+
+    /// The month (1-12), if known.
+    pub month: Option<u8>,
+
+    /// The day of the month (1-31), if known.
+    pub day: Option<u8>,
+}
+
+impl Date {
+    /// Create a new instance with the specified components.
+    pub fn new(year: u16, month: Option<u8>, day: Option<u8>) -> Date {
+        Date { year, month, day }
+    }
+
+    fn checked(year: &str, month: Option<&str>, day: Option<&str>) -> Result<Date, Error> {
+        if year.len() != 4 {
+            return Err(format!("Invalid year: {:?}", year).into());
+        }
+
+        let date = Date {
+            year: year.parse()?,
+            month: match month {
+                Some(m) => Some(m.parse()?),
+                None => None,
+            },
+            day: match day {
+                Some(d) => Some(d.parse()?),
+                None => None,
+            },
+        };
+
+        if date.month.is_some_and(|m| !(1..=12).contains(&m)) {
+            Err(format!("Invalid month in date: {}", date).into())
+        } else if date.day.is_some_and(|d| !(1..=31).contains(&d)) {
+            Err(format!("Invalid day in date: {}", date).into())
+        } else {
+            Ok(date)
+        }
+    }
+}
+
+/// Parses the formats commonly found in cue sheets.
+///
+/// ```
+/// use cue_sheet::date::Date;
+///
+/// assert_eq!("1991".parse::<Date>().unwrap(), Date::new(1991, None, None));
+/// assert_eq!("2011-12".parse::<Date>().unwrap(), Date::new(2011, Some(12), None));
+/// assert_eq!("2011-12-22".parse::<Date>().unwrap(), Date::new(2011, Some(12), Some(22)));
+/// assert_eq!("22/12/2011".parse::<Date>().unwrap(), Date::new(2011, Some(12), Some(22)));
+/// assert!("12/22".parse::<Date>().is_err());
+/// ```
+impl FromStr for Date {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if s.contains('/') {
+            let parts: Vec<&str> = s.split('/').collect();
+            match parts.len() {
+                3 => Date::checked(parts[2], Some(parts[1]), Some(parts[0])),
+                _ => Err(format!("Invalid date: {:?}", s).into()),
+            }
+        } else {
+            let parts: Vec<&str> = s.split('-').collect();
+            match parts.len() {
+                1 => Date::checked(parts[0], None, None),
+                2 => Date::checked(parts[0], Some(parts[1]), None),
+                3 => Date::checked(parts[0], Some(parts[1]), Some(parts[2])),
+                _ => Err(format!("Invalid date: {:?}", s).into()),
+            }
+        }
+    }
+}
+
+/// Formats the date as `yyyy`, `yyyy-mm` or `yyyy-mm-dd`.
+impl fmt::Display for Date {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:04}", self.year)?;
+        if let Some(month) = self.month {
+            write!(f, "-{:02}", month)?;
+            if let Some(day) = self.day {
+                write!(f, "-{:02}", day)?;
+            }
+        }
+        Ok(())
+    }
+}
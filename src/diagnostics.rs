@@ -0,0 +1,306 @@
+// cue_sheet
+// Copyright (C) 2017  Leonardo Schwarz <mail@leoschwarz.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Rendering of parse errors and warnings for terminals.
+//!
+//! This module is only available with the `diagnostics` feature. Problems are shown together
+//! with the offending line of the source, the relevant part underlined:
+//!
+//! ```text
+//! error: "title" is not written in upper case
+//!  --> line 2, column 1
+//!   |
+//! 2 | title Loveless
+//!   | ^^^^^
+//!   = hint: keywords have to be written in upper case
+//! ```
+
+use errors::{Error, ErrorKind};
+use parser::{self, ParseOptions};
+use sheet::CueSheet;
+use std::fmt::Write;
+use validation::Severity;
+use warning::{Warning, WarningKind};
+
+/// A problem located in the source of a cue sheet.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Diagnostic {
+    /// Whether the problem prevented parsing or was recovered from.
+    pub severity: Severity,
+
+    /// Description of the problem.
+    pub message: String,
+
+    /// Line (starting at 1) containing the problem, if it could be determined.
+    pub line: Option<usize>,
+
+    /// Column (starting at 1) and width in characters of the offending text, if known.
+    ///
+    /// Otherwise the whole line is underlined.
+    pub range: Option<(usize, usize)>,
+
+    /// Suggestion how to fix the problem.
+    pub hint: Option<String>,
+}
+
+/// Returns the line numbered `number` (starting at 1) of `source`.
+fn source_line(source: &str, number: usize) -> Option<&str> {
+    number.checked_sub(1).and_then(|i| source.lines().nth(i))
+}
+
+/// Returns the column and width of the last occurrence of `text` in the line, ignoring ASCII
+/// case.
+fn find(line: &str, text: &str) -> Option<(usize, usize)> {
+    let (line_bytes, text_bytes) = (line.as_bytes(), text.as_bytes());
+    if text_bytes.is_empty() || text_bytes.len() > line_bytes.len() {
+        return None;
+    }
+    let start = (0..=line_bytes.len() - text_bytes.len())
+        .rev()
+        .filter(|&i| line.is_char_boundary(i))
+        .find(|&i| line_bytes[i..i + text_bytes.len()].eq_ignore_ascii_case(text_bytes))?;
+    let column = line[..start].chars().count() + 1;
+    Some((column, text.chars().count()))
+}
+
+impl Diagnostic {
+    /// Describe `error`, returned from parsing `source` with `options`.
+    ///
+    /// Errors which don't state their line are located by parsing the lines of `source` one by
+    /// one.
+    pub fn from_error(source: &str, options: &ParseOptions, error: &Error) -> Diagnostic {
+        let (line, range, hint) = match *error.kind() {
+            ErrorKind::TrailingTokens(line, column, ref text) => (
+                Some(line),
+                Some((column, text.chars().count())),
+                Some("quote values containing spaces, or remove the extra text"),
+            ),
+            ErrorKind::InvalidPlacement(line, _) => (Some(line), None, None),
+            ErrorKind::UnterminatedString(line, column) => (
+                Some(line),
+                Some((column, 1)),
+                Some("close the string with `\"` on the same line"),
+            ),
+            ErrorKind::StringTooLong(line, column) => (Some(line), Some((column, 1)), None),
+            ErrorKind::TracksOutOfOrder(_, line) => (
+                Some(line),
+                None,
+                Some("tracks in the same file have to start in increasing order"),
+            ),
+            ErrorKind::UnknownCommand(ref command) => {
+                let line = locate(source, options);
+                let range = line
+                    .and_then(|l| source_line(source, l))
+                    .and_then(|l| find(l, command));
+                (
+                    line,
+                    range,
+                    Some("unknown commands can be skipped with `ParseOptions::skip_unknown_commands`"),
+                )
+            }
+            _ => (locate(source, options), None, None),
+        };
+
+        Diagnostic {
+            severity: Severity::Error,
+            message: error.to_string(),
+            line,
+            range,
+            hint: hint.map(|h| h.to_string()),
+        }
+    }
+
+    /// Describe `warning`, returned from parsing `source`.
+    pub fn from_warning(source: &str, warning: &Warning) -> Diagnostic {
+        let text = match warning.kind {
+            WarningKind::UnknownCommand(ref text)
+            | WarningKind::NonstandardCase(ref text)
+            | WarningKind::NonstandardFileFormat(ref text)
+            | WarningKind::NonstandardTrackType(ref text)
+            | WarningKind::NonstandardTime(ref text)
+            | WarningKind::TrailingTokens(ref text)
+            | WarningKind::InvalidCatalog(ref text) => Some(text.clone()),
+            WarningKind::FileArguments(ref arguments) => arguments.last().cloned(),
+            WarningKind::DuplicateFlag(ref flag) => Some(flag.to_string()),
+            _ => None,
+        };
+        let hint = match warning.kind {
+            WarningKind::NonstandardCase(_) => Some("keywords have to be written in upper case"),
+            WarningKind::TimeOutOfRange(_) => {
+                Some("seconds have to be below 60 and frames below 75")
+            }
+            WarningKind::InferredFileFormat(_) => Some("add the file format, e.g. WAVE"),
+            WarningKind::NonstandardComment(_) => Some("comments have to be written as REM"),
+            WarningKind::UnquotedFileName(_) => {
+                Some("file names containing spaces have to be quoted")
+            }
+            WarningKind::TracksOutOfOrder(_) => Some("check the INDEX times of the track"),
+            _ => None,
+        };
+
+        Diagnostic {
+            severity: Severity::Warning,
+            message: warning.kind.to_string(),
+            line: Some(warning.line),
+            range: text.and_then(|text| find(source_line(source, warning.line)?, &text)),
+            hint: hint.map(|h| h.to_string()),
+        }
+    }
+
+    /// Render the diagnostic, showing the offending line of `source`.
+    pub fn render(&self, source: &str) -> String {
+        let mut out = String::new();
+        let label = match self.severity {
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        };
+        writeln!(out, "{}: {}", label, self.message).unwrap();
+
+        if let Some((number, line)) = self.line.and_then(|n| Some((n, source_line(source, n)?))) {
+            let gutter = number.to_string().len();
+            let (column, width) = self.range.unwrap_or_else(|| {
+                let indent = line.chars().take_while(|c| c.is_whitespace()).count();
+                (indent + 1, line.trim().chars().count())
+            });
+
+            match self.range {
+                Some((column, _)) => writeln!(
+                    out,
+                    "{:gutter$}--> line {}, column {}",
+                    "",
+                    number,
+                    column,
+                    gutter = gutter
+                ),
+                None => writeln!(out, "{:gutter$}--> line {}", "", number, gutter = gutter),
+            }
+            .unwrap();
+            writeln!(out, "{:gutter$} |", "", gutter = gutter).unwrap();
+            writeln!(out, "{} | {}", number, line.trim_end()).unwrap();
+            writeln!(
+                out,
+                "{:gutter$} | {:column$}{}",
+                "",
+                "",
+                "^".repeat(width.max(1)),
+                gutter = gutter,
+                column = column - 1
+            )
+            .unwrap();
+            if let Some(ref hint) = self.hint {
+                writeln!(out, "{:gutter$} = hint: {}", "", hint, gutter = gutter).unwrap();
+            }
+        } else if let Some(ref hint) = self.hint {
+            writeln!(out, " = hint: {}", hint).unwrap();
+        }
+
+        out
+    }
+}
+
+/// Returns the first line of `source` which can't be parsed on its own.
+fn locate(source: &str, options: &ParseOptions) -> Option<usize> {
+    source.lines().enumerate().find_map(|(i, line)| {
+        match parser::parse_line(line, i + 1, options, &mut Vec::new()) {
+            Ok(_) => None,
+            Err(_) => Some(i + 1),
+        }
+    })
+}
+
+/// Parse `source` into a `CueSheet` with `options` and describe all problems found.
+///
+/// The result contains the warnings in the order of their lines, followed by the error which
+/// stopped parsing if there was one.
+///
+/// ```
+/// use cue_sheet::diagnostics::diagnose;
+/// use cue_sheet::parser::ParseOptions;
+///
+/// let source = "TITLE Loveless\nTITLE \"a\" x\n";
+/// let options = ParseOptions {
+///     strict: true,
+///     ..Default::default()
+/// };
+/// let diagnostics = diagnose(source, &options);
+/// assert_eq!(
+///     diagnostics[0].render(source),
+///     "error: Unexpected \"x\" after command on line 2, column 11\n \
+///      --> line 2, column 11\n  |\n2 | TITLE \"a\" x\n  |           ^\n  \
+///      = hint: quote values containing spaces, or remove the extra text\n"
+/// );
+/// ```
+pub fn diagnose(source: &str, options: &ParseOptions) -> Vec<Diagnostic> {
+    let mut warnings = Vec::new();
+    let result = CueSheet::parse_collecting(source, options, &mut warnings);
+    warnings.sort_by_key(|warning| warning.line);
+
+    let mut diagnostics: Vec<Diagnostic> = warnings
+        .iter()
+        .map(|warning| Diagnostic::from_warning(source, warning))
+        .collect();
+    if let Err(e) = result {
+        diagnostics.push(Diagnostic::from_error(source, options, &e));
+    }
+    diagnostics
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn render() {
+        let options = ParseOptions {
+            strict: true,
+            ..Default::default()
+        };
+        let source = "PERFORMER MBV\ntitle Loveless\n";
+        assert_eq!(
+            diagnose(source, &options)[0].render(source),
+            "warning: \"title\" is not written in upper case\n \
+             --> line 2, column 1\n  |\n2 | title Loveless\n  | ^^^^^\n  \
+             = hint: keywords have to be written in upper case\n"
+        );
+
+        let source = "FILE a.wav WAVE\n  TRACK 01 AUDIO\n    INDEX 01 00:01:80\n";
+        let diagnostics = diagnose(source, &ParseOptions::default());
+        assert_eq!(diagnostics[0].severity, Severity::Warning);
+        assert_eq!(
+            diagnostics[0].render(source),
+            "warning: Time out of range, normalized to 00:02:05\n \
+             --> line 3\n  |\n3 |     INDEX 01 00:01:80\n  |     ^^^^^^^^^^^^^^^^^\n  \
+             = hint: seconds have to be below 60 and frames below 75\n"
+        );
+
+        let source = "FILE a.wav WAVE\nTRACK 01 AUDIO\nINDEX 01 00:00:00\nFLAGS DCP\n";
+        assert_eq!(diagnose(source, &options)[0].line, Some(4));
+
+        let source = "title Loveless\nFILE a.wav WAVE\nTRACK 01 AUDIO\nINDEX 01 00:00\n";
+        let diagnostics = diagnose(source, &options);
+        assert_eq!(diagnostics.len(), 2);
+        assert_eq!(diagnostics[0].severity, Severity::Warning);
+        assert_eq!(diagnostics[0].line, Some(1));
+        assert_eq!(diagnostics[1].severity, Severity::Error);
+    }
+
+    #[test]
+    fn find_ignores_case() {
+        assert_eq!(find("rem Ümlaut Title", "TITLE"), Some((12, 5)));
+        assert_eq!(find("TITLE x", "TITLE x y"), None);
+        assert_eq!(find("TITLE", ""), None);
+    }
+}
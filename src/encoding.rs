@@ -0,0 +1,123 @@
+// cue_sheet
+// Copyright (C) 2017  Leonardo Schwarz <mail@leoschwarz.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Detection and decoding of the text encoding of cue sheets.
+//!
+//! Cue sheets are plain text files without any declared encoding. Besides UTF-8 they are
+//! frequently written as UTF-16 (with a byte order mark) or in the legacy Windows code page.
+
+/// Text encodings which can be detected.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Encoding {
+    /// UTF-8, with or without byte order mark.
+    Utf8,
+
+    /// UTF-16 little-endian, detected by its byte order mark.
+    Utf16Le,
+
+    /// UTF-16 big-endian, detected by its byte order mark.
+    Utf16Be,
+
+    /// Windows-1252, used as fallback for content which is not valid UTF-8.
+    Windows1252,
+}
+
+/// Characters of Windows-1252 in the range `0x80..0xA0`, which differ from ISO-8859-1.
+const WINDOWS_1252: [char; 32] = [
+    '\u{20ac}', '\u{81}', '\u{201a}', '\u{192}', '\u{201e}', '\u{2026}', '\u{2020}', '\u{2021}',
+    '\u{2c6}', '\u{2030}', '\u{160}', '\u{2039}', '\u{152}', '\u{8d}', '\u{17d}', '\u{8f}',
+    '\u{90}', '\u{2018}', '\u{2019}', '\u{201c}', '\u{201d}', '\u{2022}', '\u{2013}', '\u{2014}',
+    '\u{2dc}', '\u{2122}', '\u{161}', '\u{203a}', '\u{153}', '\u{9d}', '\u{17e}', '\u{178}',
+];
+
+/// Detect the encoding of `bytes`.
+pub fn detect(bytes: &[u8]) -> Encoding {
+    if bytes.starts_with(&[0xff, 0xfe]) {
+        Encoding::Utf16Le
+    } else if bytes.starts_with(&[0xfe, 0xff]) {
+        Encoding::Utf16Be
+    } else if ::std::str::from_utf8(bytes).is_ok() {
+        Encoding::Utf8
+    } else {
+        Encoding::Windows1252
+    }
+}
+
+/// Decode `bytes` into a string, detecting the encoding first.
+///
+/// A leading byte order mark is removed.
+///
+/// ```
+/// use cue_sheet::encoding::decode;
+///
+/// assert_eq!(decode(b"TITLE \"Caf\xe9\""), "TITLE \"Café\"");
+/// assert_eq!(decode(b"\xef\xbb\xbfTITLE"), "TITLE");
+/// ```
+pub fn decode(bytes: &[u8]) -> String {
+    match detect(bytes) {
+        Encoding::Utf8 => {
+            let s = String::from_utf8_lossy(bytes);
+            s.trim_start_matches('\u{feff}').to_string()
+        }
+        Encoding::Utf16Le => decode_utf16(&bytes[2..], u16::from_le_bytes),
+        Encoding::Utf16Be => decode_utf16(&bytes[2..], u16::from_be_bytes),
+        Encoding::Windows1252 => bytes
+            .iter()
+            .map(|&b| match b {
+                0x80..=0x9f => WINDOWS_1252[b as usize - 0x80],
+                _ => b as char,
+            })
+            .collect(),
+    }
+}
+
+fn decode_utf16(bytes: &[u8], convert: fn([u8; 2]) -> u16) -> String {
+    let units = bytes
+        .chunks(2)
+        .map(|c| convert([c[0], *c.get(1).unwrap_or(&0)]));
+    ::std::char::decode_utf16(units)
+        .map(|c| c.unwrap_or(::std::char::REPLACEMENT_CHARACTER))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn utf16() {
+        let le = [0xff, 0xfe, b'R', 0, b'E', 0, b'M', 0, 0xe9, 0];
+        assert_eq!(detect(&le), Encoding::Utf16Le);
+        assert_eq!(decode(&le), "REMé");
+
+        let be = [0xfe, 0xff, 0, b'R', 0, b'E', 0, b'M', 0x20, 0xac];
+        assert_eq!(detect(&be), Encoding::Utf16Be);
+        assert_eq!(decode(&be), "REM€");
+    }
+
+    #[test]
+    fn windows_1252() {
+        let bytes = b"\x93Quoted\x94 \xc4";
+        assert_eq!(detect(bytes), Encoding::Windows1252);
+        assert_eq!(decode(bytes), "\u{201c}Quoted\u{201d} Ä");
+    }
+
+    #[test]
+    fn utf8() {
+        assert_eq!(detect("Ä".as_bytes()), Encoding::Utf8);
+        assert_eq!(decode("Ä".as_bytes()), "Ä");
+    }
+}
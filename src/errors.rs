@@ -27,9 +27,58 @@ error_chain! {
     links { }
 
     foreign_links {
+        Io(::std::io::Error)
+            #[doc="An I/O operation failed."];
         ParseInt(::std::num::ParseIntError)
             #[doc="Parsing a string into an integer failed."];
     }
 
-    errors { }
+    errors {
+        /// A command appeared in a place where the specification doesn't allow it.
+        InvalidPlacement(line: usize, reason: String) {
+            description("invalid command placement")
+            display("Invalid placement of command on line {}: {}", line, reason)
+        }
+
+        /// A line contained more tokens than its command takes.
+        TrailingTokens(line: usize, column: usize, text: String) {
+            description("trailing tokens after command")
+            display("Unexpected {:?} after command on line {}, column {}", text, line, column)
+        }
+
+        /// A line starts with an unknown command.
+        UnknownCommand(command: String) {
+            description("unknown command")
+            display("Invalid command: {:?}", command)
+        }
+
+        /// A quoted string is not closed until the end of its line.
+        UnterminatedString(line: usize, column: usize) {
+            description("unterminated string")
+            display("String opened on line {}, column {} is not closed", line, column)
+        }
+
+        /// A quoted string is longer than `parser::MAX_STRING_LENGTH`.
+        StringTooLong(line: usize, column: usize) {
+            description("string too long")
+            display(
+                "String opened on line {}, column {} exceeds the limit of {} characters",
+                line, column, ::parser::MAX_STRING_LENGTH
+            )
+        }
+
+        /// A track starts before the track preceding it in the same file.
+        ///
+        /// Outside of strict parsing this is only a `WarningKind::TracksOutOfOrder`.
+        TracksOutOfOrder(track: u32, line: usize) {
+            description("tracks out of order")
+            display("Track {:02} on line {} starts before the preceding track", track, line)
+        }
+
+        /// There are more tracks than a disc can hold.
+        TooManyTracks(count: usize) {
+            description("too many tracks")
+            display("{} tracks exceed the limit of {}", count, ::validation::MAX_TRACKS)
+        }
+    }
 }
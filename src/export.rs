@@ -0,0 +1,337 @@
+// cue_sheet
+// Copyright (C) 2017  Leonardo Schwarz <mail@leoschwarz.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Export of tracklists into playlist formats.
+
+use parser::Time;
+use tracklist::{Track, TrackFile, Tracklist};
+
+/// Performer of a track, falling back to the performer of the tracklist.
+pub(crate) fn performer<'a>(tracklist: &'a Tracklist, track: &'a Track) -> Option<&'a str> {
+    track
+        .performer
+        .as_ref()
+        .or(tracklist.performer.as_ref())
+        .map(|p| p.as_str())
+}
+
+/// Title of a track, falling back to its number.
+pub(crate) fn title(track: &Track) -> String {
+    match track.title {
+        Some(ref title) => title.clone(),
+        None => format!("Track {:02}", track.number),
+    }
+}
+
+/// Format a time in seconds with millisecond precision.
+fn seconds(time: &Time) -> String {
+    format!("{:.3}", time.total_seconds())
+}
+
+fn write_m3u<F>(tracklist: &Tracklist, offsets: bool, path: F) -> String
+where
+    F: Fn(&TrackFile, &Track) -> String,
+{
+    let mut out = String::from("#EXTM3U\n");
+
+    for file in &tracklist.files {
+        for track in &file.tracks {
+            let duration = track
+                .duration
+                .as_ref()
+                .map_or(-1, |d| d.total_seconds().round() as i64);
+            let name = match performer(tracklist, track) {
+                Some(performer) => format!("{} - {}", performer, title(track)),
+                None => title(track),
+            };
+            out.push_str(&format!("#EXTINF:{},{}\n", duration, name));
+
+            if offsets {
+                if let Some((_, start)) = track.index.iter().find(|&&(n, _)| n == 1) {
+                    out.push_str(&format!("#EXTVLCOPT:start-time={}\n", seconds(start)));
+                    // A stop time which can't be represented is left out.
+                    if let Some(stop) = track.duration.as_ref().and_then(|d| start.checked_add(d)) {
+                        out.push_str(&format!("#EXTVLCOPT:stop-time={}\n", seconds(&stop)));
+                    }
+                }
+            }
+
+            out.push_str(&path(file, track));
+            out.push('\n');
+        }
+    }
+
+    out
+}
+
+/// Export the tracklist as an extended M3U playlist referring to the files of the cue sheet.
+///
+/// Every track gets an entry pointing at the file containing it, its position within the file is
+/// described by `#EXTVLCOPT:start-time` and `#EXTVLCOPT:stop-time` lines (in seconds) as
+/// understood by VLC and other players.
+pub fn to_m3u(tracklist: &Tracklist) -> String {
+    write_m3u(tracklist, true, |file, _| file.name.clone())
+}
+
+/// Export the tracklist as an extended M3U playlist referring to one file per track.
+///
+/// The path of every track's file is determined by `path`, which is useful after a cue sheet's
+/// image was split into separate files.
+pub fn to_m3u_with_paths<F>(tracklist: &Tracklist, path: F) -> String
+where
+    F: Fn(&TrackFile, &Track) -> String,
+{
+    write_m3u(tracklist, false, path)
+}
+
+fn escape_xml(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// Percent-encode a path so it can be used as relative URI.
+fn escape_uri(path: &str) -> String {
+    let mut out = String::with_capacity(path.len());
+    for b in path.replace('\\', "/").bytes() {
+        match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => {
+                out.push(b as char)
+            }
+            b => out.push_str(&format!("%{:02X}", b)),
+        }
+    }
+    out
+}
+
+/// Export the tracklist as XSPF playlist.
+///
+/// The locations of the tracks refer to the files of the cue sheet, as relative URIs.
+pub fn to_xspf(tracklist: &Tracklist) -> String {
+    let mut out = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <playlist version=\"1\" xmlns=\"http://xspf.org/ns/0/\">\n",
+    );
+    if let Some(ref title) = tracklist.title {
+        out.push_str(&format!("  <title>{}</title>\n", escape_xml(title)));
+    }
+    if let Some(ref performer) = tracklist.performer {
+        out.push_str(&format!("  <creator>{}</creator>\n", escape_xml(performer)));
+    }
+
+    out.push_str("  <trackList>\n");
+    for file in &tracklist.files {
+        for track in &file.tracks {
+            out.push_str("    <track>\n");
+            out.push_str(&format!(
+                "      <location>{}</location>\n",
+                escape_xml(&escape_uri(&file.name))
+            ));
+            out.push_str(&format!(
+                "      <title>{}</title>\n",
+                escape_xml(&title(track))
+            ));
+            if let Some(performer) = performer(tracklist, track) {
+                out.push_str(&format!(
+                    "      <creator>{}</creator>\n",
+                    escape_xml(performer)
+                ));
+            }
+            if let Some(ref album) = tracklist.title {
+                out.push_str(&format!("      <album>{}</album>\n", escape_xml(album)));
+            }
+            out.push_str(&format!("      <trackNum>{}</trackNum>\n", track.number));
+            if let Some(ref duration) = track.duration {
+                let millis = (duration.total_seconds() * 1000.).round() as i64;
+                out.push_str(&format!("      <duration>{}</duration>\n", millis));
+            }
+            out.push_str("    </track>\n");
+        }
+    }
+    out.push_str("  </trackList>\n</playlist>\n");
+
+    out
+}
+
+fn json_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+fn json_opt_string(s: Option<&String>) -> String {
+    s.map_or_else(|| "null".to_string(), |s| json_string(s))
+}
+
+fn json_time(time: &Time) -> String {
+    format!(
+        "{{\"time\":\"{}\",\"frames\":{}}}",
+        time,
+        time.total_frames()
+    )
+}
+
+/// Export the tracklist as JSON.
+///
+/// Times are represented as objects containing the `mm:ss:ff` representation as `time` and the
+/// total number of frames as `frames`. Missing values are represented as `null`.
+///
+/// ```
+/// use cue_sheet::export::to_json;
+/// use cue_sheet::tracklist::Tracklist;
+///
+/// let tracklist = Tracklist::parse("TITLE \"Loveless\"").unwrap();
+/// assert_eq!(
+///     to_json(&tracklist),
+///     r#"{"title":"Loveless","performer":null,"files":[]}"#
+/// );
+/// ```
+pub fn to_json(tracklist: &Tracklist) -> String {
+    let files: Vec<String> = tracklist
+        .files
+        .iter()
+        .map(|file| {
+            let tracks: Vec<String> = file
+                .tracks
+                .iter()
+                .map(|track| {
+                    let index: Vec<String> = track
+                        .index
+                        .iter()
+                        .map(|(n, time)| {
+                            format!("{{\"number\":{},\"time\":{}}}", n, json_time(time))
+                        })
+                        .collect();
+                    format!(
+                        "{{\"number\":{},\"type\":{},\"title\":{},\"performer\":{},\
+                         \"index\":[{}],\"duration\":{}}}",
+                        track.number,
+                        json_string(&track.track_type.to_string()),
+                        json_opt_string(track.title.as_ref()),
+                        json_opt_string(track.performer.as_ref()),
+                        index.join(","),
+                        track
+                            .duration
+                            .as_ref()
+                            .map_or_else(|| "null".to_string(), json_time)
+                    )
+                })
+                .collect();
+            format!(
+                "{{\"name\":{},\"format\":{},\"tracks\":[{}]}}",
+                json_string(&file.name),
+                json_string(&file.format.to_string()),
+                tracks.join(",")
+            )
+        })
+        .collect();
+
+    format!(
+        "{{\"title\":{},\"performer\":{},\"files\":[{}]}}",
+        json_opt_string(tracklist.title.as_ref()),
+        json_opt_string(tracklist.performer.as_ref()),
+        files.join(",")
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCE: &str = r#"PERFORMER "My Bloody Valentine"
+                                    FILE "Loveless.wav" WAVE
+                                      TRACK 01 AUDIO
+                                        TITLE "Only Shallow"
+                                        INDEX 01 00:00:00
+                                      TRACK 02 AUDIO
+                                        TITLE "Loomer"
+                                        INDEX 01 04:17:52"#;
+
+    #[test]
+    fn m3u() {
+        let tracklist = Tracklist::parse(SOURCE).unwrap();
+        assert_eq!(
+            to_m3u(&tracklist),
+            "#EXTM3U\n\
+             #EXTINF:258,My Bloody Valentine - Only Shallow\n\
+             #EXTVLCOPT:start-time=0.000\n\
+             #EXTVLCOPT:stop-time=257.693\n\
+             Loveless.wav\n\
+             #EXTINF:-1,My Bloody Valentine - Loomer\n\
+             #EXTVLCOPT:start-time=257.693\n\
+             Loveless.wav\n"
+        );
+    }
+
+    #[test]
+    fn xspf() {
+        let tracklist = Tracklist::parse(SOURCE).unwrap();
+        let xspf = to_xspf(&tracklist);
+        assert!(xspf.contains("<creator>My Bloody Valentine</creator>"));
+        assert!(xspf.contains("<location>Loveless.wav</location>"));
+        assert!(xspf.contains("<duration>257693</duration>"));
+        assert_eq!(escape_uri("A B/ü.wav"), "A%20B/%C3%BC.wav");
+        assert_eq!(escape_xml("\"a\" & <b>"), "&quot;a&quot; &amp; &lt;b&gt;");
+    }
+
+    #[test]
+    fn json() {
+        let tracklist = Tracklist::parse(SOURCE).unwrap();
+        let json = to_json(&tracklist);
+        assert!(json.starts_with(
+            r#"{"title":null,"performer":"My Bloody Valentine","files":[{"name":"Loveless.wav","#
+        ));
+        assert!(json.contains(
+            r#"{"number":1,"type":"AUDIO","title":"Only Shallow","performer":null,"index":[{"number":1,"time":{"time":"00:00:00","frames":0}}],"duration":{"time":"04:17:52","frames":19327}}"#
+        ));
+        assert_eq!(json_string("a\"b\\\n\u{1}"), r#""a\"b\\\n\u0001""#);
+    }
+
+    #[test]
+    fn m3u_with_paths() {
+        let tracklist = Tracklist::parse(SOURCE).unwrap();
+        let m3u = to_m3u_with_paths(&tracklist, |_, track| format!("{:02}.flac", track.number));
+        assert_eq!(
+            m3u,
+            "#EXTM3U\n\
+             #EXTINF:258,My Bloody Valentine - Only Shallow\n\
+             01.flac\n\
+             #EXTINF:-1,My Bloody Valentine - Loomer\n\
+             02.flac\n"
+        );
+    }
+}
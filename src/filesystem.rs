@@ -0,0 +1,106 @@
+// cue_sheet
+// Copyright (C) 2017  Leonardo Schwarz <mail@leoschwarz.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Access to the files referenced by cue sheets.
+//!
+//! Resolving `FILE` references and probing audio files goes through the `FileSystem` trait, so
+//! the files don't have to be stored in a local directory, e.g. when reading a rip from an
+//! archive.
+
+use errors::Error;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{Cursor, Read, Seek};
+use std::path::{Path, PathBuf};
+
+/// A reader which can also seek, as returned by `FileSystem::open`.
+pub trait ReadSeek: Read + Seek {}
+
+impl<T: Read + Seek> ReadSeek for T {}
+
+/// Storage containing the files referenced by a cue sheet.
+pub trait FileSystem {
+    /// Returns true if there is a file at `path`.
+    fn is_file(&self, path: &Path) -> bool;
+
+    /// Open the file at `path` for reading.
+    fn open(&self, path: &Path) -> Result<Box<dyn ReadSeek + '_>, Error>;
+}
+
+impl<F: FileSystem + ?Sized> FileSystem for &F {
+    fn is_file(&self, path: &Path) -> bool {
+        (**self).is_file(path)
+    }
+
+    fn open(&self, path: &Path) -> Result<Box<dyn ReadSeek + '_>, Error> {
+        (**self).open(path)
+    }
+}
+
+/// The file system of the operating system.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct LocalFileSystem;
+
+impl FileSystem for LocalFileSystem {
+    fn is_file(&self, path: &Path) -> bool {
+        path.is_file()
+    }
+
+    fn open(&self, path: &Path) -> Result<Box<dyn ReadSeek + '_>, Error> {
+        Ok(Box::new(File::open(path)?))
+    }
+}
+
+/// Files kept in memory, e.g. extracted from an archive or for tests.
+///
+/// ```
+/// use cue_sheet::filesystem::{FileSystem, MemoryFileSystem};
+/// use std::path::Path;
+///
+/// let mut files = MemoryFileSystem::new();
+/// files.insert("rip/a.wav", b"RIFF".to_vec());
+/// assert!(files.is_file(Path::new("rip/a.wav")));
+/// assert!(files.open(Path::new("rip/b.wav")).is_err());
+/// ```
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct MemoryFileSystem {
+    files: HashMap<PathBuf, Vec<u8>>,
+}
+
+impl MemoryFileSystem {
+    /// Create an empty file system.
+    pub fn new() -> MemoryFileSystem {
+        MemoryFileSystem::default()
+    }
+
+    /// Add a file with the content `data` at `path`, replacing any previous one.
+    pub fn insert<P: Into<PathBuf>>(&mut self, path: P, data: Vec<u8>) {
+        self.files.insert(path.into(), data);
+    }
+}
+
+impl FileSystem for MemoryFileSystem {
+    fn is_file(&self, path: &Path) -> bool {
+        self.files.contains_key(path)
+    }
+
+    fn open(&self, path: &Path) -> Result<Box<dyn ReadSeek + '_>, Error> {
+        match self.files.get(path) {
+            Some(data) => Ok(Box::new(Cursor::new(&data[..]))),
+            None => Err(format!("No such file: {}", path.display()).into()),
+        }
+    }
+}
@@ -0,0 +1,119 @@
+// cue_sheet
+// Copyright (C) 2017  Leonardo Schwarz <mail@leoschwarz.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Detection of the program which generated a cue sheet.
+//!
+//! Ripping programs differ in how they describe gaps between tracks, knowing the generator
+//! allows adjusting the expectations accordingly.
+
+use parser::Token;
+
+/// A program known to generate cue sheets.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub enum Generator {
+    /// Exact Audio Copy.
+    ExactAudioCopy,
+
+    /// X Lossless Decoder.
+    Xld,
+
+    /// CUERipper, part of CUETools.
+    CueRipper,
+
+    /// CUETools.
+    CueTools,
+
+    /// foobar2000.
+    Foobar2000,
+
+    /// The generator could not be determined.
+    Unknown,
+}
+
+impl Generator {
+    /// Detect the generator from the `REM COMMENT` lines of a cue sheet.
+    ///
+    /// ```
+    /// use cue_sheet::generator::Generator;
+    /// use cue_sheet::parser::Token;
+    ///
+    /// let rem = vec![(
+    ///     "COMMENT".to_string(),
+    ///     Token::String("ExactAudioCopy v1.6".to_string()),
+    /// )];
+    /// assert_eq!(Generator::detect(&rem), Generator::ExactAudioCopy);
+    /// ```
+    pub fn detect(rem: &[(String, Token)]) -> Generator {
+        rem.iter()
+            .filter(|(key, _)| key.eq_ignore_ascii_case("COMMENT"))
+            .map(|(_, value)| Generator::from_comment(&value.text()))
+            .find(|generator| *generator != Generator::Unknown)
+            .unwrap_or(Generator::Unknown)
+    }
+
+    fn from_comment(comment: &str) -> Generator {
+        let comment = comment.to_lowercase();
+        if comment.starts_with("exactaudiocopy") || comment.starts_with("eac ") {
+            Generator::ExactAudioCopy
+        } else if comment.starts_with("xld") || comment.starts_with("x lossless decoder") {
+            Generator::Xld
+        } else if comment.starts_with("cueripper") {
+            Generator::CueRipper
+        } else if comment.starts_with("cuetools") {
+            Generator::CueTools
+        } else if comment.starts_with("foobar2000") {
+            Generator::Foobar2000
+        } else {
+            Generator::Unknown
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tracklist::Tracklist;
+
+    #[test]
+    fn detect() {
+        let src = r#"REM GENRE Alternative
+                     REM COMMENT "CUERipper v2.1.6 Copyright (C) 2008-13 Grigory Chudov"
+                     FILE "a.wav" WAVE
+                       TRACK 01 AUDIO
+                         INDEX 01 00:00:00"#;
+        let tracklist = Tracklist::parse(src).unwrap();
+        assert_eq!(tracklist.detect_generator(), Generator::CueRipper);
+
+        let comment = |c: &str| vec![("COMMENT".to_string(), Token::String(c.to_string()))];
+        assert_eq!(
+            Generator::detect(&comment("XLD version 20191004")),
+            Generator::Xld
+        );
+        assert_eq!(
+            Generator::detect(&comment("foobar2000 v1.6.2")),
+            Generator::Foobar2000
+        );
+        assert_eq!(
+            Generator::detect(&comment("CUETools generated dummy CUE sheet")),
+            Generator::CueTools
+        );
+        assert_eq!(
+            Generator::detect(&comment("Ripped by me")),
+            Generator::Unknown
+        );
+        assert_eq!(Generator::detect(&[]), Generator::Unknown);
+    }
+}
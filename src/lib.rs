@@ -23,9 +23,51 @@
 
 #![deny(missing_docs)]
 
+#[cfg(feature = "chrono")]
+extern crate chrono;
 #[macro_use]
 extern crate error_chain;
+#[cfg(feature = "flate2")]
+extern crate flate2;
+#[cfg(feature = "rayon")]
+extern crate rayon;
+#[cfg(feature = "serde")]
+extern crate serde;
+#[cfg(all(test, feature = "serde"))]
+extern crate serde_json;
+extern crate sha1_smol;
+#[cfg(feature = "time")]
+extern crate time;
+#[cfg(feature = "unicode-normalization")]
+extern crate unicode_normalization;
 
+pub mod album;
+pub mod anonymize;
+pub mod batch;
+pub mod burn;
+pub mod cdtext;
+pub mod cleanup;
+pub mod ctdb;
+pub mod cursor;
+pub mod date;
+#[cfg(feature = "diagnostics")]
+pub mod diagnostics;
+pub mod encoding;
 pub mod errors;
+pub mod export;
+pub mod filesystem;
+pub mod generator;
+pub mod msf;
+pub mod musicbrainz;
 pub mod parser;
+#[cfg(feature = "audio-probe")]
+pub mod probe;
+pub mod rem;
+pub mod sheet;
+pub mod split;
+pub mod summary;
+pub mod tags;
 pub mod tracklist;
+pub mod validation;
+pub mod warning;
+pub mod writer;
@@ -0,0 +1,104 @@
+// cue_sheet
+// Copyright (C) 2017  Leonardo Schwarz <mail@leoschwarz.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Conversion between MSF times and logical block addresses.
+//!
+//! A position on a CD can be expressed in MSF (minutes, seconds, frames) or as a logical
+//! block address (LBA) counting frames/sectors. The Red Book (and the MMC command set)
+//! defines LBA 0 as the start of the program area, which is located at the absolute MSF
+//! address `00:02:00` because of the 150 frames (two seconds) of the first pregap.
+//!
+//! Times in cue sheets are relative to the start of a file and don't include this offset,
+//! while many disc ids and the TOC of a physical disc use absolute addresses.
+
+use parser::Time;
+
+/// Number of frames between the absolute MSF address `00:00:00` and LBA 0.
+pub const LBA_OFFSET: i64 = 150;
+
+/// Convert an MSF time into a logical block address.
+///
+/// With `with_offset` the time is treated as an absolute address on the disc, so the 150
+/// frames of the first pregap are subtracted. Otherwise it is a plain frame count, as for the
+/// times of a cue sheet.
+///
+/// ```
+/// use cue_sheet::msf::msf_to_lba;
+/// use cue_sheet::parser::Time;
+///
+/// assert_eq!(msf_to_lba(&Time::new(0, 2, 0), true), 0);
+/// assert_eq!(msf_to_lba(&Time::new(0, 2, 0), false), 150);
+/// assert_eq!(msf_to_lba(&Time::new(1, 0, 0), true), 4350);
+/// ```
+pub fn msf_to_lba(time: &Time, with_offset: bool) -> i64 {
+    if with_offset {
+        time.total_frames() - LBA_OFFSET
+    } else {
+        time.total_frames()
+    }
+}
+
+/// Convert a time relative to the start of the program area (e.g. `Track::album_start`) into
+/// the absolute address on the disc in frames, i.e. add the 150 frames of the first pregap.
+///
+/// This is the form of the track offsets and the lead-out in the TOCs of disc ids.
+///
+/// ```
+/// use cue_sheet::msf::absolute_address;
+/// use cue_sheet::parser::Time;
+///
+/// assert_eq!(absolute_address(&Time::new(0, 0, 0)), 150);
+/// assert_eq!(absolute_address(&Time::new(4, 17, 52)), 19477);
+/// ```
+pub fn absolute_address(time: &Time) -> i64 {
+    msf_to_lba(time, false) + LBA_OFFSET
+}
+
+/// Convert a logical block address into an MSF time, the inverse of `msf_to_lba`.
+///
+/// With `with_offset` the result is the absolute address on the disc, i.e. 150 frames are
+/// added. `None` if the address is out of the range of `Time`.
+///
+/// ```
+/// use cue_sheet::msf::lba_to_msf;
+/// use cue_sheet::parser::Time;
+///
+/// assert_eq!(lba_to_msf(0, true), Some(Time::new(0, 2, 0)));
+/// assert_eq!(lba_to_msf(4350, false), Some(Time::new(0, 58, 0)));
+/// assert_eq!(lba_to_msf(i64::MAX, true), None);
+/// ```
+pub fn lba_to_msf(lba: i64, with_offset: bool) -> Option<Time> {
+    if with_offset {
+        Time::checked_from_frames(lba.checked_add(LBA_OFFSET)?)
+    } else {
+        Time::checked_from_frames(lba)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        for &lba in &[-150, 0, 1, 74, 75, 4499, 359_849] {
+            for &offset in &[false, true] {
+                assert_eq!(msf_to_lba(&lba_to_msf(lba, offset).unwrap(), offset), lba);
+            }
+        }
+        assert_eq!(lba_to_msf(-150, true), Some(Time::new(0, 0, 0)));
+    }
+}
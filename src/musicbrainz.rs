@@ -0,0 +1,176 @@
+// cue_sheet
+// Copyright (C) 2017  Leonardo Schwarz <mail@leoschwarz.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Looking up discs on MusicBrainz.
+//!
+//! MusicBrainz identifies discs by their table of contents (TOC): the numbers of the first and
+//! last track, the position of the lead-out and the positions of all tracks, as absolute
+//! addresses in frames. The TOC can be passed to the web service or the website directly,
+//! without computing the disc id.
+
+use errors::Error;
+use msf::absolute_address;
+use parser::Time;
+use tracklist::Tracklist;
+
+/// The server used by `Lookup` unless another one is set with `Lookup::server`.
+pub const DEFAULT_SERVER: &str = "https://musicbrainz.org";
+
+/// Returns the absolute positions of all tracks of `tracklist` in frames.
+///
+/// The positions of the tracks of later files can only be determined if the durations of the
+/// earlier files are known, see `Tracklist::fill_durations`.
+pub(crate) fn track_offsets(tracklist: &Tracklist) -> Result<Vec<i64>, Error> {
+    tracklist
+        .tracks()
+        .map(|(_, track)| match track.album_start() {
+            Some(start) => Ok(absolute_address(&start)),
+            None => Err(format!(
+                "Position of track {:02} is unknown, see `Tracklist::fill_durations`.",
+                track.number
+            )
+            .into()),
+        })
+        .collect()
+}
+
+/// Returns the TOC of `tracklist` in the format used by MusicBrainz, i.e. the numbers of the
+/// first and last track, the offset of the lead-out and the offsets of all tracks separated by
+/// spaces.
+///
+/// `leadout` is the position of the lead-out relative to the start of the first track, i.e.
+/// the total length of the disc.
+///
+/// ```
+/// use cue_sheet::musicbrainz::toc_string;
+/// use cue_sheet::parser::Time;
+/// use cue_sheet::tracklist::Tracklist;
+///
+/// let tracklist = Tracklist::parse(
+///     "FILE a.wav WAVE\nTRACK 01 AUDIO\nINDEX 01 00:00:00\nTRACK 02 AUDIO\nINDEX 01 04:17:52",
+/// )
+/// .unwrap();
+/// assert_eq!(
+///     toc_string(&tracklist, Time::new(10, 0, 0)).unwrap(),
+///     "1 2 45150 150 19477"
+/// );
+/// ```
+pub fn toc_string(tracklist: &Tracklist, leadout: Time) -> Result<String, Error> {
+    let offsets = track_offsets(tracklist)?;
+    let numbers: Vec<u32> = tracklist.tracks().map(|(_, t)| t.number).collect();
+    let (first, last) = match (numbers.first(), numbers.last()) {
+        (Some(first), Some(last)) => (first, last),
+        _ => return Err("The tracklist doesn't contain any tracks.".into()),
+    };
+
+    let mut toc = format!("{} {} {}", first, last, absolute_address(&leadout));
+    for offset in offsets {
+        toc.push_str(&format!(" {}", offset));
+    }
+    Ok(toc)
+}
+
+/// Builder for the URLs to look up a disc on MusicBrainz or to submit it.
+///
+/// ```
+/// use cue_sheet::musicbrainz::Lookup;
+/// use cue_sheet::parser::Time;
+/// use cue_sheet::tracklist::Tracklist;
+///
+/// let tracklist = Tracklist::parse("FILE a.wav WAVE\nTRACK 01 AUDIO\nINDEX 01 00:00:00").unwrap();
+/// let lookup = Lookup::new(&tracklist, Time::new(42, 0, 0)).unwrap();
+/// assert_eq!(
+///     lookup.lookup_url(),
+///     "https://musicbrainz.org/ws/2/discid/-?toc=1+1+189150+150"
+/// );
+/// assert_eq!(
+///     lookup.submission_url(),
+///     "https://musicbrainz.org/cdtoc/attach?toc=1+1+189150+150&tracks=1"
+/// );
+/// ```
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Lookup {
+    toc: String,
+    tracks: usize,
+    server: String,
+}
+
+impl Lookup {
+    /// Create a lookup for `tracklist`, see `toc_string`.
+    pub fn new(tracklist: &Tracklist, leadout: Time) -> Result<Lookup, Error> {
+        let toc = toc_string(tracklist, leadout)?;
+        Ok(Lookup {
+            tracks: toc.split(' ').count() - 3,
+            toc,
+            server: DEFAULT_SERVER.to_string(),
+        })
+    }
+
+    /// Use the server at `server` (e.g. a mirror or the test server) instead of
+    /// `DEFAULT_SERVER`.
+    pub fn server(mut self, server: &str) -> Lookup {
+        self.server = server.trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Returns the URL of the web service looking up releases matching the TOC.
+    pub fn lookup_url(&self) -> String {
+        format!("{}/ws/2/discid/-?toc={}", self.server, self.toc_param())
+    }
+
+    /// Returns the URL of the website page for attaching the TOC to a release.
+    pub fn submission_url(&self) -> String {
+        format!(
+            "{}/cdtoc/attach?toc={}&tracks={}",
+            self.server,
+            self.toc_param(),
+            self.tracks
+        )
+    }
+
+    fn toc_param(&self) -> String {
+        self.toc.replace(' ', "+")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use errors::Error;
+    use tracklist::TrackFile;
+
+    #[test]
+    fn multiple_files() {
+        let src = r#"FILE "01.wav" WAVE
+                       TRACK 01 AUDIO
+                         INDEX 01 00:00:00
+                     FILE "02.wav" WAVE
+                       TRACK 02 AUDIO
+                         INDEX 01 00:00:00"#;
+        let mut tracklist = Tracklist::parse(src).unwrap();
+        assert!(toc_string(&tracklist, Time::new(8, 0, 0)).is_err());
+
+        let length = |_: &TrackFile| -> Result<Time, Error> { Ok(Time::new(4, 0, 0)) };
+        tracklist.fill_durations(&length).unwrap();
+        let lookup = Lookup::new(&tracklist, Time::new(8, 0, 0))
+            .unwrap()
+            .server("https://test.musicbrainz.org/");
+        assert_eq!(
+            lookup.submission_url(),
+            "https://test.musicbrainz.org/cdtoc/attach?toc=1+2+36150+150+18150&tracks=2"
+        );
+    }
+}
@@ -14,12 +14,19 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
-use super::{FileFormat, Time, Token, TrackFlag, TrackType};
-use errors::Error;
+use super::{
+    frames_per_second, tokenize, FileFormat, ParseOptions, Quoted, Time, Token, TrackFlag,
+    TrackType,
+};
+use errors::{Error, ErrorKind};
+use std::fmt;
+use std::path::Path;
 use std::str::FromStr;
+use validation::catalog_checksum_valid;
+use warning::WarningKind;
 
 /// The main grammar element of CUE sheets.
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Eq, PartialEq)]
 pub enum Command {
     /// A 13-digit UPC/EAN code.
     Catalog(String),
@@ -28,7 +35,10 @@ pub enum Command {
     Cdtextfile(String),
 
     /// A path to a file containing audio data, and to which subsequent commands apply.
-    File(String, FileFormat),
+    ///
+    /// The last field holds nonstandard arguments following the file format, like the byte
+    /// offsets some cdrdao-style sheets add. They are only accepted outside of strict mode.
+    File(String, FileFormat, Vec<Token>),
 
     /// Per-track subcode flag(s).
     Flags(Vec<TrackFlag>),
@@ -62,68 +72,336 @@ pub enum Command {
     Track(u32, TrackType),
 }
 
-fn consume_token(tokens: &mut Vec<Token>) -> Result<Token, Error> {
-    if tokens.len() == 0 {
-        Err("No tokens left!".into())
-    } else {
-        Ok(tokens.remove(0))
+/// A command together with the spelling of its keywords in the source, which
+/// `KeywordCase::Preserve` writes back, see `parse_cue_spelled`.
+#[derive(Clone, Debug)]
+pub struct SpelledCommand {
+    /// The parsed command.
+    pub command: Command,
+
+    /// The keyword of the command followed by its file format, track type or flags, as they
+    /// were written.
+    pub keywords: Vec<String>,
+}
+
+/// Formats the command as one line of a cue sheet (without indentation or line ending).
+impl fmt::Display for Command {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Command::Catalog(ref catalog) => write!(f, "CATALOG {}", catalog),
+            Command::Cdtextfile(ref path) => write!(f, "CDTEXTFILE {}", Quoted(path)),
+            Command::File(ref name, ref format, ref arguments) => {
+                write!(f, "FILE {} {}", Quoted(name), format)?;
+                for argument in arguments {
+                    write!(f, " {}", argument)?;
+                }
+                Ok(())
+            }
+            Command::Flags(ref flags) => {
+                f.write_str("FLAGS")?;
+                for flag in flags {
+                    write!(f, " {}", flag)?;
+                }
+                Ok(())
+            }
+            Command::Index(num, ref time) => write!(f, "INDEX {:02} {}", num, time),
+            Command::Isrc(ref isrc) => write!(f, "ISRC {}", isrc),
+            Command::Performer(ref performer) => write!(f, "PERFORMER {}", Quoted(performer)),
+            Command::Postgap(ref time) => write!(f, "POSTGAP {}", time),
+            Command::Pregap(ref time) => write!(f, "PREGAP {}", time),
+            Command::Rem(ref key, ref value) => {
+                f.write_str("REM")?;
+                if !key.is_empty() {
+                    write!(f, " {}", key)?;
+                }
+                match *value {
+                    Token::String(ref s) if s.is_empty() => Ok(()),
+                    // Values of several words are read from the rest of the line as they are, so
+                    // quotes within them are kept by not quoting the value again.
+                    Token::String(ref s)
+                        if s.contains('"') && tokenize(s).is_ok_and(|t| t.len() > 1) =>
+                    {
+                        write!(f, " {}", s)
+                    }
+                    ref value => write!(f, " {}", value),
+                }
+            }
+            Command::Songwriter(ref songwriter) => write!(f, "SONGWRITER {}", Quoted(songwriter)),
+            Command::Title(ref title) => write!(f, "TITLE {}", Quoted(title)),
+            Command::Track(num, ref track_type) => write!(f, "TRACK {:02} {}", num, track_type),
+        }
+    }
+}
+
+fn consume_token(tokens: &mut &[Token]) -> Result<Token, Error> {
+    match tokens.split_first() {
+        Some((token, rest)) => {
+            *tokens = rest;
+            Ok(token.clone())
+        }
+        None => Err("No tokens left!".into()),
     }
 }
 
-fn consume_time(tokens: &mut Vec<Token>) -> Result<Time, Error> {
+fn consume_time(tokens: &mut &[Token]) -> Result<Time, Error> {
     match consume_token(tokens)? {
-        Token::Time(duration) => Ok(duration),
+        Token::Time { value, .. } => Ok(value),
         t => Err(format!("Expected duration but found {:?} instead", t).into()),
     }
 }
 
-fn consume_number(tokens: &mut Vec<Token>) -> Result<u32, Error> {
+fn consume_number(tokens: &mut &[Token]) -> Result<u32, Error> {
     match consume_token(tokens)? {
-        Token::Number(num) => Ok(num),
+        Token::Number { value, .. } => Ok(value),
         t => Err(format!("Expeceted number but found {:?} instead", t).into()),
     }
 }
 
-fn consume_string(tokens: &mut Vec<Token>) -> Result<String, Error> {
+/// Consume a string. Numbers and times are accepted as well, since an unquoted string like the
+/// title `99` or `12:00:00` is read as a number or time by the tokenizer.
+fn consume_string(tokens: &mut &[Token]) -> Result<String, Error> {
     match consume_token(tokens)? {
         Token::String(s) => Ok(s),
-        t => Err(format!("Expeceted string but found {:?} instead", t).into()),
+        Token::Number { raw, .. } | Token::Time { raw, .. } => Ok(raw),
+    }
+}
+
+/// The keywords of all commands, as the specification requires them to be written.
+const KEYWORDS: [&str; 13] = [
+    "CATALOG",
+    "CDTEXTFILE",
+    "FILE",
+    "FLAGS",
+    "INDEX",
+    "ISRC",
+    "PERFORMER",
+    "POSTGAP",
+    "PREGAP",
+    "REM",
+    "SONGWRITER",
+    "TITLE",
+    "TRACK",
+];
+
+/// Returns the number of tokens at the start of `tokens` belonging to the free-form rest of a
+/// command, i.e. the value of `REM` or the arguments of `FILE`.
+///
+/// If `tokens` are exactly one `line` this is all of them. Otherwise the end of the line is
+/// unknown, and the rest ends before the next keyword of a command in upper case.
+fn rest_len(tokens: &[Token], line: bool) -> usize {
+    if line {
+        return tokens.len();
+    }
+    tokens
+        .iter()
+        .position(|token| match *token {
+            Token::String(ref s) => KEYWORDS.contains(&s.as_str()),
+            _ => false,
+        })
+        .unwrap_or(tokens.len())
+}
+
+/// Record the spelling of the keyword `word` in `keywords` and warn unless it is written in
+/// upper case like the specification requires for keywords, file formats, track types and
+/// flags. Even strict mode only warns, since the case doesn't change the meaning.
+fn check_case(word: &str, warnings: &mut Vec<WarningKind>, keywords: &mut Vec<String>) {
+    if word != word.to_uppercase() {
+        warnings.push(WarningKind::NonstandardCase(word.to_string()));
+    }
+    keywords.push(word.to_string());
+}
+
+/// Consume a keyword, see `check_case`.
+fn consume_keyword(
+    tokens: &mut &[Token],
+    warnings: &mut Vec<WarningKind>,
+    keywords: &mut Vec<String>,
+) -> Result<String, Error> {
+    let word = consume_string(tokens)?;
+    check_case(&word, warnings, keywords);
+    Ok(word)
+}
+
+/// Consume a time, normalizing seconds and frames which are out of range outside of strict
+/// mode.
+fn consume_valid_time(
+    tokens: &mut &[Token],
+    options: &ParseOptions,
+    warnings: &mut Vec<WarningKind>,
+) -> Result<Time, Error> {
+    let time = match tokens.first() {
+        Some(Token::String(s)) if options.lenient_times && !options.strict => {
+            let time = Time::parse_lenient_unchecked(s)?;
+            warnings.push(WarningKind::NonstandardTime(s.clone()));
+            *tokens = &tokens[1..];
+            time
+        }
+        _ => consume_time(tokens)?,
+    };
+    match options.frame_rate {
+        Some(0) => return Err("The frame rate has to be positive.".into()),
+        Some(rate) if rate > i8::MAX as u8 => {
+            return Err(format!("The frame rate {} is above {}.", rate, i8::MAX).into())
+        }
+        _ => {}
+    }
+    let fps = frames_per_second(options.frame_rate);
+    if time.seconds() < 60 && i64::from(time.frames()) < i64::from(fps) {
+        Ok(time)
+    } else if options.strict {
+        Err(format!("Time out of range: {}", time).into())
+    } else {
+        let normalized = Time::checked_from_frames_at(time.total_frames_at(fps), fps)
+            .ok_or_else(|| Error::from(format!("Time out of range: {}", time)))?;
+        warnings.push(WarningKind::TimeOutOfRange(normalized.clone()));
+        Ok(normalized)
     }
 }
 
 impl Command {
-    pub(crate) fn consume(tokens: &mut Vec<Token>) -> Result<Command, Error> {
-        let keyword = consume_string(tokens)?;
+    /// Parse one command from the start of `tokens`, using the default options.
+    ///
+    /// On success `tokens` is advanced past the tokens belonging to the command, on failure it
+    /// is left unchanged. The underlying slice is never modified. This makes it possible to
+    /// build custom grammars on top of the commands of a cue sheet.
+    ///
+    /// Tokens don't tell where a line ends, so the value of `REM` and the arguments of `FILE`
+    /// end before the next command keyword written in upper case.
+    ///
+    /// ```
+    /// use cue_sheet::parser::{tokenize, Command, Time};
+    ///
+    /// let tokens = tokenize("INDEX 01 00:02:00 PREGAP 00:01:00").unwrap();
+    /// let mut cursor = &tokens[..];
+    /// match Command::parse(&mut cursor).unwrap() {
+    ///     Command::Index(1, time) => assert_eq!(time, Time::new(0, 2, 0)),
+    ///     c => panic!("unexpected command: {:?}", c),
+    /// }
+    /// assert_eq!(cursor.len(), 2);
+    /// ```
+    pub fn parse(tokens: &mut &[Token]) -> Result<Command, Error> {
+        Command::parse_with_options(tokens, ParseOptions::global_default())
+    }
+
+    /// Parse one command from the start of `tokens` using the provided `options`.
+    ///
+    /// See `Command::parse` for details.
+    pub fn parse_with_options(
+        tokens: &mut &[Token],
+        options: &ParseOptions,
+    ) -> Result<Command, Error> {
+        Command::parse_restoring(tokens, options, &mut Vec::new(), false).map(|c| c.command)
+    }
+
+    /// Parse one command from `tokens`, which contain exactly one line, collecting the problems
+    /// recovered from in `warnings`.
+    pub(crate) fn parse_with_warnings(
+        tokens: &mut &[Token],
+        options: &ParseOptions,
+        warnings: &mut Vec<WarningKind>,
+    ) -> Result<SpelledCommand, Error> {
+        Command::parse_restoring(tokens, options, warnings, true)
+    }
+
+    /// Parse one command, leaving `tokens` unchanged if this fails.
+    ///
+    /// With `line` the command extends to the end of `tokens`, otherwise see `rest_len`.
+    fn parse_restoring(
+        tokens: &mut &[Token],
+        options: &ParseOptions,
+        warnings: &mut Vec<WarningKind>,
+        line: bool,
+    ) -> Result<SpelledCommand, Error> {
+        let start = *tokens;
+        let mut keywords = Vec::new();
+        match Command::parse_command(tokens, options, warnings, &mut keywords, line) {
+            Ok(command) => Ok(SpelledCommand { command, keywords }),
+            Err(e) => {
+                *tokens = start;
+                Err(e)
+            }
+        }
+    }
+
+    fn parse_command(
+        tokens: &mut &[Token],
+        options: &ParseOptions,
+        warnings: &mut Vec<WarningKind>,
+        keywords: &mut Vec<String>,
+        line: bool,
+    ) -> Result<Command, Error> {
+        let keyword = consume_keyword(tokens, warnings, keywords)?;
         match keyword.to_uppercase().as_str() {
-            "CATALOG" => Ok(Command::Catalog(format!("{:013}", consume_number(tokens)?))),
+            "CATALOG" => {
+                let catalog = match consume_token(tokens)? {
+                    Token::Number { value, .. } => format!("{:013}", value),
+                    Token::String(s) => s,
+                    t => {
+                        return Err(
+                            format!("Expected catalog number but found {:?} instead", t).into()
+                        )
+                    }
+                };
+                if options.check_catalog && !catalog_checksum_valid(&catalog) {
+                    warnings.push(WarningKind::InvalidCatalog(catalog.clone()));
+                }
+                Ok(Command::Catalog(catalog))
+            }
             "CDTEXTFILE" => Ok(Command::Cdtextfile(consume_string(tokens)?)),
-            "FILE" => Ok(Command::File(
-                consume_string(tokens)?,
-                consume_string(tokens)?.parse()?,
-            )),
+            "FILE" => {
+                let name = consume_string(tokens)?;
+                let format = if !tokens.is_empty() || options.strict {
+                    let keyword = consume_string(tokens)?;
+                    match keyword.parse() {
+                        Ok(format) => {
+                            check_case(&keyword, warnings, keywords);
+                            format
+                        }
+                        Err(e) => {
+                            if options.strict {
+                                return Err(e);
+                            }
+                            warnings.push(WarningKind::NonstandardFileFormat(keyword.clone()));
+                            keywords.push(keyword.clone());
+                            FileFormat::Other(keyword)
+                        }
+                    }
+                } else {
+                    // Infer a missing file format from the file name.
+                    let format = FileFormat::from_path(Path::new(&name)).ok_or_else(|| {
+                        Error::from(format!("FILE {:?} is missing its file format", name))
+                    })?;
+                    warnings.push(WarningKind::InferredFileFormat(format.clone()));
+                    format
+                };
+
+                // Keep nonstandard arguments, strict mode rejects them as trailing tokens.
+                let mut arguments = Vec::new();
+                let rest = rest_len(tokens, line);
+                if !options.strict && rest > 0 {
+                    arguments = tokens[..rest].to_vec();
+                    *tokens = &tokens[rest..];
+                    let texts = arguments.iter().map(Token::text).collect();
+                    warnings.push(WarningKind::FileArguments(texts));
+                }
+                Ok(Command::File(name, format, arguments))
+            }
             "FLAGS" => {
                 let mut flags = Vec::<TrackFlag>::new();
 
-                while tokens.len() > 0 {
-                    let token = tokens.remove(0);
-                    let ok = match token {
-                        Token::String(ref s) => match TrackFlag::from_str(s.as_str()) {
-                            Ok(flag) => {
-                                flags.push(flag);
-                                true
-                            }
-                            Err(_) => false,
-                        },
-                        _ => false,
-                    };
-
-                    if !ok {
-                        tokens.insert(0, token);
-                        break;
+                while let Some(Token::String(s)) = tokens.first() {
+                    match TrackFlag::from_str(s.as_str()) {
+                        Ok(flag) if flags.contains(&flag) => {
+                            warnings.push(WarningKind::DuplicateFlag(flag))
+                        }
+                        Ok(flag) => flags.push(flag),
+                        Err(_) => break,
                     }
+                    check_case(s, warnings, keywords);
+                    *tokens = &tokens[1..];
                 }
 
-                if flags.len() == 0 {
+                if flags.is_empty() {
                     Err("Encountered FLAGS command without succeeding TrackFlag".into())
                 } else {
                     Ok(Command::Flags(flags))
@@ -131,23 +409,490 @@ impl Command {
             }
             "INDEX" => Ok(Command::Index(
                 consume_number(tokens)?,
-                consume_time(tokens)?,
+                consume_valid_time(tokens, options, warnings)?,
             )),
             "ISRC" => Ok(Command::Isrc(consume_string(tokens)?)),
             "PERFORMER" => Ok(Command::Performer(consume_string(tokens)?)),
-            "POSTGAP" => Ok(Command::Postgap(consume_time(tokens)?)),
-            "PREGAP" => Ok(Command::Pregap(consume_time(tokens)?)),
-            "REM" => Ok(Command::Rem(
-                consume_string(tokens)?,
-                consume_token(tokens)?,
-            )),
+            "POSTGAP" => Ok(Command::Postgap(consume_valid_time(
+                tokens, options, warnings,
+            )?)),
+            "PREGAP" => Ok(Command::Pregap(consume_valid_time(
+                tokens, options, warnings,
+            )?)),
+            "REM" => {
+                // A REM command consumes the rest of the line, which might also be empty.
+                let key = if rest_len(tokens, line) == 0 {
+                    String::new()
+                } else {
+                    consume_token(tokens)?.text()
+                };
+                let rest = rest_len(tokens, line);
+                let value = if rest == 1 {
+                    consume_token(tokens)?
+                } else {
+                    let words: Vec<String> = tokens[..rest].iter().map(|t| t.text()).collect();
+                    *tokens = &tokens[rest..];
+                    Token::String(words.join(" "))
+                };
+                Ok(Command::Rem(key, value))
+            }
             "SONGWRITER" => Ok(Command::Songwriter(consume_string(tokens)?)),
             "TITLE" => Ok(Command::Title(consume_string(tokens)?)),
-            "TRACK" => Ok(Command::Track(
-                consume_number(tokens)?,
-                consume_string(tokens)?.parse()?,
-            )),
-            cmd => Err(format!("Invalid command: {:?}", cmd).into()),
+            "TRACK" => {
+                let number = consume_number(tokens)?;
+                let keyword = consume_keyword(tokens, warnings, keywords)?;
+                let track_type: TrackType = keyword.parse()?;
+                if !track_type.to_string().eq_ignore_ascii_case(&keyword) {
+                    if options.strict {
+                        return Err(format!("Nonstandard track type: {:?}", keyword).into());
+                    }
+                    warnings.push(WarningKind::NonstandardTrackType(keyword));
+                }
+                Ok(Command::Track(number, track_type))
+            }
+            _ => Err(ErrorKind::UnknownCommand(keyword.clone()).into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parser::{parse_cue, parse_cue_with_options, parse_cue_with_warnings, ZeroWidth};
+
+    #[test]
+    fn parse_cursor() {
+        let tokens = tokenize("FLAGS DCP PRE TITLE x").unwrap();
+        let mut cursor = &tokens[..];
+        match Command::parse(&mut cursor).unwrap() {
+            Command::Flags(flags) => assert_eq!(flags, vec![TrackFlag::Dcp, TrackFlag::Pre]),
+            c => panic!("unexpected command: {:?}", c),
+        }
+        match Command::parse(&mut cursor).unwrap() {
+            Command::Title(title) => assert_eq!(title, "x"),
+            c => panic!("unexpected command: {:?}", c),
+        }
+        assert!(cursor.is_empty());
+        assert_eq!(tokens.len(), 5);
+        assert!(Command::parse(&mut cursor).is_err());
+
+        let tokens = tokenize("REM COMMENT made by hand TITLE x REM").unwrap();
+        let mut cursor = &tokens[..];
+        match Command::parse(&mut cursor).unwrap() {
+            Command::Rem(key, value) => {
+                assert_eq!(key, "COMMENT");
+                assert_eq!(value, Token::String("made by hand".to_string()));
+            }
+            c => panic!("unexpected command: {:?}", c),
+        }
+        assert_eq!(
+            Command::parse(&mut cursor).unwrap().to_string(),
+            "TITLE \"x\""
+        );
+        assert_eq!(Command::parse(&mut cursor).unwrap().to_string(), "REM");
+        assert!(cursor.is_empty());
+
+        // A failed command leaves the cursor in place.
+        let tokens = tokenize("INDEX 01 TITLE x").unwrap();
+        let mut cursor = &tokens[..];
+        assert!(Command::parse(&mut cursor).is_err());
+        assert_eq!(cursor.len(), 4);
+    }
+
+    #[test]
+    fn trailing_tokens() {
+        let source = "FILE \"a.wav\" WAVE\n  TRACK 01 AUDIO\n    INDEX 01 00:00:00 extra junk";
+        assert_eq!(parse_cue(source).unwrap().len(), 3);
+
+        let options = ParseOptions {
+            strict: true,
+            ..Default::default()
+        };
+        match parse_cue_with_options(source, &options).unwrap_err().kind() {
+            ErrorKind::TrailingTokens(3, 23, text) => assert_eq!(text, "extra junk"),
+            e => panic!("unexpected error: {:?}", e),
+        }
+    }
+
+    #[test]
+    fn casing() {
+        let source = "  file a.wav wave\n\ttrack 01 audio\n    Flags dcp\n index 01 00:00:00";
+        let commands = parse_cue(source).unwrap();
+        assert_eq!(commands[0].to_string(), "FILE \"a.wav\" WAVE");
+        assert_eq!(commands[1].to_string(), "TRACK 01 AUDIO");
+        assert_eq!(commands[2].to_string(), "FLAGS DCP");
+
+        let options = ParseOptions {
+            strict: true,
+            ..Default::default()
+        };
+        for (source, word) in &[
+            ("file a.wav WAVE", "file"),
+            ("FILE a.wav Wave", "Wave"),
+            ("FILE a.wav WAVE\nTRACK 01 AUDIO\nFLAGS DCP pre", "pre"),
+            ("FILE a.wav WAVE\nTRACK 01 mode1/2352", "mode1/2352"),
+        ] {
+            let (_, warnings) = parse_cue_with_warnings(source, &options).unwrap();
+            assert_eq!(
+                warnings[0].kind,
+                WarningKind::NonstandardCase(word.to_string())
+            );
+        }
+    }
+
+    #[test]
+    fn zero_width() {
+        let source = "\u{200b}TI\u{ad}TLE \"Only\u{200b}Shallow\"\nPERFORMER \u{2060}MBV";
+        let commands = parse_cue(source).unwrap();
+        assert_eq!(commands[0].to_string(), "TITLE \"Only\u{200b}Shallow\"");
+        assert_eq!(commands[1].to_string(), "PERFORMER \"MBV\"");
+
+        let mut options = ParseOptions {
+            zero_width: ZeroWidth::Whitespace,
+            ..Default::default()
+        };
+        let commands = parse_cue_with_options("PERFORMER\u{2060}MBV", &options).unwrap();
+        assert_eq!(commands[0].to_string(), "PERFORMER \"MBV\"");
+        // The first line no longer contains a known command.
+        assert!(parse_cue_with_options(source, &options).is_err());
+        options.skip_unknown_commands = true;
+        assert_eq!(parse_cue_with_options(source, &options).unwrap().len(), 1);
+
+        options.zero_width = ZeroWidth::Keep;
+        assert_eq!(parse_cue_with_options(source, &options).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn unquoted_file_name() {
+        let source = "FILE 01 My Bloody Valentine - Only Shallow.wav WAVE\nFILE a.wav WAVE x y";
+        let (commands, warnings) =
+            parse_cue_with_warnings(source, &ParseOptions::default()).unwrap();
+        assert_eq!(
+            commands[0].to_string(),
+            "FILE \"01 My Bloody Valentine - Only Shallow.wav\" WAVE"
+        );
+        assert_eq!(
+            warnings[0].kind,
+            WarningKind::UnquotedFileName("01 My Bloody Valentine - Only Shallow.wav".to_string())
+        );
+        // A file format right after the name still starts the arguments.
+        assert_eq!(commands[1].to_string(), "FILE \"a.wav\" WAVE x y");
+
+        // The whitespace between the words of the name is kept.
+        let (commands, _) = parse_cue_with_warnings(
+            "FILE The  Cure\t- 01.flac   WAVE\nFILE \"a\" b.wav WAVE",
+            &ParseOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(
+            commands[0].to_string(),
+            "FILE \"The  Cure\t- 01.flac\" WAVE"
+        );
+        assert_eq!(commands[1].to_string(), "FILE \"a\" b.wav WAVE");
+
+        let options = ParseOptions {
+            strict: true,
+            ..Default::default()
+        };
+        assert!(parse_cue_with_options(source, &options).is_err());
+    }
+
+    #[test]
+    fn cdrdao_track_modes() {
+        let source = "TRACK 01 MODE1_RAW\nTRACK 02 mode2_form_mix";
+        let (commands, warnings) =
+            parse_cue_with_warnings(source, &ParseOptions::default()).unwrap();
+        assert_eq!(commands[0].to_string(), "TRACK 01 MODE1/2352");
+        assert_eq!(commands[1].to_string(), "TRACK 02 MODE2/2336");
+        assert_eq!(
+            warnings[0].kind,
+            WarningKind::NonstandardTrackType("MODE1_RAW".to_string())
+        );
+
+        let options = ParseOptions::new().strict(true);
+        assert!(parse_cue_with_options("TRACK 01 MODE1_RAW", &options).is_err());
+        let source = "FILE a.bin BINARY\nTRACK 01 CDI/2352\nINDEX 01 00:00:00";
+        assert!(parse_cue_with_options(source, &options).is_ok());
+    }
+
+    #[test]
+    fn lenient_times() {
+        let source = "INDEX 01 4:17\nPREGAP 0:2:5";
+        assert!(parse_cue(source).is_err());
+
+        let mut options = ParseOptions::new().lenient_times(true);
+        let (commands, warnings) = parse_cue_with_warnings(source, &options).unwrap();
+        assert_eq!(commands[0].to_string(), "INDEX 01 04:17:00");
+        assert_eq!(commands[1].to_string(), "PREGAP 00:02:05");
+        assert_eq!(
+            warnings[0].kind,
+            WarningKind::NonstandardTime("4:17".to_string())
+        );
+
+        options.strict = true;
+        assert!(parse_cue_with_options(source, &options).is_err());
+    }
+
+    #[test]
+    fn nonstandard_comments() {
+        let source = "; ripped with \"tool\"\nTITLE Loveless\n  // checked";
+        let (commands, warnings) =
+            parse_cue_with_warnings(source, &ParseOptions::default()).unwrap();
+        assert_eq!(commands.len(), 1);
+        assert_eq!(commands[0].to_string(), "TITLE \"Loveless\"");
+        assert_eq!(warnings.len(), 2);
+        assert_eq!(warnings[1].line, 3);
+        assert_eq!(
+            warnings[0].kind,
+            WarningKind::NonstandardComment("ripped with \"tool\"".to_string())
+        );
+
+        let mut options = ParseOptions {
+            keep_comments: true,
+            ..Default::default()
+        };
+        let commands = parse_cue_with_options(source, &options).unwrap();
+        assert_eq!(commands.len(), 3);
+        assert_eq!(commands[2].to_string(), "REM COMMENT checked");
+
+        options.strict = true;
+        assert!(parse_cue_with_options(source, &options).is_err());
+    }
+
+    #[test]
+    fn warnings() {
+        let source = "FILE a.wav\nTRACK 01 audio\nINDEX 01 00:00:80 x\nCOMPOSER y";
+        assert!(parse_cue(source).is_err());
+        let options = ParseOptions::new().skip_unknown_commands(true);
+        let (commands, warnings) = parse_cue_with_warnings(source, &options).unwrap();
+        assert_eq!(commands.len(), 3);
+        let kinds: Vec<(usize, WarningKind)> =
+            warnings.into_iter().map(|w| (w.line, w.kind)).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                (1, WarningKind::InferredFileFormat(FileFormat::Wave)),
+                (2, WarningKind::NonstandardCase("audio".to_string())),
+                (3, WarningKind::TimeOutOfRange(Time::new(0, 1, 5))),
+                (3, WarningKind::TrailingTokens("x".to_string())),
+                (4, WarningKind::UnknownCommand("COMPOSER".to_string())),
+            ]
+        );
+
+        let options = options.strict(true);
+        assert!(parse_cue_with_options("INDEX 01 00:00:80", &options).is_err());
+        match parse_cue_with_options("COMPOSER y", &options)
+            .unwrap_err()
+            .kind()
+        {
+            ErrorKind::UnknownCommand(c) => assert_eq!(c, "COMPOSER"),
+            e => panic!("unexpected error: {:?}", e),
+        }
+    }
+
+    #[test]
+    fn file_without_format() {
+        let commands = parse_cue("FILE \"a b.mp3\"").unwrap();
+        match commands[0] {
+            Command::File(ref name, FileFormat::Mp3, _) => assert_eq!(name, "a b.mp3"),
+            ref c => panic!("unexpected command: {:?}", c),
+        }
+
+        assert!(parse_cue("FILE \"a.xyz\"").is_err());
+
+        let options = ParseOptions {
+            strict: true,
+            ..Default::default()
+        };
+        assert!(parse_cue_with_options("FILE \"a.mp3\"", &options).is_err());
+    }
+
+    #[test]
+    fn nonstandard_file_format() {
+        let (commands, warnings) =
+            parse_cue_with_warnings("FILE \"a.flac\" flac", &ParseOptions::default()).unwrap();
+        match commands[0] {
+            Command::File(_, FileFormat::Other(ref keyword), _) => assert_eq!(keyword, "flac"),
+            ref c => panic!("unexpected command: {:?}", c),
+        }
+        assert_eq!(commands[0].to_string(), "FILE \"a.flac\" flac");
+        assert_eq!(
+            warnings[0].kind,
+            WarningKind::NonstandardFileFormat("flac".to_string())
+        );
+
+        let options = ParseOptions {
+            strict: true,
+            ..Default::default()
+        };
+        assert!(parse_cue_with_options("FILE \"a.flac\" FLAC", &options).is_err());
+    }
+
+    #[test]
+    fn file_arguments() {
+        let source = "FILE \"data.bin\" BINARY 0 00:02:00 \"swap bytes\"";
+        let (commands, warnings) =
+            parse_cue_with_warnings(source, &ParseOptions::default()).unwrap();
+        match commands[0] {
+            Command::File(_, FileFormat::Binary, ref arguments) => assert_eq!(arguments.len(), 3),
+            ref c => panic!("unexpected command: {:?}", c),
+        }
+        assert_eq!(commands[0].to_string(), source);
+        assert_eq!(
+            warnings[0].kind,
+            WarningKind::FileArguments(vec![
+                "0".to_string(),
+                "00:02:00".to_string(),
+                "swap bytes".to_string()
+            ])
+        );
+
+        let options = ParseOptions {
+            strict: true,
+            ..Default::default()
+        };
+        match parse_cue_with_options(source, &options).unwrap_err().kind() {
+            ErrorKind::TrailingTokens(1, 24, text) => assert_eq!(text, "0 00:02:00 \"swap bytes\""),
+            e => panic!("unexpected error: {:?}", e),
+        }
+    }
+
+    #[test]
+    fn duplicate_flags() {
+        let options = ParseOptions::default();
+        let (commands, warnings) = parse_cue_with_warnings("FLAGS DCP PRE DCP", &options).unwrap();
+        assert_eq!(commands[0].to_string(), "FLAGS DCP PRE");
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].kind, WarningKind::DuplicateFlag(TrackFlag::Dcp));
+    }
+
+    #[test]
+    fn time_overflow() {
+        let options = ParseOptions::default();
+        assert!(parse_cue_with_options("INDEX 01 2147483647:59:74", &options).is_ok());
+        assert!(parse_cue_with_options("INDEX 01 2147483647:60:00", &options).is_err());
+        assert!(parse_cue_with_options("INDEX 01 2147483648:00:00", &options).is_err());
+        assert!(parse_cue_with_options("PREGAP 99999999999999999999:00:00", &options).is_err());
+    }
+
+    #[test]
+    fn catalog_checksum() {
+        let source = "CATALOG 0724384264525";
+        let mut options = ParseOptions::default();
+        assert!(parse_cue_with_warnings(source, &options)
+            .unwrap()
+            .1
+            .is_empty());
+
+        options.check_catalog = true;
+        let (commands, warnings) = parse_cue_with_warnings(source, &options).unwrap();
+        assert_eq!(commands[0].to_string(), source);
+        assert_eq!(
+            warnings[0].kind,
+            WarningKind::InvalidCatalog("0724384264525".to_string())
+        );
+
+        options.strict = true;
+        let warnings = parse_cue_with_warnings("CATALOG 0724384264529", &options)
+            .unwrap()
+            .1;
+        assert!(warnings.is_empty());
+    }
+
+    #[test]
+    fn frame_rate() {
+        let mut options = ParseOptions::default();
+        let (commands, warnings) = parse_cue_with_warnings("INDEX 01 00:01:99", &options).unwrap();
+        assert_eq!(commands[0].to_string(), "INDEX 01 00:02:24");
+        assert_eq!(warnings.len(), 1);
+
+        options.frame_rate = Some(100);
+        let (commands, warnings) = parse_cue_with_warnings("INDEX 01 00:01:99", &options).unwrap();
+        assert!(warnings.is_empty());
+        match commands[0] {
+            Command::Index(1, ref time) => {
+                assert_eq!(time.frames(), 99);
+                assert_eq!(time.to_samples_at(48000, 100), Some(95520));
+            }
+            ref c => panic!("unexpected command: {:?}", c),
+        }
+    }
+
+    #[test]
+    fn numeric_strings() {
+        let commands = parse_cue("TRACK 01 AUDIO\nTITLE 99\nPERFORMER \"99\"\nISRC 12").unwrap();
+        assert_eq!(commands[1].to_string(), "TITLE \"99\"");
+        assert_eq!(commands[2].to_string(), "PERFORMER \"99\"");
+        match commands[3] {
+            Command::Isrc(ref isrc) => assert_eq!(isrc, "12"),
+            ref c => panic!("unexpected command: {:?}", c),
+        }
+
+        let commands = parse_cue(
+            "TITLE 12:00:00
+PERFORMER 4:17
+SONGWRITER 1:2:3",
+        )
+        .unwrap();
+        assert_eq!(commands[0].to_string(), "TITLE \"12:00:00\"");
+        assert_eq!(commands[1].to_string(), "PERFORMER \"4:17\"");
+        assert_eq!(commands[2].to_string(), "SONGWRITER \"1:2:3\"");
+    }
+
+    #[test]
+    fn embedded_quotes() {
+        let commands = vec![
+            Command::Title("The \"Loveless\" Sessions".to_string()),
+            Command::Performer("\"".to_string()),
+            Command::Songwriter("Kevin \"K\" Shields".to_string()),
+            Command::Cdtextfile("C:\\Rips\\".to_string()),
+            Command::File("a \"b\".wav".to_string(), FileFormat::Wave, Vec::new()),
+        ];
+        for command in commands {
+            let reparsed = parse_cue(&command.to_string()).unwrap();
+            assert_eq!(reparsed, vec![command]);
+        }
+    }
+
+    #[test]
+    fn rem() {
+        let commands = parse_cue(
+            "REM\n\
+             REM COMMENT ExactAudioCopy v0.95b4\n\
+             REM DATE 1991\n\
+             REM GENRE \"Shoegaze\"\n\
+             REM COMMENT \"Ripped by\"  EAC",
+        )
+        .unwrap();
+
+        let rems: Vec<(String, Token)> = commands
+            .into_iter()
+            .map(|c| match c {
+                Command::Rem(key, value) => (key, value),
+                c => panic!("unexpected command: {:?}", c),
+            })
+            .collect();
+        assert_eq!(
+            rems,
+            vec![
+                (String::new(), Token::String(String::new())),
+                (
+                    "COMMENT".to_string(),
+                    Token::String("ExactAudioCopy v0.95b4".to_string()),
+                ),
+                ("DATE".to_string(), Token::String("1991".to_string())),
+                ("GENRE".to_string(), Token::String("Shoegaze".to_string())),
+                (
+                    "COMMENT".to_string(),
+                    Token::String("\"Ripped by\"  EAC".to_string()),
+                ),
+            ]
+        );
+
+        for (key, value) in &rems {
+            let command = Command::Rem(key.clone(), value.clone());
+            let reparsed = parse_cue(&command.to_string()).unwrap();
+            assert_eq!(reparsed[0], command);
         }
     }
 }
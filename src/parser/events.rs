@@ -0,0 +1,329 @@
+// cue_sheet
+// Copyright (C) 2017  Leonardo Schwarz <mail@leoschwarz.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! A stream of events describing the structure of a cue sheet.
+//!
+//! The events tell which part of the sheet a command belongs to, to build custom models without
+//! tracking the context of the commands like `Tracklist` does.
+
+use super::{parse_line, Command, CommandLine, FileFormat, ParseOptions, Token, TrackType};
+use errors::Error;
+use std::collections::VecDeque;
+use std::iter::Enumerate;
+use std::str::Lines;
+use warning::Warning;
+
+/// An event in the structure of a cue sheet, see `parse_events`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ParseEvent {
+    /// A command describing the disc.
+    ///
+    /// `CATALOG` and `CDTEXTFILE` can only describe the disc, so they are reported as this
+    /// event wherever they appear.
+    HeaderCommand(Command),
+
+    /// A `FILE` command, starting the description of a file.
+    FileStart {
+        /// Name of the file.
+        name: String,
+        /// Format of the file.
+        format: FileFormat,
+        /// Arguments following the format, see `Command::File`.
+        arguments: Vec<Token>,
+    },
+
+    /// A command following a `FILE` before its first `TRACK`.
+    ///
+    /// In a valid cue sheet these are `INDEX` commands, continuing the last track of the
+    /// previous file whose pregap was stored at the end of that file.
+    FileCommand(Command),
+
+    /// A `TRACK` command, starting the description of a track.
+    TrackStart {
+        /// Number of the track.
+        number: u32,
+        /// Type of the track.
+        track_type: TrackType,
+    },
+
+    /// A command describing the last track which was started in the current file.
+    TrackCommand(Command),
+
+    /// The end of the description of a file, before the next `FILE` or at the end of the sheet.
+    FileEnd,
+}
+
+/// An event together with the line (starting at 1) it was read from.
+pub type EventLine = (ParseEvent, usize);
+
+/// The context of the commands converted into events so far.
+#[derive(Clone, Debug, Default)]
+struct Context {
+    in_file: bool,
+    in_track: bool,
+    last_line: usize,
+}
+
+impl Context {
+    /// Append the events for `command` on `line` to `events`.
+    fn push(&mut self, command: Command, line: usize, events: &mut VecDeque<EventLine>) {
+        let event = match command {
+            Command::File(name, format, arguments) => {
+                if self.in_file {
+                    events.push_back((ParseEvent::FileEnd, self.last_line));
+                }
+                self.in_file = true;
+                self.in_track = false;
+                ParseEvent::FileStart {
+                    name,
+                    format,
+                    arguments,
+                }
+            }
+            Command::Track(number, track_type) => {
+                self.in_track = true;
+                ParseEvent::TrackStart { number, track_type }
+            }
+            command @ Command::Catalog(_) | command @ Command::Cdtextfile(_) => {
+                ParseEvent::HeaderCommand(command)
+            }
+            command if self.in_track => ParseEvent::TrackCommand(command),
+            command if self.in_file => ParseEvent::FileCommand(command),
+            command => ParseEvent::HeaderCommand(command),
+        };
+        events.push_back((event, line));
+        self.last_line = line;
+    }
+
+    /// Append the events ending the sheet to `events`.
+    fn finish(&mut self, events: &mut VecDeque<EventLine>) {
+        if self.in_file {
+            events.push_back((ParseEvent::FileEnd, self.last_line));
+            self.in_file = false;
+        }
+    }
+}
+
+impl ParseEvent {
+    /// Convert `commands` into events.
+    ///
+    /// Every event is paired with the line of the command it was created from, `FileEnd` with
+    /// the line of the last command describing the file.
+    pub fn from_commands(commands: Vec<CommandLine>) -> Vec<EventLine> {
+        let mut context = Context::default();
+        let mut events = VecDeque::new();
+        for (command, line) in commands {
+            context.push(command, line, &mut events);
+        }
+        context.finish(&mut events);
+        events.into_iter().collect()
+    }
+}
+
+/// Iterator over the events of a cue sheet, see `parse_events`.
+#[derive(Clone, Debug)]
+pub struct ParseEvents<'a> {
+    lines: Enumerate<Lines<'a>>,
+    options: &'a ParseOptions,
+    context: Context,
+    pending: VecDeque<EventLine>,
+    warnings: Vec<Warning>,
+    done: bool,
+}
+
+impl<'a> ParseEvents<'a> {
+    /// Returns the warnings about the problems recovered from in the lines read so far.
+    pub fn warnings(&self) -> &[Warning] {
+        &self.warnings
+    }
+}
+
+impl<'a> Iterator for ParseEvents<'a> {
+    type Item = Result<EventLine, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(event) = self.pending.pop_front() {
+                return Some(Ok(event));
+            }
+            if self.done {
+                return None;
+            }
+            match self.lines.next() {
+                Some((i, line)) => {
+                    match parse_line(line, i + 1, self.options, &mut self.warnings) {
+                        Ok(Some(command)) => self.context.push(command, i + 1, &mut self.pending),
+                        Ok(None) => {}
+                        Err(e) => {
+                            self.done = true;
+                            return Some(Err(e));
+                        }
+                    }
+                }
+                None => {
+                    self.done = true;
+                    self.context.finish(&mut self.pending);
+                }
+            }
+        }
+    }
+}
+
+/// Parse the cue sheet `source` using the provided `options` into events, which are read one
+/// line at a time. Warnings about the problems which were recovered from are available from
+/// `ParseEvents::warnings`, an error ends the iteration.
+///
+/// Since the lines are parsed independently, the checks of strict mode which depend on other
+/// lines (like the placement of commands) are not made. To have them made, parse the sheet with
+/// `parse_cue_with_lines` and convert the commands with `ParseEvent::from_commands`.
+///
+/// Outside of strict mode a `TRACK` might appear before the first `FILE`, in which case its
+/// `TrackStart` is not preceded by a `FileStart`.
+///
+/// ```
+/// use cue_sheet::parser::{parse_events, ParseEvent, ParseOptions};
+///
+/// let source = "TITLE Loveless\nFILE a.wav WAVE\nTRACK 01 AUDIO\nTITLE \"Only Shallow\"";
+/// let options = ParseOptions::default();
+/// let names: Vec<&str> = parse_events(source, &options)
+///     .map(|event| match event.unwrap().0 {
+///         ParseEvent::HeaderCommand(_) => "header",
+///         ParseEvent::FileStart { .. } => "file start",
+///         ParseEvent::FileCommand(_) => "file",
+///         ParseEvent::TrackStart { .. } => "track start",
+///         ParseEvent::TrackCommand(_) => "track",
+///         ParseEvent::FileEnd => "file end",
+///     })
+///     .collect();
+/// assert_eq!(
+///     names,
+///     vec!["header", "file start", "track start", "track", "file end"]
+/// );
+/// ```
+pub fn parse_events<'a>(source: &'a str, options: &'a ParseOptions) -> ParseEvents<'a> {
+    ParseEvents {
+        lines: source.lines().enumerate(),
+        options,
+        context: Context::default(),
+        pending: VecDeque::new(),
+        warnings: Vec::new(),
+        done: false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parser::{parse_cue_with_lines, Time};
+
+    fn events(source: &str) -> Vec<EventLine> {
+        parse_events(source, &Default::default())
+            .collect::<Result<_, _>>()
+            .unwrap()
+    }
+
+    #[test]
+    fn file_events() {
+        let source = "PERFORMER MBV\nFILE a.wav WAVE\nTRACK 01 AUDIO\nINDEX 00 00:00:00\n\
+                      FILE b.wav WAVE\nINDEX 01 00:00:00\nCATALOG 0724384264525\n\
+                      TRACK 02 AUDIO\nINDEX 01 04:17:52\n\nFILE c.wav WAVE";
+        let file = |name: &str| ParseEvent::FileStart {
+            name: name.to_string(),
+            format: FileFormat::Wave,
+            arguments: Vec::new(),
+        };
+        let track = |number| ParseEvent::TrackStart {
+            number,
+            track_type: TrackType::Audio,
+        };
+        let expected = vec![
+            (
+                ParseEvent::HeaderCommand(Command::Performer("MBV".to_string())),
+                1,
+            ),
+            (file("a.wav"), 2),
+            (track(1), 3),
+            (
+                ParseEvent::TrackCommand(Command::Index(0, Time::new(0, 0, 0))),
+                4,
+            ),
+            (ParseEvent::FileEnd, 4),
+            (file("b.wav"), 5),
+            (
+                ParseEvent::FileCommand(Command::Index(1, Time::new(0, 0, 0))),
+                6,
+            ),
+            (
+                ParseEvent::HeaderCommand(Command::Catalog("0724384264525".to_string())),
+                7,
+            ),
+            (track(2), 8),
+            (
+                ParseEvent::TrackCommand(Command::Index(1, Time::new(4, 17, 52))),
+                9,
+            ),
+            (ParseEvent::FileEnd, 9),
+            (file("c.wav"), 11),
+            (ParseEvent::FileEnd, 11),
+        ];
+        assert_eq!(events(source), expected);
+
+        let (commands, _) = parse_cue_with_lines(source, &Default::default()).unwrap();
+        assert_eq!(ParseEvent::from_commands(commands), expected);
+    }
+
+    #[test]
+    fn track_without_file() {
+        let source = "TITLE Loveless\nTRACK 01 AUDIO\nTITLE \"Only Shallow\"";
+        assert_eq!(
+            events(source),
+            vec![
+                (
+                    ParseEvent::HeaderCommand(Command::Title("Loveless".to_string())),
+                    1
+                ),
+                (
+                    ParseEvent::TrackStart {
+                        number: 1,
+                        track_type: TrackType::Audio,
+                    },
+                    2
+                ),
+                (
+                    ParseEvent::TrackCommand(Command::Title("Only Shallow".to_string())),
+                    3
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn lazy_events() {
+        let source = "title Loveless\nFILE a.wav WAVE\nTRACK 01 AUDIO\nINDEX 01 00:00";
+        let options = ParseOptions::default();
+        let mut events = parse_events(source, &options);
+        assert_eq!(
+            events.next().unwrap().unwrap(),
+            (
+                ParseEvent::HeaderCommand(Command::Title("Loveless".to_string())),
+                1
+            )
+        );
+        assert_eq!(events.warnings().len(), 1);
+        assert!(events.nth(2).unwrap().is_err());
+        assert!(events.next().is_none());
+    }
+}
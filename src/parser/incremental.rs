@@ -0,0 +1,182 @@
+// cue_sheet
+// Copyright (C) 2017  Leonardo Schwarz <mail@leoschwarz.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Reparsing of cue sheets after edits, e.g. for live validation in an editor.
+
+use super::{check_sheet, parse_line, Command, CommandLine, ParseOptions};
+use errors::Error;
+use std::ops::Range;
+use warning::{Warning, WarningKind};
+
+/// The result of parsing one line.
+#[derive(Clone, Debug)]
+struct Line {
+    text: String,
+    command: Option<Command>,
+    warnings: Vec<WarningKind>,
+    failed: bool,
+}
+
+impl Line {
+    fn parse(text: &str, options: &ParseOptions) -> Line {
+        let mut warnings = Vec::new();
+        // The line number is only used in errors, which `IncrementalParse::result` recreates.
+        let (command, failed) = match parse_line(text, 0, options, &mut warnings) {
+            Ok(command) => (command, false),
+            Err(_) => (None, true),
+        };
+        Line {
+            text: text.to_string(),
+            command,
+            warnings: warnings.into_iter().map(|w| w.kind).collect(),
+            failed,
+        }
+    }
+}
+
+/// A parsed cue sheet which can be edited, only the changed lines are parsed again.
+///
+/// Since every command occupies exactly one line, lines are tokenized and parsed independently
+/// of each other. Only the placement of commands (in strict mode) is checked for the whole
+/// sheet, which is cheap compared to parsing.
+///
+/// ```
+/// use cue_sheet::parser::{IncrementalParse, ParseOptions};
+///
+/// let mut parse = IncrementalParse::new(
+///     "TITLE Loveless\nFILE a.wav WAVE\nTRACK 01 AUDIO\nINDEX 01 00:00:00",
+///     &ParseOptions::default(),
+/// );
+/// assert_eq!(parse.edit(3..4, "TRACK 01 AUDIO\nTITLE \"Only Shallow\""), 2);
+///
+/// let (commands, _) = parse.result().unwrap();
+/// assert_eq!(commands.len(), 5);
+/// assert_eq!(commands[3].1, 4);
+/// assert_eq!(parse.source(), "TITLE Loveless\nFILE a.wav WAVE\nTRACK 01 AUDIO\n\
+///                             TITLE \"Only Shallow\"\nINDEX 01 00:00:00");
+/// ```
+#[derive(Clone, Debug)]
+pub struct IncrementalParse {
+    options: ParseOptions,
+    lines: Vec<Line>,
+}
+
+impl IncrementalParse {
+    /// Parse `source` using the provided `options`.
+    pub fn new(source: &str, options: &ParseOptions) -> IncrementalParse {
+        IncrementalParse {
+            options: options.clone(),
+            lines: source
+                .lines()
+                .map(|line| Line::parse(line, options))
+                .collect(),
+        }
+    }
+
+    /// Returns the number of lines.
+    pub fn len(&self) -> usize {
+        self.lines.len()
+    }
+
+    /// Returns whether there are no lines.
+    pub fn is_empty(&self) -> bool {
+        self.lines.is_empty()
+    }
+
+    /// Replace the `lines` (starting at 1, e.g. `2..4` for lines 2 and 3) with `text`, which may
+    /// consist of any number of lines.
+    ///
+    /// An empty range like `2..2` inserts `text` before line 2, an empty `text` removes the
+    /// lines. Returns the number of lines which were parsed.
+    ///
+    /// # Panics
+    ///
+    /// If `lines` starts at 0 or reaches beyond the line after the last one.
+    pub fn edit(&mut self, lines: Range<usize>, text: &str) -> usize {
+        assert!(lines.start >= 1, "lines start at 1");
+        let replacement: Vec<Line> = text
+            .lines()
+            .map(|line| Line::parse(line, &self.options))
+            .collect();
+        let parsed = replacement.len();
+        self.lines
+            .splice(lines.start - 1..lines.end - 1, replacement);
+        parsed
+    }
+
+    /// Returns the current source.
+    pub fn source(&self) -> String {
+        let lines: Vec<&str> = self.lines.iter().map(|line| line.text.as_str()).collect();
+        lines.join("\n")
+    }
+
+    /// Returns the commands paired with their lines and the warnings, like
+    /// `parse_cue_with_lines` would for the current source.
+    pub fn result(&self) -> Result<(Vec<CommandLine>, Vec<Warning>), Error> {
+        let mut commands = Vec::new();
+        let mut warnings = Vec::new();
+
+        for (i, line) in self.lines.iter().enumerate() {
+            if line.failed {
+                // Parse the line again to obtain the error with the right line number.
+                parse_line(&line.text, i + 1, &self.options, &mut Vec::new())?;
+            }
+            if let Some(ref command) = line.command {
+                commands.push((command.clone(), i + 1));
+            }
+            warnings.extend(line.warnings.iter().map(|kind| Warning {
+                line: i + 1,
+                kind: kind.clone(),
+            }));
+        }
+
+        check_sheet(&commands, &self.options, &mut warnings)?;
+        Ok((commands, warnings))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use errors::ErrorKind;
+    use parser::parse_cue_with_lines;
+
+    #[test]
+    fn matches_full_parse() {
+        let options = ParseOptions {
+            strict: true,
+            ..Default::default()
+        };
+        let mut parse = IncrementalParse::new("FILE a.wav WAVE\nTRACK 01 AUDIO\n", &options);
+        parse.edit(3..3, "INDEX 01 00:00:00 x");
+        match parse.result().unwrap_err().kind() {
+            ErrorKind::TrailingTokens(3, 19, _) => {}
+            e => panic!("unexpected error: {:?}", e),
+        }
+
+        parse.edit(3..4, "INDEX 01 00:00:00\nTRACK 02 AUDIO\nINDEX 01 04:17:52");
+        parse.edit(1..1, "TITLE Loveless x");
+        assert!(parse.result().is_err());
+        parse.edit(1..2, "");
+        assert_eq!(parse.len(), 5);
+
+        let (commands, _) = parse.result().unwrap();
+        let (expected, _) = parse_cue_with_lines(&parse.source(), &options).unwrap();
+        let lines: Vec<usize> = commands.iter().map(|&(_, line)| line).collect();
+        let expected: Vec<usize> = expected.iter().map(|&(_, line)| line).collect();
+        assert_eq!(lines, expected);
+    }
+}
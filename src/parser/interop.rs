@@ -0,0 +1,136 @@
+// cue_sheet
+// Copyright (C) 2017  Leonardo Schwarz <mail@leoschwarz.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Conversions between `Time` and the duration types of the `chrono` and `time` crates.
+//!
+//! Frames don't correspond to a whole number of nanoseconds, so conversions round to the
+//! nearest nanosecond or frame respectively. Converting a `Time` to a duration and back always
+//! yields the original value.
+
+use super::{Time, FPS};
+#[cfg(feature = "chrono")]
+use chrono;
+use errors::Error;
+use std::convert::TryFrom;
+#[cfg(feature = "time")]
+use time;
+
+const NANOS_PER_SEC: i128 = 1_000_000_000;
+
+/// Returns `numerator / denominator` rounded to the nearest integer.
+fn div_round(numerator: i128, denominator: i128) -> i128 {
+    (2 * numerator + denominator).div_euclid(2 * denominator)
+}
+
+/// Returns the length of `time` as (seconds, nanoseconds) with the same sign.
+fn to_parts(time: &Time) -> (i64, i32) {
+    let nanos = div_round(
+        i128::from(time.total_frames()) * NANOS_PER_SEC,
+        i128::from(FPS),
+    );
+    (
+        (nanos / NANOS_PER_SEC) as i64,
+        (nanos % NANOS_PER_SEC) as i32,
+    )
+}
+
+/// Create a `Time` from a length of `secs` seconds and `nanos` nanoseconds.
+fn from_parts(secs: i64, nanos: i32) -> Result<Time, Error> {
+    let nanos = i128::from(secs) * NANOS_PER_SEC + i128::from(nanos);
+    let frames = div_round(nanos * i128::from(FPS), NANOS_PER_SEC);
+    i64::try_from(frames)
+        .ok()
+        .and_then(Time::checked_from_frames)
+        .ok_or_else(|| "Duration is too long to be represented as a Time.".into())
+}
+
+#[cfg(feature = "chrono")]
+impl From<Time> for chrono::Duration {
+    fn from(time: Time) -> chrono::Duration {
+        let (secs, nanos) = to_parts(&time);
+        chrono::Duration::seconds(secs) + chrono::Duration::nanoseconds(i64::from(nanos))
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl TryFrom<chrono::Duration> for Time {
+    type Error = Error;
+
+    fn try_from(duration: chrono::Duration) -> Result<Time, Error> {
+        let secs = duration.num_seconds();
+        let nanos = (duration - chrono::Duration::seconds(secs))
+            .num_nanoseconds()
+            .unwrap_or(0);
+        from_parts(secs, nanos as i32)
+    }
+}
+
+#[cfg(feature = "time")]
+impl From<Time> for time::Duration {
+    fn from(time: Time) -> time::Duration {
+        let (secs, nanos) = to_parts(&time);
+        time::Duration::new(secs, nanos)
+    }
+}
+
+#[cfg(feature = "time")]
+impl TryFrom<time::Duration> for Time {
+    type Error = Error;
+
+    fn try_from(duration: time::Duration) -> Result<Time, Error> {
+        from_parts(duration.whole_seconds(), duration.subsec_nanoseconds())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parts() {
+        assert_eq!(to_parts(&Time::new(0, 1, 1)), (1, 13_333_333));
+        assert_eq!(to_parts(&Time::new(0, 0, 2)), (0, 26_666_667));
+        assert_eq!(to_parts(&Time::from_frames(-1)), (0, -13_333_333));
+        assert_eq!(from_parts(1, 13_333_333).unwrap(), Time::new(0, 1, 1));
+        assert_eq!(from_parts(0, 6_000_000).unwrap(), Time::new(0, 0, 0));
+        assert_eq!(from_parts(0, 7_000_000).unwrap(), Time::new(0, 0, 1));
+        assert!(from_parts(i64::MAX, 0).is_err());
+
+        for frames in &[0, 1, 74, 75, 4653, -4653, 9_663_676_411_499] {
+            let time = Time::from_frames(*frames);
+            let (secs, nanos) = to_parts(&time);
+            assert_eq!(from_parts(secs, nanos).unwrap(), time);
+        }
+    }
+
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn chrono() {
+        let time = Time::new(4, 17, 52);
+        let duration = chrono::Duration::from(time.clone());
+        assert_eq!(duration.num_milliseconds(), 257_693);
+        assert_eq!(Time::try_from(duration).unwrap(), time);
+    }
+
+    #[cfg(feature = "time")]
+    #[test]
+    fn time() {
+        let time = Time::new(4, 17, 52);
+        let duration = time::Duration::from(time.clone());
+        assert_eq!(duration.whole_milliseconds(), 257_693);
+        assert_eq!(Time::try_from(duration).unwrap(), time);
+    }
+}
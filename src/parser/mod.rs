@@ -16,30 +16,58 @@
 
 //! Parsing of cue sheets. Also contains some data types.
 
-use errors::Error;
+use cleanup::MetadataCleanup;
+use errors::{Error, ErrorKind};
+use split::Rounding;
+use std::borrow::Cow;
 use std::cmp::Ordering;
+use std::convert::TryFrom;
 use std::fmt;
 use std::ops::Sub;
+use std::path::Path;
 use std::str::FromStr;
+use std::sync::OnceLock;
+use tracklist::DurationMode;
+use warning::{Warning, WarningKind};
+
+pub mod scanner;
 
 mod tokenization;
-use self::tokenization::tokenize;
-pub use self::tokenization::Token;
+pub(crate) use self::tokenization::Quoted;
+pub use self::tokenization::{tokenize, tokenize_with_spans, Span, Token, MAX_STRING_LENGTH};
 
 mod command;
-pub use self::command::Command;
+pub use self::command::{Command, SpelledCommand};
+
+mod placement;
+use self::placement::{check_placement, duplicate_disc_commands};
+
+mod incremental;
+pub use self::incremental::IncrementalParse;
+
+mod events;
+pub use self::events::{parse_events, EventLine, ParseEvent, ParseEvents};
+
+#[cfg(any(feature = "chrono", feature = "time"))]
+mod interop;
 
 /// Number of audio frames/sectors per second in cue sheets.
 ///
 /// This value is supposed to be fixed for all cue sheets to 75 frames per second.
 /// TODO: Double-check, how does this interact with the media type?
-const FPS: i64 = 75;
+pub(crate) const FPS: i64 = 75;
+
+/// Returns the number of frames per second for a nonstandard `frame_rate`, see
+/// `ParseOptions::frame_rate`.
+pub(crate) fn frames_per_second(frame_rate: Option<u8>) -> u8 {
+    frame_rate.unwrap_or(FPS as u8)
+}
 
 /// Time representation of the format `mm:ss:ff`.
 ///
 /// Where mm = minutes, ss = seconds, ff = frames/sectors.
-/// There are 75 frames per second, 60 seconds per minute.
-#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+/// There are 75 frames per second, 60 seconds per minute. The default is `00:00:00`.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Hash)]
 pub struct Time {
     /// Minutes time component.
     mins: i32,
@@ -57,7 +85,7 @@ impl Time {
         Time {
             mins: minutes,
             secs: seconds,
-            frames: frames,
+            frames,
         }
     }
 
@@ -66,6 +94,29 @@ impl Time {
         format!("{:02}:{:02}", self.mins, self.secs)
     }
 
+    /// Format as `h:mm:ss` dropping the remaining frames, useful for times longer than an hour.
+    ///
+    /// ```
+    /// use cue_sheet::parser::Time;
+    ///
+    /// assert_eq!(Time::new(123, 45, 67).to_string_hms(), "2:03:45");
+    /// assert_eq!(Time::new(4, 17, 52).to_string_hms(), "0:04:17");
+    /// ```
+    pub fn to_string_hms(&self) -> String {
+        format!("{}:{:02}:{:02}", self.hours(), self.mins % 60, self.secs)
+    }
+
+    /// Returns the number of full hours represented by this instance.
+    ///
+    /// ```
+    /// use cue_sheet::parser::Time;
+    ///
+    /// assert_eq!(Time::new(123, 45, 67).hours(), 2);
+    /// ```
+    pub fn hours(&self) -> i32 {
+        self.mins / 60
+    }
+
     /// Returns the "minutes" component of this instance.
     ///
     /// ```
@@ -155,17 +206,346 @@ impl Time {
     /// let time = Time::from_frames(200);
     /// assert_eq!(time, Time::new(0, 2, 50));
     /// ```
+    ///
+    /// # Panics
+    ///
+    /// If the number of minutes doesn't fit into an `i32`, see `Time::checked_from_frames`.
     pub fn from_frames(from: i64) -> Time {
+        Time::checked_from_frames(from).expect("Time::from_frames overflowed")
+    }
+
+    /// Create an instance for the specified number of frames/sectors, returning `None` if the
+    /// number of minutes doesn't fit into an `i32`.
+    ///
+    /// ```
+    /// use cue_sheet::parser::Time;
+    ///
+    /// assert_eq!(Time::checked_from_frames(200), Some(Time::new(0, 2, 50)));
+    /// assert_eq!(Time::checked_from_frames(i64::MAX), None);
+    /// ```
+    pub fn checked_from_frames(from: i64) -> Option<Time> {
         let frames = from % FPS;
         let secs_all = from / FPS;
         let secs = secs_all % 60;
         let mins = secs_all / 60;
 
-        Time {
+        if mins > i64::from(i32::MAX) || mins < i64::from(i32::MIN) {
+            return None;
+        }
+
+        Some(Time {
             mins: mins as i32,
             secs: secs as i8,
             frames: frames as i8,
+        })
+    }
+
+    /// Returns the sum of both times, or `None` if the number of minutes doesn't fit into an
+    /// `i32`.
+    ///
+    /// ```
+    /// use cue_sheet::parser::Time;
+    ///
+    /// let time = Time::new(4, 17, 52);
+    /// assert_eq!(time.checked_add(&Time::new(0, 2, 30)), Some(Time::new(4, 20, 7)));
+    /// assert_eq!(Time::new(i32::MAX, 59, 0).checked_add(&Time::new(0, 1, 0)), None);
+    /// ```
+    pub fn checked_add(&self, other: &Time) -> Option<Time> {
+        Time::checked_from_frames(self.total_frames() + other.total_frames())
+    }
+
+    /// Returns the difference of both times, or `None` if `other` is later than this time or the
+    /// number of minutes doesn't fit into an `i32`.
+    ///
+    /// ```
+    /// use cue_sheet::parser::Time;
+    ///
+    /// let a = Time::new(0, 2, 0);
+    /// let b = Time::new(0, 0, 74);
+    /// assert_eq!(a.checked_sub(&b), Some(Time::new(0, 1, 1)));
+    /// assert_eq!(b.checked_sub(&a), None);
+    /// ```
+    pub fn checked_sub(&self, other: &Time) -> Option<Time> {
+        match self.signed_diff(other) {
+            diff if diff < 0 => None,
+            diff => Time::checked_from_frames(diff),
+        }
+    }
+
+    /// Returns the difference of both times in frames, which is negative if `other` is later than
+    /// this time.
+    ///
+    /// ```
+    /// use cue_sheet::parser::Time;
+    ///
+    /// assert_eq!(Time::new(0, 1, 0).signed_diff(&Time::new(0, 0, 70)), 5);
+    /// assert_eq!(Time::new(0, 0, 70).signed_diff(&Time::new(0, 1, 0)), -5);
+    /// ```
+    pub fn signed_diff(&self, other: &Time) -> i64 {
+        self.total_frames() - other.total_frames()
+    }
+
+    /// Parse a time like `FromStr`, but also accept single digit fields and times without
+    /// frames, e.g. `4:17` or `4:7:5`, as found in chapter lists.
+    ///
+    /// Seconds have to be below 60 and frames below 75.
+    ///
+    /// ```
+    /// use cue_sheet::parser::Time;
+    ///
+    /// assert_eq!(Time::parse_lenient("04:17").unwrap(), Time::new(4, 17, 0));
+    /// assert_eq!(Time::parse_lenient("4:7:5").unwrap(), Time::new(4, 7, 5));
+    /// assert!(Time::parse_lenient("4").is_err());
+    /// assert!(Time::parse_lenient("4:170").is_err());
+    /// assert!(Time::parse_lenient("4:99").is_err());
+    /// assert!(Time::parse_lenient("0:30:99").is_err());
+    /// ```
+    pub fn parse_lenient(s: &str) -> Result<Time, Error> {
+        let time = Time::parse_lenient_unchecked(s)?;
+        if time.secs >= 60 || i64::from(time.frames) >= FPS {
+            return Err(format!("Time out of range: {:?}", s).into());
+        }
+        Ok(time)
+    }
+
+    /// Parse a time like `Time::parse_lenient` without checking that seconds and frames are in
+    /// range, which is left to the caller.
+    pub(crate) fn parse_lenient_unchecked(s: &str) -> Result<Time, Error> {
+        let invalid = || Error::from(format!("Invalid time: {:?}", s));
+        let parts: Vec<&str> = s.split(':').collect();
+        let digits = |p: &&str| !p.is_empty() && p.chars().all(|c| c.is_ascii_digit());
+        if parts.len() < 2
+            || parts.len() > 3
+            || !parts.iter().all(digits)
+            || parts[1..].iter().any(|p| p.len() > 2)
+        {
+            return Err(invalid());
+        }
+
+        Ok(Time {
+            mins: parts[0].parse().map_err(|_| invalid())?,
+            secs: parts[1].parse()?,
+            frames: parts.get(2).map_or(Ok(0), |p| p.parse())?,
+        })
+    }
+
+    /// Parse a human-friendly time like `4:17.693` (`mm:ss`), `1:04:17` (`h:mm:ss`) or `257.693`
+    /// (seconds), where the seconds may have a decimal fraction.
+    ///
+    /// The fraction is rounded to the nearest frame, see `Time::parse_flexible_rounded`.
+    ///
+    /// ```
+    /// use cue_sheet::parser::Time;
+    ///
+    /// assert_eq!(Time::parse_flexible("4:17.693").unwrap(), Time::new(4, 17, 52));
+    /// assert_eq!(Time::parse_flexible("1:02:03").unwrap(), Time::new(62, 3, 0));
+    /// assert_eq!(Time::parse_flexible("90.5").unwrap(), Time::new(1, 30, 38));
+    /// assert!(Time::parse_flexible("4:60").is_err());
+    /// ```
+    pub fn parse_flexible(s: &str) -> Result<Time, Error> {
+        Time::parse_flexible_rounded(s, Rounding::Nearest)
+    }
+
+    /// Parse a human-friendly time like `Time::parse_flexible`, rounding fractions of a frame
+    /// as specified.
+    ///
+    /// ```
+    /// use cue_sheet::parser::Time;
+    /// use cue_sheet::split::Rounding;
+    ///
+    /// let time = Time::parse_flexible_rounded("4:17.693", Rounding::Down).unwrap();
+    /// assert_eq!(time, Time::new(4, 17, 51));
+    /// ```
+    pub fn parse_flexible_rounded(s: &str, rounding: Rounding) -> Result<Time, Error> {
+        let invalid = || Error::from(format!("Invalid time: {:?}", s));
+        let digits = |p: &str| !p.is_empty() && p.chars().all(|c| c.is_ascii_digit());
+
+        let (whole, fraction) = match s.find('.') {
+            Some(pos) => (&s[..pos], Some(&s[pos + 1..])),
+            None => (s, None),
+        };
+        let parts: Vec<&str> = whole.split(':').collect();
+        if parts.len() > 3 || !parts.iter().all(|p| digits(p)) {
+            return Err(invalid());
+        }
+
+        let mut seconds: i64 = 0;
+        for (i, part) in parts.iter().enumerate() {
+            let value: i64 = part.parse().map_err(|_| invalid())?;
+            // Everything but the leading component has to be below 60.
+            if i > 0 && (value >= 60 || part.len() != 2) {
+                return Err(invalid());
+            }
+            seconds = seconds
+                .checked_mul(60)
+                .and_then(|s| s.checked_add(value))
+                .ok_or_else(invalid)?;
+        }
+
+        let mut frames = seconds.checked_mul(FPS).ok_or_else(invalid)?;
+        if let Some(fraction) = fraction {
+            if !digits(fraction) || fraction.len() > 9 {
+                return Err(invalid());
+            }
+            let denominator = 10i64.pow(fraction.len() as u32);
+            frames = frames
+                .checked_add(rounding.apply(fraction.parse::<i64>()? * FPS, denominator))
+                .ok_or_else(invalid)?;
+        }
+
+        Time::checked_from_frames(frames).ok_or_else(invalid)
+    }
+
+    /// Returns the number of audio samples (per channel) represented by this instance at the
+    /// specified sample rate.
+    ///
+    /// ```
+    /// use cue_sheet::parser::Time;
+    ///
+    /// // One frame of CD audio contains 588 samples.
+    /// assert_eq!(Time::new(0, 0, 1).to_samples(44100), 588);
+    /// assert_eq!(Time::new(0, 2, 0).to_samples(48000), 96000);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// If the number of samples doesn't fit into an `i64`, see `Time::checked_to_samples`.
+    pub fn to_samples(&self, sample_rate: u32) -> i64 {
+        self.checked_to_samples(sample_rate)
+            .expect("Time::to_samples overflowed")
+    }
+
+    /// Returns the number of audio samples (per channel) like `Time::to_samples`, `None` if it
+    /// doesn't fit into an `i64`.
+    ///
+    /// ```
+    /// use cue_sheet::parser::Time;
+    ///
+    /// assert_eq!(Time::new(0, 0, 1).checked_to_samples(44100), Some(588));
+    /// assert_eq!(Time::new(i32::MAX, 0, 0).checked_to_samples(u32::MAX), None);
+    /// ```
+    pub fn checked_to_samples(&self, sample_rate: u32) -> Option<i64> {
+        self.total_frames()
+            .checked_mul(i64::from(sample_rate))
+            .map(|product| product / FPS)
+    }
+
+    /// Returns the number of audio samples (per channel) this instance corresponds to, if the
+    /// frames count `frame_rate` per second instead of 75. `None` if `frame_rate` is zero.
+    ///
+    /// This is only useful for nonstandard cue sheets, see `ParseOptions::frame_rate`.
+    ///
+    /// ```
+    /// use cue_sheet::parser::Time;
+    ///
+    /// assert_eq!(Time::new(0, 1, 50).to_samples_at(96000, 100), Some(144000));
+    /// assert_eq!(Time::new(0, 1, 50).to_samples_at(96000, 0), None);
+    /// ```
+    pub fn to_samples_at(&self, sample_rate: u32, frame_rate: u8) -> Option<i64> {
+        if frame_rate == 0 {
+            return None;
         }
+        Some(self.total_frames_at(frame_rate) * i64::from(sample_rate) / i64::from(frame_rate))
+    }
+
+    /// Returns the total number of frames represented by this instance, if there are
+    /// `frame_rate` frames per second instead of 75.
+    ///
+    /// ```
+    /// use cue_sheet::parser::Time;
+    ///
+    /// assert_eq!(Time::new(0, 1, 50).total_frames_at(100), 150);
+    /// assert_eq!(Time::new(0, 1, 50).total_frames_at(75), Time::new(0, 1, 50).total_frames());
+    /// ```
+    pub fn total_frames_at(&self, frame_rate: u8) -> i64 {
+        (i64::from(self.mins) * 60 + i64::from(self.secs)) * i64::from(frame_rate)
+            + i64::from(self.frames)
+    }
+
+    /// Create an instance for the specified number of frames, if there are `frame_rate` frames
+    /// per second instead of 75. `None` if `frame_rate` is zero or above 127, or if the number
+    /// of minutes doesn't fit into an `i32`.
+    ///
+    /// ```
+    /// use cue_sheet::parser::Time;
+    ///
+    /// assert_eq!(Time::checked_from_frames_at(199, 100), Some(Time::new(0, 1, 99)));
+    /// assert_eq!(Time::checked_from_frames_at(199, 0), None);
+    /// ```
+    pub fn checked_from_frames_at(from: i64, frame_rate: u8) -> Option<Time> {
+        if frame_rate == 0 || frame_rate > i8::MAX as u8 {
+            return None;
+        }
+        let frame_rate = i64::from(frame_rate);
+        let secs_all = from / frame_rate;
+        let mins = i32::try_from(secs_all / 60).ok()?;
+
+        Some(Time {
+            mins,
+            secs: (secs_all % 60) as i8,
+            frames: (from % frame_rate) as i8,
+        })
+    }
+
+    /// Create an instance for the specified number of audio samples (per channel) at the
+    /// specified sample rate.
+    ///
+    /// Samples which don't make up a full frame are truncated.
+    ///
+    /// ```
+    /// use cue_sheet::parser::Time;
+    ///
+    /// assert_eq!(Time::from_samples(588, 44100), Time::new(0, 0, 1));
+    /// assert_eq!(Time::from_samples(1000, 44100), Time::new(0, 0, 1));
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// If `sample_rate` is zero, or if the number of minutes doesn't fit into an `i32` like for
+    /// `Time::from_frames`. See `Time::checked_from_samples`.
+    pub fn from_samples(samples: i64, sample_rate: u32) -> Time {
+        Time::checked_from_samples(samples, sample_rate)
+            .expect("Time::from_samples overflowed or got a sample rate of zero")
+    }
+
+    /// Create an instance for the specified number of audio samples like
+    /// `Time::from_samples`, `None` if `sample_rate` is zero or the number of minutes doesn't
+    /// fit into an `i32`.
+    ///
+    /// ```
+    /// use cue_sheet::parser::Time;
+    ///
+    /// assert_eq!(Time::checked_from_samples(588, 44100), Some(Time::new(0, 0, 1)));
+    /// assert_eq!(Time::checked_from_samples(588, 0), None);
+    /// ```
+    pub fn checked_from_samples(samples: i64, sample_rate: u32) -> Option<Time> {
+        if sample_rate == 0 {
+            return None;
+        }
+        // The intermediate product can exceed an `i64` even if the result doesn't.
+        let frames = i128::from(samples) * i128::from(FPS) / i128::from(sample_rate);
+        i64::try_from(frames)
+            .ok()
+            .and_then(Time::checked_from_frames)
+    }
+
+    /// Returns the number of bytes represented by this instance, if every frame/sector occupies
+    /// `sector_size` bytes.
+    ///
+    /// ```
+    /// use cue_sheet::parser::Time;
+    ///
+    /// assert_eq!(Time::new(0, 1, 0).to_bytes(2352), 176400);
+    /// ```
+    pub fn to_bytes(&self, sector_size: u32) -> i64 {
+        self.total_frames() * sector_size as i64
+    }
+}
+
+/// Converts into the total number of frames/sectors.
+impl From<Time> for i64 {
+    fn from(time: Time) -> i64 {
+        time.total_frames()
     }
 }
 
@@ -185,18 +565,25 @@ impl FromStr for Time {
     type Err = Error;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        if s.len() != 8 {
-            return Err("Time was not 8 chars long.".into());
+        let parts: Vec<&str> = s.split(':').collect();
+        if parts.len() != 3 {
+            return Err("Time was not properly formatted.".into());
         }
 
-        if s.chars().nth(2).unwrap() != ':' || s.chars().nth(5).unwrap() != ':' {
+        // Minutes have at least two digits (more for long DJ mixes), the others exactly two.
+        let digits = |p: &str| p.chars().all(|c| c.is_ascii_digit());
+        if parts[0].len() < 2
+            || parts[1].len() != 2
+            || parts[2].len() != 2
+            || !parts.iter().all(|p| digits(p))
+        {
             return Err("Time was not properly formatted.".into());
         }
 
         Ok(Time {
-            mins: s[0..2].parse()?,
-            secs: s[3..5].parse()?,
-            frames: s[6..8].parse()?,
+            mins: parts[0].parse()?,
+            secs: parts[1].parse()?,
+            frames: parts[2].parse()?,
         })
     }
 }
@@ -207,22 +594,27 @@ impl fmt::Display for Time {
     }
 }
 
+/// Subtracts two times, saturating at zero.
+///
+/// A time can't represent a negative value, so subtracting a later time results in `00:00:00`.
+/// Use `Time::signed_diff` for the signed difference or `Time::checked_sub` to detect this.
 impl Sub for Time {
     type Output = Time;
 
     fn sub(self, rhs: Time) -> Self::Output {
-        let left = self.total_frames();
-        let right = rhs.total_frames();
-
-        let diff = left - right;
-        Time::from_frames(diff)
+        Time::from_frames(self.signed_diff(&rhs).max(0))
     }
 }
 
 /// Describes the file format of an audio file.
-#[derive(Clone, Debug, Eq, PartialEq)]
+///
+/// The specification only knows the formats supported by CD burning programs. Players
+/// accept any audio file they can decode for `WAVE` (e.g. FLAC or WavPack), so the actual
+/// format can only be told apart by the file name, see `TrackFile::inferred_format`.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
 pub enum FileFormat {
-    /// Also includes other lossless formats.
+    /// A WAVE audio file, or any other audio file decoded by the player.
+    #[default]
     Wave,
 
     /// An MP3 audio file.
@@ -236,6 +628,52 @@ pub enum FileFormat {
 
     /// Big-endian binary raw data file.
     Motorola,
+
+    /// A nonstandard file format keyword (e.g. `FLAC`) as it appeared in the cue sheet.
+    ///
+    /// This is only produced outside of strict mode.
+    Other(String),
+}
+
+impl FileFormat {
+    /// Infer the file format from the extension of `path`.
+    ///
+    /// Returns `None` if the extension is unknown.
+    ///
+    /// ```
+    /// use cue_sheet::parser::FileFormat;
+    /// use std::path::Path;
+    ///
+    /// assert_eq!(FileFormat::from_path(Path::new("a.flac")), Some(FileFormat::Wave));
+    /// assert_eq!(FileFormat::from_path(Path::new("a.MP3")), Some(FileFormat::Mp3));
+    /// assert_eq!(FileFormat::from_path(Path::new("a")), None);
+    /// ```
+    pub fn from_path(path: &Path) -> Option<FileFormat> {
+        let extension = path.extension()?.to_str()?.to_lowercase();
+        match extension.as_str() {
+            "wav" | "flac" | "ape" | "wv" | "tta" | "tak" | "ogg" | "opus" | "m4a" => {
+                Some(FileFormat::Wave)
+            }
+            "mp3" => Some(FileFormat::Mp3),
+            "aif" | "aiff" | "aifc" => Some(FileFormat::Aiff),
+            "bin" | "img" | "iso" | "raw" => Some(FileFormat::Binary),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for FileFormat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let s = match *self {
+            FileFormat::Wave => "WAVE",
+            FileFormat::Mp3 => "MP3",
+            FileFormat::Aiff => "AIFF",
+            FileFormat::Binary => "BINARY",
+            FileFormat::Motorola => "MOTOROLA",
+            FileFormat::Other(ref keyword) => keyword,
+        };
+        f.write_str(s)
+    }
 }
 
 impl FromStr for FileFormat {
@@ -254,7 +692,10 @@ impl FromStr for FileFormat {
 }
 
 /// Additional flags a Track can have.
-#[derive(Clone, Debug)]
+///
+/// Flags are ordered as they are listed by the specification, which is also the order in which
+/// `Tracklist` writes them.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash, Ord, PartialOrd)]
 pub enum TrackFlag {
     /// Digital Copy Permitted
     Dcp,
@@ -269,6 +710,18 @@ pub enum TrackFlag {
     Scms,
 }
 
+impl fmt::Display for TrackFlag {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let s = match *self {
+            TrackFlag::Dcp => "DCP",
+            TrackFlag::FourChannel => "4CH",
+            TrackFlag::Pre => "PRE",
+            TrackFlag::Scms => "SCMS",
+        };
+        f.write_str(s)
+    }
+}
+
 impl FromStr for TrackFlag {
     type Err = Error;
 
@@ -285,10 +738,11 @@ impl FromStr for TrackFlag {
 
 /// Describes the type of tracks on the media.
 ///
-/// Most of the times for music this will be just `Audio`.
-#[derive(Clone, Debug, Eq, PartialEq)]
+/// Most of the times for music this will be just `Audio`, which is also the default.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
 pub enum TrackType {
     /// Audio/Music (2352 — 588 samples)
+    #[default]
     Audio,
 
     /// Karaoke CD+G (2448)
@@ -307,6 +761,68 @@ pub enum TrackType {
     Cdi(u16),
 }
 
+impl TrackType {
+    /// Returns the number of bytes per sector of a track of this type in a `BINARY` image.
+    ///
+    /// ```
+    /// use cue_sheet::parser::TrackType;
+    ///
+    /// assert_eq!(TrackType::Audio.sector_size(), 2352);
+    /// assert_eq!(TrackType::Cdg.sector_size(), 2448);
+    /// assert_eq!(TrackType::Mode(1, 2048).sector_size(), 2048);
+    /// ```
+    pub fn sector_size(&self) -> u16 {
+        match *self {
+            TrackType::Audio => 2352,
+            TrackType::Cdg => 2448,
+            TrackType::Mode(_, size) | TrackType::Cdi(size) => size,
+        }
+    }
+
+    /// True for tracks containing audio, i.e. `Audio` and `Cdg` (audio with graphics in the
+    /// subchannel).
+    pub fn is_audio(&self) -> bool {
+        match *self {
+            TrackType::Audio | TrackType::Cdg => true,
+            TrackType::Mode(..) | TrackType::Cdi(_) => false,
+        }
+    }
+
+    /// True for data tracks, i.e. the CD-ROM and CD-i modes.
+    ///
+    /// ```
+    /// use cue_sheet::parser::TrackType;
+    ///
+    /// assert!(TrackType::Mode(2, 2352).is_data());
+    /// assert!(!TrackType::Cdg.is_data());
+    /// assert!(TrackType::Cdg.is_audio());
+    /// ```
+    pub fn is_data(&self) -> bool {
+        !self.is_audio()
+    }
+}
+
+impl fmt::Display for TrackType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            TrackType::Audio => write!(f, "AUDIO"),
+            TrackType::Cdg => write!(f, "CDG"),
+            TrackType::Mode(mode, size) => write!(f, "MODE{}/{}", mode, size),
+            TrackType::Cdi(size) => write!(f, "CDI/{}", size),
+        }
+    }
+}
+
+/// Besides the keywords of the specification the spellings of cdrdao (e.g. `MODE2_FORM1`) are
+/// accepted, which are written with the keyword of the specification again.
+///
+/// ```
+/// use cue_sheet::parser::TrackType;
+///
+/// let mode: TrackType = "MODE2_FORM1".parse().unwrap();
+/// assert_eq!(mode, TrackType::Mode(2, 2048));
+/// assert_eq!(mode.to_string(), "MODE2/2048");
+/// ```
 impl FromStr for TrackType {
     type Err = Error;
 
@@ -316,25 +832,503 @@ impl FromStr for TrackType {
             "CDG" => Ok(TrackType::Cdg),
             "MODE1/2048" => Ok(TrackType::Mode(1, 2048)),
             "MODE1/2352" => Ok(TrackType::Mode(1, 2352)),
-            "MODE2/2048" => Ok(TrackType::Mode(1, 2048)),
-            "MODE2/2324" => Ok(TrackType::Mode(1, 2324)),
-            "MODE2/2336" => Ok(TrackType::Mode(1, 2336)),
-            "MODE2/2352" => Ok(TrackType::Mode(1, 2352)),
+            "MODE2/2048" => Ok(TrackType::Mode(2, 2048)),
+            "MODE2/2324" => Ok(TrackType::Mode(2, 2324)),
+            "MODE2/2336" => Ok(TrackType::Mode(2, 2336)),
+            "MODE2/2352" => Ok(TrackType::Mode(2, 2352)),
             "CDI/2336" => Ok(TrackType::Cdi(2336)),
             "CDI/2352" => Ok(TrackType::Cdi(2352)),
+            // Spellings of the track modes used by cdrdao TOC files.
+            "MODE1" => Ok(TrackType::Mode(1, 2048)),
+            "MODE1_RAW" => Ok(TrackType::Mode(1, 2352)),
+            "MODE2" | "MODE2_FORM_MIX" => Ok(TrackType::Mode(2, 2336)),
+            "MODE2_FORM1" => Ok(TrackType::Mode(2, 2048)),
+            "MODE2_FORM2" => Ok(TrackType::Mode(2, 2324)),
+            "MODE2_RAW" => Ok(TrackType::Mode(2, 2352)),
             _ => Err(format!("Unknown track type: {:?}", s).into()),
         }
     }
 }
 
+/// Options controlling how cue sheets are parsed.
+#[derive(Clone, Debug, Default)]
+pub struct ParseOptions {
+    /// Reject cue sheets which don't conform to the specification.
+    ///
+    /// This currently checks that all commands appear in places where the specification allows
+    /// them, e.g. `CATALOG` before the first `FILE` or `FLAGS` between `TRACK` and the first
+    /// `INDEX` and that lines don't contain any tokens besides their command. Keywords, file
+    /// formats, track types and flags which aren't written in upper case only result in a
+    /// `WarningKind::NonstandardCase`, even in strict mode.
+    ///
+    /// Otherwise some common mistakes are tolerated: a `FILE` without a file format gets the
+    /// format inferred from its file name, an unquoted file name containing spaces is read up
+    /// to the file format at the end of the line, nonstandard file formats are kept as
+    /// `FileFormat::Other`, and arguments following the file format are kept in
+    /// `Command::File`.
+    pub strict: bool,
+
+    /// Cleanup applied to the metadata strings of a parsed `CueSheet` or `Tracklist`.
+    ///
+    /// The raw values are kept in `Tracklist::raw_metadata`, a `CueSheet` only has the cleaned
+    /// ones.
+    pub metadata_cleanup: MetadataCleanup,
+
+    /// How track durations of a parsed `CueSheet` or `Tracklist` are computed.
+    pub duration_mode: DurationMode,
+
+    /// How zero-width characters outside of quoted strings are handled.
+    pub zero_width: ZeroWidth,
+
+    /// Nonstandard number of frames per second of the times in the cue sheet.
+    ///
+    /// The specification defines 75 frames per second (the CD sector rate), but some tools
+    /// write cue sheets for high resolution audio with other rates. With a rate set here,
+    /// frames up to the rate (at most 127) are accepted as they are instead of being
+    /// normalized or rejected, and a rate of zero is an error. The rate is kept in
+    /// `Tracklist::frame_rate`, see there for what takes it into account. Otherwise times
+    /// should be converted with `Time::to_samples_at` using the same rate.
+    pub frame_rate: Option<u8>,
+
+    /// Name of the file assumed for tracks which aren't preceded by a `FILE` command.
+    ///
+    /// By default such tracks are dropped, i.e. a cue sheet without any `FILE` command results
+    /// in an empty list of files. In strict mode they are rejected regardless of this option.
+    pub implicit_file: Option<String>,
+
+    /// Verify the check digit of `CATALOG` numbers, see `validation::catalog_checksum_valid`.
+    ///
+    /// A failing check only results in a `WarningKind::InvalidCatalog`, even in strict mode,
+    /// but mastering tools usually reject such cue sheets.
+    pub check_catalog: bool,
+
+    /// Keep nonstandard comment lines as `REM COMMENT` commands.
+    ///
+    /// Outside of strict mode lines starting with `;` or `//`, which a few tools write, are
+    /// skipped with a `WarningKind::NonstandardComment`. With this option their text is kept
+    /// instead, so it ends up in the `REM` comments.
+    pub keep_comments: bool,
+
+    /// Accept times written in a nonstandard form, see `Time::parse_lenient`.
+    ///
+    /// Such times result in a `WarningKind::NonstandardTime`. Strict mode ignores this option.
+    pub lenient_times: bool,
+
+    /// Skip lines with unknown commands instead of rejecting the cue sheet.
+    ///
+    /// Skipped lines result in a `WarningKind::UnknownCommand`. Strict mode ignores this
+    /// option.
+    pub skip_unknown_commands: bool,
+}
+
+/// Builder-style setters, e.g. for sharing one configuration between threads.
+///
+/// ```
+/// use cue_sheet::parser::ParseOptions;
+/// use std::sync::Arc;
+///
+/// let options = Arc::new(ParseOptions::new().strict(true).check_catalog(true));
+/// let worker = {
+///     let options = Arc::clone(&options);
+///     std::thread::spawn(move || options.strict)
+/// };
+/// assert!(worker.join().unwrap());
+/// ```
+impl ParseOptions {
+    /// Create the default options, same as `ParseOptions::default`.
+    pub fn new() -> ParseOptions {
+        ParseOptions::default()
+    }
+
+    /// Returns a shared instance of the default options, which the parsing functions without
+    /// options use.
+    pub fn global_default() -> &'static ParseOptions {
+        static DEFAULT: OnceLock<ParseOptions> = OnceLock::new();
+        DEFAULT.get_or_init(ParseOptions::default)
+    }
+
+    /// Set `ParseOptions::strict`.
+    pub fn strict(mut self, strict: bool) -> ParseOptions {
+        self.strict = strict;
+        self
+    }
+
+    /// Set `ParseOptions::metadata_cleanup`.
+    pub fn metadata_cleanup(mut self, cleanup: MetadataCleanup) -> ParseOptions {
+        self.metadata_cleanup = cleanup;
+        self
+    }
+
+    /// Set `ParseOptions::duration_mode`.
+    pub fn duration_mode(mut self, mode: DurationMode) -> ParseOptions {
+        self.duration_mode = mode;
+        self
+    }
+
+    /// Set `ParseOptions::zero_width`.
+    pub fn zero_width(mut self, mode: ZeroWidth) -> ParseOptions {
+        self.zero_width = mode;
+        self
+    }
+
+    /// Set `ParseOptions::frame_rate`.
+    pub fn frame_rate(mut self, rate: u8) -> ParseOptions {
+        self.frame_rate = Some(rate);
+        self
+    }
+
+    /// Set `ParseOptions::implicit_file`.
+    pub fn implicit_file(mut self, name: &str) -> ParseOptions {
+        self.implicit_file = Some(name.to_string());
+        self
+    }
+
+    /// Set `ParseOptions::check_catalog`.
+    pub fn check_catalog(mut self, check: bool) -> ParseOptions {
+        self.check_catalog = check;
+        self
+    }
+
+    /// Set `ParseOptions::keep_comments`.
+    pub fn keep_comments(mut self, keep: bool) -> ParseOptions {
+        self.keep_comments = keep;
+        self
+    }
+
+    /// Set `ParseOptions::lenient_times`.
+    pub fn lenient_times(mut self, lenient: bool) -> ParseOptions {
+        self.lenient_times = lenient;
+        self
+    }
+
+    /// Set `ParseOptions::skip_unknown_commands`.
+    pub fn skip_unknown_commands(mut self, skip: bool) -> ParseOptions {
+        self.skip_unknown_commands = skip;
+        self
+    }
+}
+
+/// Handling of invisible characters, which often end up in cue sheets copied from web pages.
+///
+/// This applies to zero-width spaces, joiners and non-joiners, word joiners, soft hyphens and
+/// left-to-right/right-to-left marks outside of quoted strings. A byte order mark is always
+/// treated as whitespace.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum ZeroWidth {
+    /// Remove the characters, so `TI\u{200b}TLE` is read as `TITLE`.
+    #[default]
+    Remove,
+
+    /// Treat the characters as whitespace separating tokens.
+    Whitespace,
+
+    /// Keep the characters as part of the tokens.
+    Keep,
+}
+
+fn is_zero_width(c: char) -> bool {
+    matches!(
+        c,
+        '\u{200b}' | '\u{200c}' | '\u{200d}' | '\u{200e}' | '\u{200f}' | '\u{2060}' | '\u{ad}'
+    )
+}
+
+/// Apply `mode` to the zero-width characters outside of quoted strings in `line`.
+fn preprocess(line: &str, mode: ZeroWidth) -> Cow<'_, str> {
+    if mode == ZeroWidth::Keep || !line.chars().any(is_zero_width) {
+        return Cow::Borrowed(line);
+    }
+
+    let mut quoted = false;
+    let mut result = String::with_capacity(line.len());
+    for c in line.chars() {
+        if c == '"' {
+            quoted = !quoted;
+        }
+        if quoted || !is_zero_width(c) {
+            result.push(c);
+        } else if mode == ZeroWidth::Whitespace {
+            result.push(' ');
+        }
+    }
+    Cow::Owned(result)
+}
+
 /// Parse CUE sheet provided by the parameter `source`.
+///
+/// Each line of the source contains one command, tokens left over after a command are ignored.
+/// Unknown commands are an error unless `ParseOptions::skip_unknown_commands` is set.
 pub fn parse_cue(source: &str) -> Result<Vec<Command>, Error> {
-    let mut tokens = tokenize(source)?;
+    parse_cue_with_options(source, ParseOptions::global_default())
+}
+
+/// Parse CUE sheet provided by the parameter `source` using the provided `options`.
+pub fn parse_cue_with_options(source: &str, options: &ParseOptions) -> Result<Vec<Command>, Error> {
+    Ok(parse_cue_with_warnings(source, options)?.0)
+}
+
+/// A command together with the line (starting at 1) it was read from.
+pub type CommandLine = (Command, usize);
+
+/// Parse CUE sheet provided by the parameter `source` using the provided `options`, also
+/// returning warnings about the problems which were recovered from.
+///
+/// With `ParseOptions::skip_unknown_commands` lines with unknown commands are skipped outside
+/// of strict mode.
+///
+/// ```
+/// use cue_sheet::parser::{parse_cue_with_warnings, ParseOptions};
+/// use cue_sheet::warning::WarningKind;
+///
+/// let source = "title Loveless\nCOMPOSER \"Kevin Shields\"\nFILE a.wav";
+/// assert!(parse_cue_with_warnings(source, &ParseOptions::default()).is_err());
+///
+/// let options = ParseOptions::new().skip_unknown_commands(true);
+/// let (commands, warnings) = parse_cue_with_warnings(source, &options).unwrap();
+/// assert_eq!(commands.len(), 2);
+/// assert_eq!(warnings[0].kind, WarningKind::NonstandardCase("title".to_string()));
+/// assert_eq!(warnings[1].line, 2);
+/// assert_eq!(warnings.len(), 3);
+/// ```
+pub fn parse_cue_with_warnings(
+    source: &str,
+    options: &ParseOptions,
+) -> Result<(Vec<Command>, Vec<Warning>), Error> {
+    let (commands, warnings) = parse_cue_with_lines(source, options)?;
+    Ok((commands.into_iter().map(|(c, _)| c).collect(), warnings))
+}
+
+/// Like `parse_cue_with_warnings`, but every command is paired with the line (starting at 1)
+/// it was read from.
+///
+/// ```
+/// use cue_sheet::parser::{parse_cue_with_lines, ParseOptions};
+///
+/// let source = "TITLE Loveless\n\nFILE a.wav WAVE";
+/// let (commands, _) = parse_cue_with_lines(source, &ParseOptions::default()).unwrap();
+/// let lines: Vec<usize> = commands.iter().map(|&(_, line)| line).collect();
+/// assert_eq!(lines, vec![1, 3]);
+/// ```
+pub fn parse_cue_with_lines(
+    source: &str,
+    options: &ParseOptions,
+) -> Result<(Vec<CommandLine>, Vec<Warning>), Error> {
+    let mut warnings = Vec::new();
+    let commands = parse_cue_collecting(source, options, &mut warnings)?;
+    Ok((commands, warnings))
+}
+
+/// Like `parse_cue_with_lines`, but the warnings are added to `warnings`, so the ones found
+/// before an error are kept.
+pub(crate) fn parse_cue_collecting(
+    source: &str,
+    options: &ParseOptions,
+    warnings: &mut Vec<Warning>,
+) -> Result<Vec<CommandLine>, Error> {
     let mut commands = Vec::new();
 
-    while tokens.len() > 0 {
-        commands.push(Command::consume(&mut tokens)?);
+    // Every command occupies exactly one line.
+    for (number, line) in source.lines().enumerate() {
+        if let Some(command) = parse_line(line, number + 1, options, warnings)? {
+            commands.push((command, number + 1));
+        }
     }
 
+    check_sheet(&commands, options, warnings)?;
     Ok(commands)
 }
+
+/// Like `parse_cue_with_warnings`, but every command keeps the spelling of its keywords, which
+/// `KeywordCase::Preserve` writes back.
+///
+/// ```
+/// use cue_sheet::parser::{parse_cue_spelled, ParseOptions};
+/// use cue_sheet::writer::{write_spelled_commands, KeywordCase, WriteOptions};
+///
+/// let source = "file a.wav Wave\n  Track 01 audio\n";
+/// let (commands, _) = parse_cue_spelled(source, &ParseOptions::default()).unwrap();
+/// assert_eq!(commands[1].keywords, vec!["Track", "audio"]);
+///
+/// let options = WriteOptions::new().keyword_case(KeywordCase::Preserve);
+/// let mut text = String::new();
+/// write_spelled_commands(&commands, &mut text, &options).unwrap();
+/// assert_eq!(text, "file \"a.wav\" Wave\n  Track 01 audio\n");
+/// ```
+pub fn parse_cue_spelled(
+    source: &str,
+    options: &ParseOptions,
+) -> Result<(Vec<SpelledCommand>, Vec<Warning>), Error> {
+    let mut commands = Vec::new();
+    let mut lines = Vec::new();
+    let mut warnings = Vec::new();
+
+    for (number, line) in source.lines().enumerate() {
+        if let Some(command) = parse_spelled_line(line, number + 1, options, &mut warnings)? {
+            lines.push((command.command.clone(), number + 1));
+            commands.push(command);
+        }
+    }
+
+    check_sheet(&lines, options, &mut warnings)?;
+    Ok((commands, warnings))
+}
+
+/// Check the rules involving multiple lines: the placement of commands in strict mode, a
+/// warning for repeated disc level commands otherwise.
+pub(crate) fn check_sheet(
+    commands: &[CommandLine],
+    options: &ParseOptions,
+    warnings: &mut Vec<Warning>,
+) -> Result<(), Error> {
+    if options.strict {
+        return check_placement(commands);
+    }
+
+    for (line, keyword) in duplicate_disc_commands(commands) {
+        let kind = WarningKind::DuplicateCommand(keyword.to_string());
+        warnings.push(Warning { line, kind });
+    }
+    warnings.sort_by_key(|warning| warning.line);
+    Ok(())
+}
+
+/// Returns the text of `line` if it is a comment starting with `;` or `//`.
+fn nonstandard_comment(line: &str) -> Option<&str> {
+    let line = line.trim();
+    line.strip_prefix(';')
+        .or_else(|| line.strip_prefix("//"))
+        .map(str::trim_start)
+}
+
+/// Parse the command on one `line` (numbered `number`, starting at 1), returning `None` for
+/// empty lines and skipped unknown commands.
+///
+/// Every line is parsed independently, only the placement of commands depends on other lines.
+pub(crate) fn parse_line(
+    line: &str,
+    number: usize,
+    options: &ParseOptions,
+    warnings: &mut Vec<Warning>,
+) -> Result<Option<Command>, Error> {
+    Ok(parse_spelled_line(line, number, options, warnings)?.map(|c| c.command))
+}
+
+/// Returns the file name of a `FILE` line with an unquoted name containing spaces, i.e. the
+/// text of the line from the first word of the name to the file format if only the last of
+/// `tokens` is a file format.
+///
+/// The name is taken from `line` itself, so the whitespace between its words is kept.
+fn unquoted_file_name(line: &str, tokens: &[Token], spans: &[Span]) -> Option<String> {
+    let is_format = |token: &Token| token.text().parse::<FileFormat>().is_ok();
+    let unquoted = |span: &Span| !line[span.start..].starts_with('"');
+    let last = tokens.len().checked_sub(1)?;
+    if last < 3
+        || !tokens[0].text().eq_ignore_ascii_case("FILE")
+        || !is_format(&tokens[last])
+        || is_format(&tokens[2])
+        || !spans[1..last].iter().all(unquoted)
+    {
+        return None;
+    }
+    Some(
+        line[spans[1].start..spans[last].start]
+            .trim_end()
+            .to_string(),
+    )
+}
+
+/// Like `parse_line`, but keeps the spelling of the keywords.
+fn parse_spelled_line(
+    line: &str,
+    number: usize,
+    options: &ParseOptions,
+    warnings: &mut Vec<Warning>,
+) -> Result<Option<SpelledCommand>, Error> {
+    let mut found = Vec::new();
+    let processed = preprocess(line, options.zero_width);
+    if processed != line {
+        found.push(WarningKind::ZeroWidthCharacters);
+    }
+    let line = processed;
+
+    if !options.strict {
+        if let Some(comment) = nonstandard_comment(&line) {
+            let command = if options.keep_comments {
+                Some(SpelledCommand {
+                    command: Command::Rem(
+                        "COMMENT".to_string(),
+                        Token::String(comment.to_string()),
+                    ),
+                    keywords: Vec::new(),
+                })
+            } else {
+                None
+            };
+            found.push(WarningKind::NonstandardComment(comment.to_string()));
+            warnings.extend(found.into_iter().map(|kind| Warning { line: number, kind }));
+            return Ok(command);
+        }
+    }
+
+    // The line is tokenized on its own, so positions in errors have to be moved to its number.
+    let tokens = tokenize_with_spans(&line).map_err(|e| match *e.kind() {
+        ErrorKind::UnterminatedString(_, column) => {
+            ErrorKind::UnterminatedString(number, column).into()
+        }
+        ErrorKind::StringTooLong(_, column) => ErrorKind::StringTooLong(number, column).into(),
+        _ => e,
+    })?;
+    let (mut tokens, mut spans): (Vec<Token>, Vec<Span>) = tokens.into_iter().unzip();
+    if tokens.is_empty() {
+        return Ok(None);
+    }
+    if !options.strict {
+        if let Some(name) = unquoted_file_name(&line, &tokens, &spans) {
+            let last = tokens.len() - 1;
+            let span = Span {
+                end: spans[1].start + name.len(),
+                ..spans[1]
+            };
+            tokens.splice(1..last, Some(Token::String(name.clone())));
+            spans.splice(1..last, Some(span));
+            found.push(WarningKind::UnquotedFileName(name));
+        }
+    }
+
+    let mut cursor = &tokens[..];
+    let mut command = match Command::parse_with_warnings(&mut cursor, options, &mut found) {
+        Ok(command) => Some(command),
+        Err(Error(ErrorKind::UnknownCommand(command), _))
+            if options.skip_unknown_commands && !options.strict =>
+        {
+            found.push(WarningKind::UnknownCommand(command));
+            cursor = &[];
+            None
+        }
+        Err(e) => return Err(e),
+    };
+
+    // A `REM` value of several words is taken from the line itself, which keeps its spacing
+    // and quotes.
+    let consumed = tokens.len() - cursor.len();
+    if let Some(ref mut spelled) = command {
+        if let Command::Rem(_, ref mut value) = spelled.command {
+            if consumed > 3 {
+                *value = Token::String(line[spans[2].start..spans[consumed - 1].end].to_string());
+            }
+        }
+    }
+
+    if !cursor.is_empty() {
+        let start = spans[consumed].start;
+        let text = line[start..].trim_end().to_string();
+        if options.strict {
+            let column = line[..start].chars().count() + 1;
+            return Err(ErrorKind::TrailingTokens(number, column, text).into());
+        }
+        found.push(WarningKind::TrailingTokens(text));
+    }
+
+    warnings.extend(found.into_iter().map(|kind| Warning { line: number, kind }));
+    Ok(command)
+}
@@ -0,0 +1,256 @@
+// cue_sheet
+// Copyright (C) 2017  Leonardo Schwarz <mail@leoschwarz.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Validation of the placement rules the specification imposes on commands.
+
+use super::Command;
+use errors::{Error, ErrorKind};
+
+/// Which of the placement restricted commands were already seen in the current track.
+#[derive(Default)]
+struct TrackState {
+    flags: bool,
+    index: bool,
+    isrc: bool,
+    postgap: bool,
+    pregap: bool,
+}
+
+fn invalid(position: usize, reason: &str) -> Error {
+    ErrorKind::InvalidPlacement(position, reason.to_string()).into()
+}
+
+/// Check that all commands appear in a place where the specification allows them.
+///
+/// `commands` are paired with their line numbers, which are reported in errors.
+pub(crate) fn check_placement(commands: &[(Command, usize)]) -> Result<(), Error> {
+    let mut seen_catalog = false;
+    let mut seen_cdtextfile = false;
+    let mut seen_file = false;
+    let mut track: Option<TrackState> = None;
+
+    for &(ref command, position) in commands {
+        match *command {
+            Command::Catalog(_) => {
+                if seen_file {
+                    return Err(invalid(
+                        position,
+                        "CATALOG must appear before the first FILE",
+                    ));
+                } else if seen_catalog {
+                    return Err(invalid(position, "CATALOG may only appear once"));
+                }
+                seen_catalog = true;
+            }
+            Command::Cdtextfile(_) => {
+                if seen_file {
+                    return Err(invalid(
+                        position,
+                        "CDTEXTFILE must appear before the first FILE",
+                    ));
+                } else if seen_cdtextfile {
+                    return Err(invalid(position, "CDTEXTFILE may only appear once"));
+                }
+                seen_cdtextfile = true;
+            }
+            Command::File(..) => seen_file = true,
+            Command::Track(_, _) => {
+                if !seen_file {
+                    return Err(invalid(position, "TRACK must be preceded by a FILE"));
+                }
+                track = Some(TrackState::default());
+            }
+            Command::Flags(_) => {
+                let state = track_state(&mut track, position, "FLAGS")?;
+                if state.index {
+                    return Err(invalid(
+                        position,
+                        "FLAGS must appear before the first INDEX",
+                    ));
+                } else if state.flags {
+                    return Err(invalid(position, "FLAGS may only appear once per track"));
+                }
+                state.flags = true;
+            }
+            Command::Isrc(_) => {
+                let state = track_state(&mut track, position, "ISRC")?;
+                if state.index {
+                    return Err(invalid(position, "ISRC must appear before the first INDEX"));
+                } else if state.isrc {
+                    return Err(invalid(position, "ISRC may only appear once per track"));
+                }
+                state.isrc = true;
+            }
+            Command::Pregap(_) => {
+                let state = track_state(&mut track, position, "PREGAP")?;
+                if state.index {
+                    return Err(invalid(
+                        position,
+                        "PREGAP must appear before the first INDEX",
+                    ));
+                } else if state.pregap {
+                    return Err(invalid(position, "PREGAP may only appear once per track"));
+                }
+                state.pregap = true;
+            }
+            Command::Index(_, _) => {
+                let state = track_state(&mut track, position, "INDEX")?;
+                if state.postgap {
+                    return Err(invalid(position, "INDEX must appear before POSTGAP"));
+                }
+                state.index = true;
+            }
+            Command::Postgap(_) => {
+                let state = track_state(&mut track, position, "POSTGAP")?;
+                if !state.index {
+                    return Err(invalid(
+                        position,
+                        "POSTGAP must appear after the last INDEX",
+                    ));
+                } else if state.postgap {
+                    return Err(invalid(position, "POSTGAP may only appear once per track"));
+                }
+                state.postgap = true;
+            }
+            Command::Performer(_)
+            | Command::Rem(_, _)
+            | Command::Songwriter(_)
+            | Command::Title(_) => {}
+        }
+    }
+
+    if let Some(&(position, keyword)) = duplicate_disc_commands(commands).first() {
+        let reason = format!("{} may only appear once at the disc level", keyword);
+        return Err(invalid(position, &reason));
+    }
+
+    Ok(())
+}
+
+/// Returns the positions and keywords of disc level commands repeating an earlier one, which
+/// they supersede: `TITLE`, `PERFORMER` and `SONGWRITER` before the first `TRACK`, `CATALOG`
+/// and `CDTEXTFILE` anywhere.
+pub(crate) fn duplicate_disc_commands(commands: &[(Command, usize)]) -> Vec<(usize, &'static str)> {
+    let mut seen = Vec::new();
+    let mut duplicates = Vec::new();
+    let mut header = true;
+
+    for &(ref command, position) in commands {
+        let keyword = match *command {
+            Command::Catalog(_) => "CATALOG",
+            Command::Cdtextfile(_) => "CDTEXTFILE",
+            Command::Performer(_) if header => "PERFORMER",
+            Command::Songwriter(_) if header => "SONGWRITER",
+            Command::Title(_) if header => "TITLE",
+            Command::Track(_, _) => {
+                header = false;
+                continue;
+            }
+            _ => continue,
+        };
+        if seen.contains(&keyword) {
+            duplicates.push((position, keyword));
+        } else {
+            seen.push(keyword);
+        }
+    }
+
+    duplicates
+}
+
+fn track_state<'a>(
+    track: &'a mut Option<TrackState>,
+    position: usize,
+    keyword: &str,
+) -> Result<&'a mut TrackState, Error> {
+    track
+        .as_mut()
+        .ok_or_else(|| invalid(position, &format!("{} must appear after a TRACK", keyword)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parser::{parse_cue_with_lines, ParseOptions};
+
+    // Placement is only checked in strict mode, which also rejects a second command on a line.
+    fn check(source: &str) -> Result<(), Error> {
+        let options = ParseOptions::new().strict(true);
+        check_placement(&parse_cue_with_lines(source, &options)?.0)
+    }
+
+    #[test]
+    fn duplicate_header() {
+        let source =
+            "TITLE a\nPERFORMER b\nREM x\nTITLE c\nFILE a.wav WAVE\nTRACK 01 AUDIO\nTITLE d";
+        let (commands, _) = parse_cue_with_lines(source, &Default::default()).unwrap();
+        assert_eq!(duplicate_disc_commands(&commands), vec![(4, "TITLE")]);
+        match check_placement(&commands).unwrap_err().kind() {
+            ErrorKind::InvalidPlacement(4, _) => {}
+            e => panic!("unexpected error: {:?}", e),
+        }
+    }
+
+    #[test]
+    fn valid() {
+        let source = r#"CATALOG 1234567890123
+                        FILE "a.bin" BINARY
+                          TRACK 01 AUDIO
+                            FLAGS DCP
+                            ISRC ABCDE1234567
+                            PREGAP 00:02:00
+                            INDEX 01 00:00:00
+                            POSTGAP 00:02:00"#;
+        assert!(check(source).is_ok());
+    }
+
+    #[test]
+    fn invalid() {
+        let err = check(
+            r#"FILE "a.bin" BINARY
+
+               CATALOG 1234567890123"#,
+        )
+        .unwrap_err();
+        match *err.kind() {
+            ErrorKind::InvalidPlacement(3, _) => {}
+            ref kind => panic!("unexpected error: {:?}", kind),
+        }
+
+        assert!(check(r#"TRACK 01 AUDIO"#).is_err());
+        assert!(check(r#"FILE "a.bin" BINARY FLAGS DCP"#).is_err());
+        assert!(check(
+            r#"FILE "a.bin" BINARY
+                   FLAGS DCP"#
+        )
+        .is_err());
+        assert!(check(
+            r#"FILE "a.bin" BINARY
+                   TRACK 01 AUDIO
+                     INDEX 01 00:00:00
+                     FLAGS DCP"#
+        )
+        .is_err());
+        assert!(check(
+            r#"FILE "a.bin" BINARY
+                   TRACK 01 AUDIO
+                     PREGAP 00:02:00
+                     PREGAP 00:02:00
+                     INDEX 01 00:00:00"#
+        )
+        .is_err());
+    }
+}
@@ -0,0 +1,197 @@
+// cue_sheet
+// Copyright (C) 2017  Leonardo Schwarz <mail@leoschwarz.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! A simple scanner over the characters of a source text, as used by the tokenizer.
+//!
+//! This can also be useful to write parsers for formats related to cue sheets, like the TOC
+//! files of cdrdao or CloneCD control files.
+//!
+//! The scanner keeps track of its position as byte offset, line and column. Lines are
+//! separated by `\n` (so CRLF line endings work as well), lines and columns are counted in
+//! characters starting at 1.
+//!
+//! ```
+//! use cue_sheet::parser::scanner::Scanner;
+//!
+//! let mut scanner = Scanner::new("TRACK 01\nINDEX");
+//! let start = scanner.position();
+//! assert_eq!(scanner.take_while(|c| !c.is_whitespace()), "TRACK");
+//! assert_eq!(scanner.span_from(start).end, 5);
+//!
+//! scanner.skip_whitespace();
+//! assert_eq!(scanner.take(2), Some("01".to_string()));
+//! scanner.skip_whitespace();
+//! assert_eq!(scanner.position().line, 2);
+//! assert_eq!(scanner.peek(), Some('I'));
+//! ```
+
+use super::Span;
+
+/// Whitespace as understood by the scanner: all Unicode whitespace and the byte order mark.
+pub fn is_whitespace(c: char) -> bool {
+    c.is_whitespace() || c == '\u{feff}'
+}
+
+/// A position in the source of a `Scanner`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub struct Position {
+    /// Byte offset from the start of the source.
+    pub offset: usize,
+
+    /// Line (starting at 1).
+    pub line: usize,
+
+    /// Column (starting at 1) in characters.
+    pub column: usize,
+}
+
+/// Reads the characters of a source text one after another, see the module documentation.
+#[derive(Clone, Debug)]
+pub struct Scanner {
+    chars: Vec<char>,
+    /// Byte offsets of all chars, followed by the total length in bytes.
+    offsets: Vec<usize>,
+    index: usize,
+    line: usize,
+    column: usize,
+}
+
+impl Scanner {
+    /// Create a scanner positioned at the start of `source`.
+    pub fn new(source: &str) -> Scanner {
+        let mut offsets: Vec<usize> = source.char_indices().map(|(i, _)| i).collect();
+        offsets.push(source.len());
+
+        Scanner {
+            chars: source.chars().collect(),
+            offsets,
+            index: 0,
+            line: 1,
+            column: 1,
+        }
+    }
+
+    /// Returns the current position.
+    pub fn position(&self) -> Position {
+        Position {
+            offset: self.offsets[self.index],
+            line: self.line,
+            column: self.column,
+        }
+    }
+
+    /// Returns the span from `start` to the current position.
+    pub fn span_from(&self, start: Position) -> Span {
+        Span {
+            start: start.offset,
+            end: self.offsets[self.index],
+            line: start.line,
+        }
+    }
+
+    /// True if all characters have been read.
+    pub fn is_at_end(&self) -> bool {
+        self.index >= self.chars.len()
+    }
+
+    /// Returns the characters which haven't been read yet.
+    pub fn rest(&self) -> &[char] {
+        &self.chars[self.index..]
+    }
+
+    /// Returns the next character without reading it.
+    pub fn peek(&self) -> Option<char> {
+        self.chars.get(self.index).cloned()
+    }
+
+    /// Returns the next `n` characters without reading them, or `None` if there are less than
+    /// `n` left.
+    pub fn peek_str(&self, n: usize) -> Option<String> {
+        self.rest().get(..n).map(|chars| chars.iter().collect())
+    }
+
+    /// Reads the next character.
+    pub fn next_char(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.index += 1;
+        if c == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+        Some(c)
+    }
+
+    /// Reads the next `n` characters, or nothing if there are less than `n` left.
+    pub fn take(&mut self, n: usize) -> Option<String> {
+        let s = self.peek_str(n)?;
+        for _ in 0..n {
+            self.next_char();
+        }
+        Some(s)
+    }
+
+    /// Reads characters as long as `predicate` returns true for them.
+    pub fn take_while<F: FnMut(char) -> bool>(&mut self, mut predicate: F) -> String {
+        let mut s = String::new();
+        while let Some(c) = self.peek() {
+            if !predicate(c) {
+                break;
+            }
+            s.push(c);
+            self.next_char();
+        }
+        s
+    }
+
+    /// Skips all whitespace (see `is_whitespace`) at the current position.
+    pub fn skip_whitespace(&mut self) {
+        self.take_while(is_whitespace);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn positions() {
+        let mut scanner = Scanner::new("ä\r\nb");
+        assert_eq!(scanner.take(2), Some("ä\r".to_string()));
+        assert_eq!(
+            scanner.position(),
+            Position {
+                offset: 3,
+                line: 1,
+                column: 3,
+            }
+        );
+        assert_eq!(scanner.next_char(), Some('\n'));
+        assert_eq!(
+            scanner.position(),
+            Position {
+                offset: 4,
+                line: 2,
+                column: 1,
+            }
+        );
+        assert_eq!(scanner.take(2), None);
+        assert_eq!(scanner.next_char(), Some('b'));
+        assert!(scanner.is_at_end());
+        assert_eq!(scanner.peek(), None);
+    }
+}
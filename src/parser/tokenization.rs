@@ -14,158 +14,241 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
-use errors::Error;
+use errors::{Error, ErrorKind};
+use parser::scanner::{is_whitespace, Scanner};
 use parser::Time;
+use std::fmt;
 
 /// Any token as it can appear in a cue sheet.
+///
+/// Numbers and times keep the text they were read from, see `Token::text`.
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub enum Token {
     /// A two digit long integer.
-    Number(u32),
+    Number {
+        /// The value of the number.
+        value: u32,
+        /// The number as it appeared in the source.
+        raw: String,
+    },
 
     /// Any string, notice commands and long numbers are all treated as String for the sake of this
     /// parser's implementation.
     String(String),
 
     /// A time (usually relative to the start of the file).
-    Time(Time),
+    Time {
+        /// The value of the time.
+        value: Time,
+        /// The time as it appeared in the source, e.g. `00:00:80` for an out of range value.
+        raw: String,
+    },
 }
 
-struct Reader {
-    chars: Vec<char>,
-    position: usize,
-}
-
-const DIGITS: [char; 10] = ['0', '1', '2', '3', '4', '5', '6', '7', '8', '9'];
-
-fn is_whitespace(c: char) -> bool {
-    c.is_whitespace() || c == '\u{feff}'
-}
-
-impl Reader {
-    fn new(source: &str) -> Self {
-        Reader {
-            chars: source.chars().collect(),
-            position: 0,
+impl Token {
+    /// Create a number token, as if `value` was written with two digits.
+    pub fn number(value: u32) -> Token {
+        Token::Number {
+            value,
+            raw: format!("{:02}", value),
         }
     }
 
-    /// True if there are still chars available to be read.
-    fn available(&self) -> bool {
-        self.chars.len() > self.position
+    /// Create a time token, as if `value` was written as `mm:ss:ff`.
+    pub fn time(value: Time) -> Token {
+        Token::Time {
+            raw: value.to_string(),
+            value,
+        }
     }
 
-    fn peek(&self, n: usize) -> Result<String, Error> {
-        if self.position + n <= self.chars.len() {
-            Ok(self.chars[self.position..self.position + n]
-                .into_iter()
-                .collect())
-        } else {
-            Err("Tried to read out of bounds of reader.".into())
+    /// Returns the text of the token as it appeared in the source, without any quotes.
+    ///
+    /// ```
+    /// use cue_sheet::parser::{tokenize, Token};
+    ///
+    /// assert_eq!(Token::number(1).text(), "01");
+    /// assert_eq!(Token::String("a b".to_string()).text(), "a b");
+    /// assert_eq!(tokenize("123:00:80").unwrap()[0].text(), "123:00:80");
+    /// ```
+    pub fn text(&self) -> String {
+        match *self {
+            Token::String(ref s)
+            | Token::Number { raw: ref s, .. }
+            | Token::Time { raw: ref s, .. } => s.clone(),
         }
     }
+}
 
-    fn take(&mut self, n: usize) -> Result<String, Error> {
-        self.peek(n).map(|s| {
-            self.position += n;
-            s
-        })
+/// Formats the token the way it has to be written in a cue sheet.
+///
+/// Strings are quoted if they would otherwise not be read back as the same string, quotes
+/// within them are escaped as `\"`.
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Token::Number { value, .. } => write!(f, "{:02}", value),
+            Token::String(ref s) => {
+                let tokens = tokenize(s).ok();
+                if tokens == Some(vec![Token::String(s.clone())]) {
+                    f.write_str(s)
+                } else {
+                    write!(f, "{}", Quoted(s))
+                }
+            }
+            Token::Time { ref value, .. } => write!(f, "{}", value),
+        }
     }
+}
 
-    fn try_take_time(&mut self) -> Option<Time> {
-        self.peek(8).ok().and_then(|s| s.parse().ok()).map(|time| {
-            self.position += 8;
-            time
-        })
-    }
+/// Location of a token in the source it was read from.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub struct Span {
+    /// Byte offset of the first character of the token.
+    pub start: usize,
 
-    // notice that numbers can only be two digits long
-    fn try_take_number(&mut self) -> Option<u32> {
-        // Check if the next two chars are digits.
-        let s = match self.peek(2) {
-            Ok(s) => s,
-            Err(_) => return None,
-        };
+    /// Byte offset after the last character of the token.
+    pub end: usize,
 
-        if s.chars()
-            .map(|c| DIGITS.contains(&c))
-            .fold(true, |old, new| old && new)
-        {
-            // Return a number if the third character is either whitespace or EOF.
-            if let Ok(s3) = self.peek(3) {
-                if !is_whitespace(s3.chars().nth(2).unwrap()) {
-                    return None;
-                }
-            }
+    /// Line (starting at 1) on which the token starts.
+    pub line: usize,
+}
 
-            // Parse the number.
-            self.position += 3;
-            Some(s.parse().unwrap())
-        } else {
-            None
-        }
-    }
+fn try_take_time(scanner: &mut Scanner) -> Option<Token> {
+    // Times are at least 8 chars long, but the minutes might have more than two digits.
+    let len = scanner
+        .rest()
+        .iter()
+        .take_while(|&&c| !is_whitespace(c))
+        .count();
+    let raw = scanner.peek_str(len)?;
+    let value = raw.parse().ok()?;
+    scanner.take(len);
+    Some(Token::Time { value, raw })
+}
 
-    fn take_string(&mut self) -> Result<String, Error> {
-        let mut result = Vec::new();
+// notice that numbers can only be two digits long
+fn try_take_number(scanner: &mut Scanner) -> Option<Token> {
+    let digits: Vec<u32> = scanner
+        .rest()
+        .iter()
+        .take(2)
+        .filter_map(|c| c.to_digit(10))
+        .collect();
+    if digits.len() != 2 {
+        return None;
+    }
 
-        // Check if string is quoted.
-        let first = self.take(1)?.chars().next().unwrap();
-        let is_quoted = first == '"';
-        if !is_quoted {
-            result.push(first);
+    // The number has to be followed by whitespace (including the `\r` of CRLF line endings
+    // and tabs) or EOF, otherwise it is the start of a string like `860B640B`.
+    if let Some(&next) = scanner.rest().get(2) {
+        if !is_whitespace(next) {
+            return None;
         }
+    }
 
-        // Now read as many chars as possible.
-        while let Ok(next) = self.take(1) {
-            let next = next.chars().next().unwrap();
-            if next == '"' {
-                if is_quoted {
-                    return Ok(result.into_iter().collect());
-                } else {
-                    return Err("The `\"` char is not allowed in strings.".into());
+    let raw = scanner.take(2)?;
+    Some(Token::Number {
+        value: digits[0] * 10 + digits[1],
+        raw,
+    })
+}
+
+/// Maximum number of characters in a quoted string.
+///
+/// Longer strings are rejected as soon as the limit is reached, instead of reading on for an
+/// unterminated quote.
+pub const MAX_STRING_LENGTH: usize = 4096;
+
+/// Reads a string, which is either quoted or ends before the next whitespace.
+///
+/// Within quotes `\"` is a quote which is part of the string, unless no other quote follows on
+/// the same line. That way strings ending with a backslash like `"C:\Music\"` are read as
+/// before.
+fn take_string(scanner: &mut Scanner) -> Result<String, Error> {
+    if scanner.peek() == Some('"') {
+        let start = scanner.position();
+        scanner.next_char();
+        let mut s = String::new();
+        loop {
+            if s.chars().count() >= MAX_STRING_LENGTH && scanner.peek() != Some('"') {
+                return Err(ErrorKind::StringTooLong(start.line, start.column).into());
+            }
+            match scanner.next_char() {
+                Some('"') => return Ok(s),
+                Some('\\') if scanner.peek() == Some('"') && is_escaped_quote(scanner.rest()) => {
+                    scanner.next_char();
+                    s.push('"');
                 }
-            } else if !is_quoted && is_whitespace(next) {
-                break;
-            } else {
-                result.push(next);
+                Some(c) => s.push(c),
+                None => return Err(ErrorKind::UnterminatedString(start.line, start.column).into()),
             }
         }
-
-        if is_quoted {
-            Err("Opened string not closed until EOF.".into())
+    } else {
+        let s = scanner.take_while(|c| c != '"' && !is_whitespace(c));
+        if scanner.peek() == Some('"') {
+            Err("The `\"` char is not allowed in strings.".into())
         } else {
-            Ok(result.into_iter().collect())
+            Ok(s)
         }
     }
+}
 
-    fn try_skip_whitespace(&mut self) {
-        while let Ok(next) = self.peek(1) {
-            let next = next.chars().next().unwrap();
-            if is_whitespace(next) {
-                self.position += 1;
-            } else {
-                return;
-            }
-        }
+/// Whether the quote at the start of `rest` is followed by another one on the same line.
+fn is_escaped_quote(rest: &[char]) -> bool {
+    rest[1..]
+        .iter()
+        .take_while(|&&c| c != '\n' && c != '\r')
+        .any(|&c| c == '"')
+}
+
+/// Writes a string in quotes, escaping the quotes within it as `\"`.
+pub(crate) struct Quoted<'a>(pub &'a str);
+
+impl<'a> fmt::Display for Quoted<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "\"{}\"", self.0.replace('"', "\\\""))
     }
 }
 
 /// Converts a string into a vector of tokens.
 pub fn tokenize(source: &str) -> Result<Vec<Token>, Error> {
+    Ok(tokenize_with_spans(source)?
+        .into_iter()
+        .map(|(token, _)| token)
+        .collect())
+}
+
+/// Converts a string into a vector of tokens together with their location in `source`.
+///
+/// ```
+/// use cue_sheet::parser::{tokenize_with_spans, Span, Token};
+///
+/// let tokens = tokenize_with_spans("TITLE\n  \"Loveless\"").unwrap();
+/// assert_eq!(
+///     tokens[1],
+///     (
+///         Token::String("Loveless".to_string()),
+///         Span { start: 8, end: 18, line: 2 }
+///     )
+/// );
+/// ```
+pub fn tokenize_with_spans(source: &str) -> Result<Vec<(Token, Span)>, Error> {
     let mut tokens = Vec::new();
-    let mut reader = Reader::new(source);
-
-    reader.try_skip_whitespace();
-    while reader.available() {
-        if let Some(time) = reader.try_take_time() {
-            tokens.push(Token::Time(time));
-        } else if let Some(num) = reader.try_take_number() {
-            tokens.push(Token::Number(num));
-        } else {
-            tokens.push(Token::String(reader.take_string()?));
-        }
-        reader.try_skip_whitespace();
+    let mut scanner = Scanner::new(source);
+
+    scanner.skip_whitespace();
+    while !scanner.is_at_end() {
+        let start = scanner.position();
+        let token = match try_take_time(&mut scanner) {
+            Some(token) => token,
+            None => match try_take_number(&mut scanner) {
+                Some(token) => token,
+                None => Token::String(take_string(&mut scanner)?),
+            },
+        };
+        tokens.push((token, scanner.span_from(start)));
+        scanner.skip_whitespace();
     }
 
     Ok(tokens)
@@ -177,42 +260,118 @@ mod tests {
 
     #[test]
     fn try_take_time() {
-        let mut r1 = Reader::new("10:11:12");
-        assert_eq!(r1.try_take_time(), Some(Time::new(10, 11, 12)));
-
-        let mut r2 = Reader::new("10");
-        assert_eq!(r2.try_take_time(), None);
-
-        let mut r3 = Reader::new(" ");
-        assert_eq!(r3.try_take_time(), None);
+        let mut r1 = Scanner::new("10:11:12");
+        assert_eq!(
+            super::try_take_time(&mut r1),
+            Some(Token::time(Time::new(10, 11, 12)))
+        );
+
+        let mut r2 = Scanner::new("10");
+        assert_eq!(super::try_take_time(&mut r2), None);
+
+        let mut r3 = Scanner::new(" ");
+        assert_eq!(super::try_take_time(&mut r3), None);
+
+        let mut r4 = Scanner::new("123:45:67 ");
+        assert_eq!(
+            super::try_take_time(&mut r4),
+            Some(Token::time(Time::new(123, 45, 67)))
+        );
+
+        let mut r5 = Scanner::new("1:45:67");
+        assert_eq!(super::try_take_time(&mut r5), None);
     }
 
     #[test]
     fn try_take_number() {
-        let mut r1 = Reader::new("12");
-        assert_eq!(r1.try_take_number(), Some(12));
+        let mut r1 = Scanner::new("12");
+        assert_eq!(super::try_take_number(&mut r1), Some(Token::number(12)));
 
-        let mut r2 = Reader::new("xyz");
-        assert_eq!(r2.try_take_number(), None);
+        let mut r2 = Scanner::new("xyz");
+        assert_eq!(super::try_take_number(&mut r2), None);
 
-        let mut r3 = Reader::new(" ");
-        assert_eq!(r3.try_take_number(), None);
+        let mut r3 = Scanner::new(" ");
+        assert_eq!(super::try_take_number(&mut r3), None);
+
+        let mut r4 = Scanner::new("1");
+        assert_eq!(super::try_take_number(&mut r4), None);
+
+        for source in &["01\r\n", "01\t", "01\n", "01\r"] {
+            assert_eq!(
+                super::try_take_number(&mut Scanner::new(source)),
+                Some(Token::number(1))
+            );
+        }
+        assert_eq!(super::try_take_number(&mut Scanner::new("011")), None);
+        assert_eq!(super::try_take_number(&mut Scanner::new("0\t1")), None);
+    }
+
+    #[test]
+    fn crlf_numbers() {
+        let source = "TRACK 01 AUDIO\r\nINDEX\t01\t00:00:00\r\nTRACK 02\r\nISRC 12\r\n";
+        let tokens = tokenize(source).unwrap();
+        assert_eq!(
+            tokens,
+            vec![
+                Token::String("TRACK".to_string()),
+                Token::number(1),
+                Token::String("AUDIO".to_string()),
+                Token::String("INDEX".to_string()),
+                Token::number(1),
+                Token::time(Time::new(0, 0, 0)),
+                Token::String("TRACK".to_string()),
+                Token::number(2),
+                Token::String("ISRC".to_string()),
+                Token::number(12),
+            ]
+        );
     }
 
     #[test]
     fn string_starting_with_num() {
-        let mut r1 = Reader::new("860B640B");
-        assert_eq!(r1.try_take_number(), None);
-        assert_eq!(r1.take_string().unwrap(), "860B640B".to_string());
+        let mut r1 = Scanner::new("860B640B");
+        assert_eq!(super::try_take_number(&mut r1), None);
+        assert_eq!(super::take_string(&mut r1).unwrap(), "860B640B".to_string());
     }
 
     #[test]
     fn take_string() {
-        let mut r1 = Reader::new("abc");
-        assert_eq!(r1.take_string().unwrap(), "abc".to_string());
+        let mut r1 = Scanner::new("abc");
+        assert_eq!(super::take_string(&mut r1).unwrap(), "abc".to_string());
 
-        let mut r2 = Reader::new("\"abc\"");
-        assert_eq!(r2.take_string().unwrap(), "abc".to_string());
+        let mut r2 = Scanner::new("\"abc\"");
+        assert_eq!(super::take_string(&mut r2).unwrap(), "abc".to_string());
+    }
+
+    #[test]
+    fn escaped_quotes() {
+        let tokens = tokenize(r#"TITLE "say \"hi\"" "C:\Music\""#).unwrap();
+        assert_eq!(tokens[1], Token::String("say \"hi\"".to_string()));
+        assert_eq!(tokens[2], Token::String("C:\\Music\\".to_string()));
+
+        for s in &["say \"hi\"", "\"", "a\"b", "C:\\Music\\"] {
+            let token = Token::String(s.to_string());
+            assert_eq!(tokenize(&token.to_string()).unwrap(), vec![token]);
+        }
+    }
+
+    #[test]
+    fn malformed_strings() {
+        match *tokenize("TITLE x\nTITLE \"abc").unwrap_err().kind() {
+            ErrorKind::UnterminatedString(2, 7) => {}
+            ref kind => panic!("unexpected error {:?}", kind),
+        }
+
+        let long = "x".repeat(MAX_STRING_LENGTH);
+        let tokens = tokenize(&format!("\"{}\"", long)).unwrap();
+        assert_eq!(tokens, vec![Token::String(long.clone())]);
+        match *tokenize(&format!("TITLE \"{}x\"", long))
+            .unwrap_err()
+            .kind()
+        {
+            ErrorKind::StringTooLong(1, 7) => {}
+            ref kind => panic!("unexpected error {:?}", kind),
+        }
     }
 
     #[test]
@@ -223,11 +382,79 @@ mod tests {
         println!("{:?}", tokens);
         assert_eq!(tokens.len(), 4);
         assert_eq!(tokens[0], Token::String("ABC".to_string()));
-        assert_eq!(tokens[1], Token::Number(12));
-        assert_eq!(tokens[2], Token::Time(Time::new(10, 10, 30)));
+        assert_eq!(tokens[1], Token::number(12));
+        assert_eq!(tokens[2], Token::time(Time::new(10, 10, 30)));
         assert_eq!(tokens[3], Token::String("Abc".to_string()));
     }
 
+    #[test]
+    fn raw_lexemes() {
+        let tokens = tokenize("INDEX 01 00:01:80").unwrap();
+        assert_eq!(tokens[1].text(), "01");
+        assert_eq!(
+            tokens[2],
+            Token::Time {
+                value: Time::new(0, 1, 80),
+                raw: "00:01:80".to_string(),
+            }
+        );
+        assert_eq!(tokens[2].text(), "00:01:80");
+    }
+
+    #[test]
+    fn display() {
+        assert_eq!(Token::number(1).to_string(), "01");
+        assert_eq!(Token::String("abc".to_string()).to_string(), "abc");
+        assert_eq!(Token::String("a b".to_string()).to_string(), "\"a b\"");
+        assert_eq!(Token::String("12".to_string()).to_string(), "\"12\"");
+        assert_eq!(Token::String("".to_string()).to_string(), "\"\"");
+        assert_eq!(Token::time(Time::new(1, 2, 3)).to_string(), "01:02:03");
+    }
+
+    #[test]
+    fn spans() {
+        let source = "ABC 12\r\n\"ä b\" 10:10:30 x";
+        let spans: Vec<Span> = tokenize_with_spans(source)
+            .unwrap()
+            .into_iter()
+            .map(|(_, span)| span)
+            .collect();
+
+        assert_eq!(
+            spans[0],
+            Span {
+                start: 0,
+                end: 3,
+                line: 1
+            }
+        );
+        assert_eq!(
+            spans[1],
+            Span {
+                start: 4,
+                end: 6,
+                line: 1
+            }
+        );
+        assert_eq!(
+            spans[2],
+            Span {
+                start: 8,
+                end: 14,
+                line: 2
+            }
+        );
+        assert_eq!(&source[spans[3].start..spans[3].end], "10:10:30");
+        assert_eq!(
+            spans[4],
+            Span {
+                start: 24,
+                end: 25,
+                line: 2
+            }
+        );
+    }
+
     #[test]
     fn test_strings() {
         let source = r#"ABC "xyz xyz 12 10:10:30" " abc ""#;
@@ -0,0 +1,261 @@
+// cue_sheet
+// Copyright (C) 2017  Leonardo Schwarz <mail@leoschwarz.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Determining the length of audio files from their headers.
+//!
+//! Only uncompressed WAVE and AIFF files are supported, which can be inspected without any
+//! decoding. This module is only available with the `audio-probe` feature enabled.
+
+use errors::Error;
+use filesystem::{FileSystem, LocalFileSystem};
+use parser::Time;
+use std::io::{BufReader, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use tracklist::{DurationProvider, TrackFile};
+
+fn read_array<R: Read, const N: usize>(reader: &mut R) -> Result<[u8; N], Error> {
+    let mut buf = [0; N];
+    reader.read_exact(&mut buf)?;
+    Ok(buf)
+}
+
+/// Skip `len` bytes of chunk data, including the pad byte of odd sized chunks.
+fn skip_chunk<R: Seek>(reader: &mut R, len: u32) -> Result<(), Error> {
+    reader.seek(SeekFrom::Current(i64::from(len) + i64::from(len % 2)))?;
+    Ok(())
+}
+
+/// Convert an 80 bit IEEE 754 extended precision number (as used by AIFF) to `f64`.
+fn extended_to_f64(bytes: [u8; 10]) -> f64 {
+    let exponent = i32::from(u16::from_be_bytes([bytes[0] & 0x7f, bytes[1]]));
+    let mut mantissa = [0; 8];
+    mantissa.copy_from_slice(&bytes[2..]);
+    let mantissa = u64::from_be_bytes(mantissa) as f64;
+    let value = mantissa * 2f64.powi(exponent - 16383 - 63);
+    if bytes[0] & 0x80 != 0 {
+        -value
+    } else {
+        value
+    }
+}
+
+/// Determine the length of a WAVE file from its header.
+pub fn wave_length<R: Read + Seek>(reader: &mut R) -> Result<Time, Error> {
+    let header: [u8; 12] = read_array(reader)?;
+    if &header[0..4] != b"RIFF" || &header[8..12] != b"WAVE" {
+        return Err("Not a WAVE file.".into());
+    }
+
+    let mut format = None;
+    loop {
+        let id: [u8; 4] = read_array(reader)?;
+        let len = u32::from_le_bytes(read_array(reader)?);
+        match &id {
+            b"fmt " => {
+                let fmt: [u8; 16] = read_array(reader)?;
+                let sample_rate = u32::from_le_bytes([fmt[4], fmt[5], fmt[6], fmt[7]]);
+                let block_align = u16::from_le_bytes([fmt[12], fmt[13]]);
+                format = Some((sample_rate, block_align));
+                skip_chunk(reader, len.saturating_sub(16))?;
+            }
+            b"data" => {
+                let (sample_rate, block_align) =
+                    format.ok_or("WAVE data chunk precedes the fmt chunk.")?;
+                if sample_rate == 0 || block_align == 0 {
+                    return Err("Invalid WAVE fmt chunk.".into());
+                }
+                let samples = i64::from(len / u32::from(block_align));
+                return Ok(Time::from_samples(samples, sample_rate));
+            }
+            _ => skip_chunk(reader, len)?,
+        }
+    }
+}
+
+/// Determine the length of an AIFF or AIFF-C file from its header.
+pub fn aiff_length<R: Read + Seek>(reader: &mut R) -> Result<Time, Error> {
+    let header: [u8; 12] = read_array(reader)?;
+    if &header[0..4] != b"FORM" || (&header[8..12] != b"AIFF" && &header[8..12] != b"AIFC") {
+        return Err("Not an AIFF file.".into());
+    }
+
+    loop {
+        let id: [u8; 4] = read_array(reader)?;
+        let len = u32::from_be_bytes(read_array(reader)?);
+        if &id == b"COMM" {
+            let comm: [u8; 18] = read_array(reader)?;
+            let frames = u32::from_be_bytes([comm[2], comm[3], comm[4], comm[5]]);
+            let mut rate = [0; 10];
+            rate.copy_from_slice(&comm[8..18]);
+            let sample_rate = extended_to_f64(rate).round();
+            if !(1.0..=f64::from(u32::MAX)).contains(&sample_rate) {
+                return Err("Invalid AIFF sample rate.".into());
+            }
+            return Ok(Time::from_samples(i64::from(frames), sample_rate as u32));
+        }
+        skip_chunk(reader, len)?;
+    }
+}
+
+/// Determine the length of the WAVE or AIFF file at `path`.
+///
+/// Samples at the end of the file which don't make up a full frame are not counted.
+pub fn file_length(path: &Path) -> Result<Time, Error> {
+    file_length_in(path, &LocalFileSystem)
+}
+
+/// Determine the length of the WAVE or AIFF file at `path` in `fs`, see `file_length`.
+pub fn file_length_in<F: FileSystem + ?Sized>(path: &Path, fs: &F) -> Result<Time, Error> {
+    let mut reader = BufReader::new(fs.open(path)?);
+    let header: [u8; 4] = read_array(&mut reader)?;
+    reader.seek(SeekFrom::Start(0))?;
+    match &header {
+        b"RIFF" => wave_length(&mut reader),
+        b"FORM" => aiff_length(&mut reader),
+        _ => Err(format!("Unsupported audio file: {}", path.display()).into()),
+    }
+}
+
+/// Returns whether the file at `path` in `fs` starts like a WAVE or AIFF file, i.e. it isn't
+/// compressed audio, which is sometimes declared as `WAVE` too.
+///
+/// Files too short to contain a header are treated as uncompressed, reading them fails later.
+pub fn is_uncompressed_in<F: FileSystem + ?Sized>(path: &Path, fs: &F) -> Result<bool, Error> {
+    let mut magic = Vec::new();
+    fs.open(path)?.take(4).read_to_end(&mut magic)?;
+    Ok(magic.len() < 4 || &magic[..] == b"RIFF" || &magic[..] == b"FORM")
+}
+
+/// A `DurationProvider` reading the headers of the files referenced by a tracklist.
+///
+/// The files are resolved with `TrackFile::resolve`.
+///
+/// ```no_run
+/// use cue_sheet::probe::AudioProbe;
+/// use cue_sheet::tracklist::Tracklist;
+///
+/// let mut tracklist = Tracklist::parse("FILE \"a.wav\" WAVE").unwrap();
+/// tracklist.fill_durations(&AudioProbe::new("/music/album")).unwrap();
+/// ```
+#[derive(Clone, Debug)]
+pub struct AudioProbe<F = LocalFileSystem> {
+    directory: PathBuf,
+    fs: F,
+}
+
+impl AudioProbe {
+    /// Create a probe resolving file names relative to `directory`, which usually is the
+    /// directory containing the cue sheet.
+    pub fn new<P: Into<PathBuf>>(directory: P) -> Self {
+        AudioProbe::with_file_system(directory, LocalFileSystem)
+    }
+}
+
+impl<F: FileSystem> AudioProbe<F> {
+    /// Create a probe reading the files from `fs` instead of the local file system.
+    pub fn with_file_system<P: Into<PathBuf>>(directory: P, fs: F) -> Self {
+        AudioProbe {
+            directory: directory.into(),
+            fs,
+        }
+    }
+}
+
+impl<F: FileSystem> DurationProvider for AudioProbe<F> {
+    fn file_length(&self, file: &TrackFile) -> Result<Time, Error> {
+        let path = file
+            .resolve(&self.directory, &self.fs)
+            .ok_or_else(|| format!("File not found: {:?}", file.name))?;
+        file_length_in(&path, &self.fs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use filesystem::MemoryFileSystem;
+    use std::io::Cursor;
+    use std::{env, fs};
+    use tracklist::Tracklist;
+
+    fn wave_file(samples: u32) -> Vec<u8> {
+        let mut bytes = b"RIFF\0\0\0\0WAVELIST\x03\0\0\0abc\0fmt \x10\0\0\0".to_vec();
+        bytes.extend_from_slice(&[1, 0, 2, 0]);
+        bytes.extend_from_slice(&44100u32.to_le_bytes());
+        bytes.extend_from_slice(&176400u32.to_le_bytes());
+        bytes.extend_from_slice(&[4, 0, 16, 0]);
+        bytes.extend_from_slice(b"data");
+        bytes.extend_from_slice(&(samples * 4).to_le_bytes());
+        bytes
+    }
+
+    #[test]
+    fn wave() {
+        let mut reader = Cursor::new(wave_file(44100 * 61 + 588 * 3));
+        assert_eq!(wave_length(&mut reader).unwrap(), Time::new(1, 1, 3));
+
+        let mut reader = Cursor::new(b"RIFF\0\0\0\0AVI ".to_vec());
+        assert!(wave_length(&mut reader).is_err());
+    }
+
+    #[test]
+    fn aiff() {
+        let mut bytes = b"FORM\0\0\0\0AIFFCOMM\0\0\0\x12\0\x02".to_vec();
+        bytes.extend_from_slice(&(44100u32 * 90).to_be_bytes());
+        bytes.extend_from_slice(&[0, 16, 0x40, 0x0e, 0xac, 0x44, 0, 0, 0, 0, 0, 0]);
+        let mut reader = Cursor::new(bytes);
+        assert_eq!(aiff_length(&mut reader).unwrap(), Time::new(1, 30, 0));
+    }
+
+    #[test]
+    fn probe() {
+        let dir = env::temp_dir().join(format!("cue_sheet_probe_{}", ::std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("a.wav"), wave_file(44100 * 2)).unwrap();
+
+        let mut tracklist = Tracklist::parse(
+            "FILE a.wav WAVE\nTRACK 01 AUDIO\nINDEX 01 00:00:00\nTRACK 02 AUDIO\nINDEX 01 00:01:00",
+        )
+        .unwrap();
+        tracklist.fill_durations(&AudioProbe::new(&dir)).unwrap();
+        assert_eq!(
+            tracklist.files[0].tracks[1].duration,
+            Some(Time::new(0, 1, 0))
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn probe_memory_file_system() {
+        let mut files = MemoryFileSystem::new();
+        files.insert(Path::new("rip").join("a.wav"), wave_file(44100 * 3));
+
+        let mut tracklist =
+            Tracklist::parse("FILE \"C:\\rips\\a.wav\" WAVE\nTRACK 01 AUDIO\nINDEX 01 00:00:00")
+                .unwrap();
+        tracklist
+            .fill_durations(&AudioProbe::with_file_system("rip", &files))
+            .unwrap();
+        assert_eq!(
+            tracklist.files[0].tracks[0].duration,
+            Some(Time::new(0, 3, 0))
+        );
+        assert!(tracklist
+            .fill_durations(&AudioProbe::with_file_system("other", &files))
+            .is_err());
+    }
+}
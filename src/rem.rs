@@ -0,0 +1,186 @@
+// cue_sheet
+// Copyright (C) 2017  Leonardo Schwarz <mail@leoschwarz.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Custom handling of `REM` comments.
+//!
+//! Applications can store their own data in cue sheets using `REM` comments with a key of
+//! their choice. Implementing `RemHandler` allows converting such comments into typed data.
+
+use errors::Error;
+use parser::Token;
+use sheet::CueSheet;
+use tracklist::{TrackFile, Tracklist};
+
+/// Where a `REM` comment appeared in the cue sheet.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum RemScope {
+    /// The comment is part of the disc level information.
+    Disc,
+
+    /// The comment belongs to the track with the specified number.
+    Track(u32),
+}
+
+/// Handler for `REM` comments, see `Tracklist::handle_rem`.
+pub trait RemHandler {
+    /// Handle one `REM` comment.
+    ///
+    /// Returns `true` if the comment was handled and should be removed from the list of
+    /// comments, or `false` if it should be kept.
+    fn handle(&mut self, scope: RemScope, key: &str, value: &Token) -> Result<bool, Error>;
+}
+
+impl<F> RemHandler for F
+where
+    F: FnMut(RemScope, &str, &Token) -> Result<bool, Error>,
+{
+    fn handle(&mut self, scope: RemScope, key: &str, value: &Token) -> Result<bool, Error> {
+        self(scope, key, value)
+    }
+}
+
+fn handle_list<H: RemHandler + ?Sized>(
+    rem: &[(String, Token)],
+    scope: RemScope,
+    handler: &mut H,
+) -> Result<Vec<bool>, Error> {
+    rem.iter()
+        .map(|(key, value)| handler.handle(scope, key, value))
+        .collect()
+}
+
+fn remove_handled(rem: &mut Vec<(String, Token)>, handled: Vec<bool>) {
+    let mut handled = handled.into_iter();
+    rem.retain(|_| !handled.next().unwrap_or(false));
+}
+
+fn handle_all<H: RemHandler + ?Sized>(
+    rem: &mut Vec<(String, Token)>,
+    files: &mut [TrackFile],
+    handler: &mut H,
+) -> Result<(), Error> {
+    // Nothing is removed before all comments were handled, so that an error leaves the
+    // comments untouched.
+    let disc = handle_list(rem, RemScope::Disc, handler)?;
+    let mut tracks = Vec::new();
+    for file in files.iter() {
+        for track in &file.tracks {
+            tracks.push(handle_list(
+                &track.rem,
+                RemScope::Track(track.number),
+                handler,
+            )?);
+        }
+    }
+
+    remove_handled(rem, disc);
+    let mut tracks = tracks.into_iter();
+    for file in files {
+        for track in &mut file.tracks {
+            remove_handled(&mut track.rem, tracks.next().unwrap_or_default());
+        }
+    }
+    Ok(())
+}
+
+impl Tracklist {
+    /// Pass all `REM` comments (disc and track level) to `handler` in the order they appear.
+    ///
+    /// Comments the handler reports as handled are removed, the first error aborts and leaves all
+    /// comments in place.
+    ///
+    /// ```
+    /// use cue_sheet::errors::Error;
+    /// use cue_sheet::parser::Token;
+    /// use cue_sheet::rem::RemScope;
+    /// use cue_sheet::tracklist::Tracklist;
+    ///
+    /// let mut tracklist = Tracklist::parse(
+    ///     "REM X-MYAPP-RATING 4\nREM GENRE Shoegaze\n\
+    ///      FILE a.wav WAVE\nTRACK 01 AUDIO\nREM X-MYAPP-RATING 5\nINDEX 01 00:00:00",
+    /// )
+    /// .unwrap();
+    ///
+    /// let mut ratings = Vec::new();
+    /// tracklist
+    ///     .handle_rem(&mut |scope, key: &str, value: &Token| -> Result<bool, Error> {
+    ///         if key != "X-MYAPP-RATING" {
+    ///             return Ok(false);
+    ///         }
+    ///         ratings.push((scope, value.text().parse::<u8>()?));
+    ///         Ok(true)
+    ///     })
+    ///     .unwrap();
+    ///
+    /// assert_eq!(ratings, vec![(RemScope::Disc, 4), (RemScope::Track(1), 5)]);
+    /// assert_eq!(tracklist.rem.len(), 1);
+    /// ```
+    pub fn handle_rem<H: RemHandler + ?Sized>(&mut self, handler: &mut H) -> Result<(), Error> {
+        handle_all(&mut self.rem, &mut self.files, handler)
+    }
+}
+
+impl CueSheet {
+    /// Pass all `REM` comments (disc and track level) to `handler`, see `Tracklist::handle_rem`.
+    pub fn handle_rem<H: RemHandler + ?Sized>(&mut self, handler: &mut H) -> Result<(), Error> {
+        handle_all(&mut self.rem, &mut self.files, handler)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Counter(usize);
+
+    impl RemHandler for Counter {
+        fn handle(&mut self, _: RemScope, key: &str, _: &Token) -> Result<bool, Error> {
+            if key == "FAIL" {
+                return Err("failed".into());
+            }
+            self.0 += 1;
+            Ok(false)
+        }
+    }
+
+    #[test]
+    fn handler() {
+        let src = "REM A 1\nFILE a.wav WAVE\nTRACK 01 AUDIO\nREM B 2\nINDEX 01 00:00:00";
+        let mut sheet = CueSheet::parse(src).unwrap();
+        let mut counter = Counter(0);
+        sheet.handle_rem(&mut counter).unwrap();
+        assert_eq!(counter.0, 2);
+        assert_eq!(sheet.files[0].tracks[0].rem.len(), 1);
+
+        let mut sheet = CueSheet::parse("REM FAIL\nREM A 1").unwrap();
+        assert!(sheet.handle_rem(&mut counter).is_err());
+    }
+
+    #[test]
+    fn error_keeps_comments() {
+        let src = "REM A 1\nFILE a.wav WAVE\nTRACK 01 AUDIO\nREM FAIL\nINDEX 01 00:00:00";
+        let mut tracklist = Tracklist::parse(src).unwrap();
+        let result = tracklist.handle_rem(&mut |_, key: &str, _: &Token| -> Result<bool, Error> {
+            if key == "FAIL" {
+                return Err("failed".into());
+            }
+            Ok(true)
+        });
+        assert!(result.is_err());
+        assert_eq!(tracklist.rem.len(), 1);
+        assert_eq!(tracklist.files[0].tracks[0].rem.len(), 1);
+    }
+}
@@ -0,0 +1,406 @@
+// cue_sheet
+// Copyright (C) 2017  Leonardo Schwarz <mail@leoschwarz.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! The disc level representation of a cue sheet.
+
+use errors::Error;
+use generator::Generator;
+use parser::{self, Command, CommandLine, FileFormat, ParseOptions, Token};
+use std::path::Path;
+use std::str::FromStr;
+use tracklist::{push_file_commands, update_album_starts, DurationMode, TrackFile};
+use warning::Warning;
+use writer::{self, WriteOptions};
+
+/// A cue sheet with all disc level information, and the files it describes.
+///
+/// `Tracklist` provides a simpler view of the same data, which can be obtained with
+/// `Tracklist::from`.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct CueSheet {
+    /// The 13-digit UPC/EAN code of the disc.
+    pub catalog: Option<String>,
+
+    /// Path to a file containing CD-Text information.
+    pub cdtextfile: Option<String>,
+
+    /// Disc level `REM` comments as (key, value) pairs in the order they appeared.
+    pub rem: Vec<(String, Token)>,
+
+    /// Title of the disc.
+    pub title: Option<String>,
+
+    /// Performer of the disc.
+    pub performer: Option<String>,
+
+    /// Songwriter of the disc.
+    pub songwriter: Option<String>,
+
+    /// Files described by the cue sheet.
+    pub files: Vec<TrackFile>,
+
+    /// Nonstandard number of frames per second of the times, see `ParseOptions::frame_rate`.
+    pub frame_rate: Option<u8>,
+
+    /// Disc level `CATALOG`, `CDTEXTFILE`, `TITLE`, `PERFORMER` and `SONGWRITER` values which
+    /// were replaced by a later command of the same kind, as (keyword, value) pairs in the
+    /// order they appeared.
+    ///
+    /// Strict parsing rejects such cue sheets, so this is only filled outside of strict mode.
+    /// The values are not written back by `CueSheet::to_cue_string`.
+    pub superseded: Vec<(String, String)>,
+}
+
+impl CueSheet {
+    /// Parse a cue sheet (content provided as `source`).
+    pub fn parse(source: &str) -> Result<CueSheet, Error> {
+        CueSheet::parse_with_options(source, ParseOptions::global_default())
+    }
+
+    /// Parse a cue sheet (content provided as `source`) using the provided `options`.
+    pub fn parse_with_options(source: &str, options: &ParseOptions) -> Result<CueSheet, Error> {
+        Ok(CueSheet::parse_with_warnings(source, options)?.0)
+    }
+
+    /// Parse a cue sheet (content provided as `source`) using the provided `options`, also
+    /// returning warnings about the problems which were recovered from.
+    ///
+    /// `ParseOptions::duration_mode` and `ParseOptions::metadata_cleanup` are applied to the
+    /// tracks and metadata like for a `Tracklist`, but a `CueSheet` keeps neither the mode nor
+    /// the raw metadata.
+    pub fn parse_with_warnings(
+        source: &str,
+        options: &ParseOptions,
+    ) -> Result<(CueSheet, Vec<Warning>), Error> {
+        let mut warnings = Vec::new();
+        let mut sheet = CueSheet::parse_collecting(source, options, &mut warnings)?;
+        sheet.apply_options(options)?;
+        Ok((sheet, warnings))
+    }
+
+    /// Applies `ParseOptions::duration_mode` and `ParseOptions::metadata_cleanup`.
+    fn apply_options(&mut self, options: &ParseOptions) -> Result<(), Error> {
+        let cleanup = &options.metadata_cleanup;
+        cleanup.apply_opt(&mut self.title);
+        cleanup.apply_opt(&mut self.performer);
+        cleanup.apply_opt(&mut self.songwriter);
+        for file in &mut self.files {
+            for track in &mut file.tracks {
+                if options.duration_mode == DurationMode::WithPostgap {
+                    track.add_postgap()?;
+                }
+                cleanup.apply_opt(&mut track.title);
+                cleanup.apply_opt(&mut track.performer);
+                cleanup.apply_opt(&mut track.songwriter);
+            }
+        }
+        Ok(())
+    }
+
+    /// Like `CueSheet::parse_with_warnings`, but the warnings are added to `warnings`, so the
+    /// ones found before an error are kept.
+    pub(crate) fn parse_collecting(
+        source: &str,
+        options: &ParseOptions,
+        warnings: &mut Vec<Warning>,
+    ) -> Result<CueSheet, Error> {
+        let mut commands = parser::parse_cue_collecting(source, options, warnings)?;
+        if let (Some(name), false) = (options.implicit_file.as_ref(), options.strict) {
+            insert_implicit_file(&mut commands, name);
+        }
+        CueSheet::from_lines(commands, options, warnings)
+    }
+
+    /// Build a cue sheet from a list of commands, see `Tracklist::from_commands`.
+    pub fn from_commands(commands: Vec<Command>) -> Result<CueSheet, Error> {
+        CueSheet::from_lines(
+            commands
+                .into_iter()
+                .enumerate()
+                .map(|(i, command)| (command, i + 1))
+                .collect(),
+            &ParseOptions::new().strict(true),
+            &mut Vec::new(),
+        )
+    }
+
+    /// Builds a cue sheet from parsed `commands`, paired with the lines they were read from.
+    ///
+    /// `CATALOG` and `CDTEXTFILE` can only describe the disc, so they are accepted anywhere,
+    /// even after the first `FILE` (which strict mode rejects before getting here). Like for
+    /// the other disc level commands the last one wins.
+    ///
+    /// Tracks starting before the previous track of their file are an error in strict mode and
+    /// otherwise reported in `warnings`. Durations are computed at `ParseOptions::frame_rate`.
+    pub(crate) fn from_lines(
+        mut commands: Vec<CommandLine>,
+        options: &ParseOptions,
+        warnings: &mut Vec<Warning>,
+    ) -> Result<CueSheet, Error> {
+        let mut sheet = CueSheet {
+            frame_rate: options.frame_rate,
+            ..Default::default()
+        };
+        commands.retain(|(command, _)| match *command {
+            Command::Catalog(ref c) => {
+                supersede(&mut sheet, "CATALOG", c.clone());
+                false
+            }
+            Command::Cdtextfile(ref c) => {
+                supersede(&mut sheet, "CDTEXTFILE", c.clone());
+                false
+            }
+            _ => true,
+        });
+
+        while !commands.is_empty() {
+            match commands[0].0.clone() {
+                Command::Performer(p) => supersede(&mut sheet, "PERFORMER", p),
+                Command::Rem(key, value) => sheet.rem.push((key, value)),
+                Command::Songwriter(s) => supersede(&mut sheet, "SONGWRITER", s),
+                Command::Title(t) => supersede(&mut sheet, "TITLE", t),
+                _ => break,
+            }
+            commands.remove(0);
+        }
+
+        while let Some((Command::File(..), _)) = commands.first() {
+            let file =
+                TrackFile::consume(&mut commands, sheet.files.last_mut(), options, warnings)?;
+            sheet.files.push(file);
+        }
+        update_album_starts(&mut sheet.files, &[], sheet.frame_rate)?;
+
+        Ok(sheet)
+    }
+
+    /// Guess which program generated the cue sheet, see `Generator::detect`.
+    pub fn detect_generator(&self) -> Generator {
+        Generator::detect(&self.rem)
+    }
+
+    /// Returns the commands describing this cue sheet.
+    pub fn to_commands(&self) -> Vec<Command> {
+        let mut commands = Vec::new();
+
+        for (key, value) in &self.rem {
+            commands.push(Command::Rem(key.clone(), value.clone()));
+        }
+        if let Some(ref catalog) = self.catalog {
+            commands.push(Command::Catalog(catalog.clone()));
+        }
+        if let Some(ref cdtextfile) = self.cdtextfile {
+            commands.push(Command::Cdtextfile(cdtextfile.clone()));
+        }
+        if let Some(ref performer) = self.performer {
+            commands.push(Command::Performer(performer.clone()));
+        }
+        if let Some(ref songwriter) = self.songwriter {
+            commands.push(Command::Songwriter(songwriter.clone()));
+        }
+        if let Some(ref title) = self.title {
+            commands.push(Command::Title(title.clone()));
+        }
+
+        push_file_commands(&self.files, &mut commands);
+
+        commands
+    }
+
+    /// Format the cue sheet as text.
+    pub fn to_cue_string(&self) -> String {
+        let mut s = String::new();
+        writer::write_commands(&self.to_commands(), &mut s, WriteOptions::global_default())
+            .unwrap();
+        s
+    }
+
+    /// Format the cue sheet as text using the provided `options`.
+    pub fn to_cue_string_with_options(&self, options: &WriteOptions) -> String {
+        let mut s = String::new();
+        writer::write_commands(&self.to_commands(), &mut s, options).unwrap();
+        s
+    }
+}
+
+/// Parses a cue sheet with the default `ParseOptions`, see `CueSheet::parse`.
+impl FromStr for CueSheet {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        CueSheet::parse(s)
+    }
+}
+
+/// Sets the disc level value of `keyword` to `value`, keeping a previous value in
+/// `CueSheet::superseded`.
+fn supersede(sheet: &mut CueSheet, keyword: &str, value: String) {
+    let field = match keyword {
+        "CATALOG" => &mut sheet.catalog,
+        "CDTEXTFILE" => &mut sheet.cdtextfile,
+        "PERFORMER" => &mut sheet.performer,
+        "SONGWRITER" => &mut sheet.songwriter,
+        _ => &mut sheet.title,
+    };
+    if let Some(previous) = field.replace(value) {
+        sheet.superseded.push((keyword.to_string(), previous));
+    }
+}
+
+/// Inserts a `FILE` command for a file named `name` before the first `TRACK` command, unless
+/// it is already preceded by one.
+fn insert_implicit_file(commands: &mut Vec<CommandLine>, name: &str) {
+    for i in 0..commands.len() {
+        match commands[i] {
+            (Command::File(..), _) => return,
+            (Command::Track(..), line) => {
+                let format = FileFormat::from_path(Path::new(name)).unwrap_or(FileFormat::Wave);
+                commands.insert(
+                    i,
+                    (Command::File(name.to_string(), format, Vec::new()), line),
+                );
+                return;
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parser::Time;
+    use tracklist::Tracklist;
+    use warning::WarningKind;
+
+    #[test]
+    fn disc_level_commands() {
+        let source = r#"REM GENRE Alternative
+                        CATALOG 0724384264525
+                        CDTEXTFILE "disc.cdt"
+                        PERFORMER "My Bloody Valentine"
+                        SONGWRITER "Kevin Shields"
+                        TITLE "Loveless"
+                        FILE "Loveless.wav" WAVE
+                          TRACK 01 AUDIO
+                            INDEX 01 00:00:00"#;
+
+        let sheet = CueSheet::parse(source).unwrap();
+        assert_eq!(sheet.catalog, Some("0724384264525".to_string()));
+        assert_eq!(sheet.cdtextfile, Some("disc.cdt".to_string()));
+        assert_eq!(sheet.songwriter, Some("Kevin Shields".to_string()));
+        assert_eq!(sheet.files.len(), 1);
+        assert_eq!(CueSheet::parse(&sheet.to_cue_string()).unwrap(), sheet);
+        assert_eq!(source.parse::<CueSheet>().unwrap(), sheet);
+
+        let tracklist = Tracklist::from(sheet.clone());
+        assert_eq!(tracklist.title, sheet.title);
+        assert_eq!(tracklist.performer, sheet.performer);
+        assert_eq!(tracklist.files, sheet.files);
+    }
+
+    #[test]
+    fn interleaved_header() {
+        let source = r#"FILE "Loveless.wav" WAVE
+                          TRACK 01 AUDIO
+                            CATALOG 0724384264525
+                            INDEX 01 00:00:00
+                          TRACK 02 AUDIO
+                            INDEX 01 04:17:52
+                        CDTEXTFILE "disc.cdt""#;
+
+        let sheet = CueSheet::parse(source).unwrap();
+        assert_eq!(sheet.catalog, Some("0724384264525".to_string()));
+        assert_eq!(sheet.cdtextfile, Some("disc.cdt".to_string()));
+        assert_eq!(sheet.files[0].tracks.len(), 2);
+
+        let source = "CATALOG 0724384264525\nFILE a.wav WAVE\nTRACK 01 AUDIO\n\
+                      CATALOG 5012093631421\nINDEX 01 00:00:00";
+        let (sheet, warnings) = CueSheet::parse_with_warnings(source, &Default::default()).unwrap();
+        assert_eq!(sheet.catalog, Some("5012093631421".to_string()));
+        assert_eq!(
+            sheet.superseded,
+            vec![("CATALOG".to_string(), "0724384264525".to_string())]
+        );
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].line, 4);
+        assert_eq!(
+            warnings[0].kind,
+            WarningKind::DuplicateCommand("CATALOG".to_string())
+        );
+
+        let options = ParseOptions {
+            strict: true,
+            ..Default::default()
+        };
+        assert!(CueSheet::parse_with_options(source, &options).is_err());
+    }
+
+    #[test]
+    fn duplicate_header() {
+        let source =
+            "TITLE Loveless\nPERFORMER MBV\nTITLE \"Loveless (Remastered)\"\nFILE a.wav WAVE";
+        let (sheet, warnings) = CueSheet::parse_with_warnings(source, &Default::default()).unwrap();
+        assert_eq!(sheet.title, Some("Loveless (Remastered)".to_string()));
+        assert_eq!(
+            sheet.superseded,
+            vec![("TITLE".to_string(), "Loveless".to_string())]
+        );
+        assert_eq!(warnings[0].line, 3);
+
+        let options = ParseOptions {
+            strict: true,
+            ..Default::default()
+        };
+        assert!(CueSheet::parse_with_options(source, &options).is_err());
+    }
+
+    #[test]
+    fn implicit_file() {
+        let source = "TITLE \"Loveless\"\nTRACK 01 AUDIO\nINDEX 01 00:00:00";
+        assert!(CueSheet::parse(source).unwrap().files.is_empty());
+
+        let mut options = ParseOptions {
+            implicit_file: Some("Loveless.flac".to_string()),
+            ..Default::default()
+        };
+        let sheet = CueSheet::parse_with_options(source, &options).unwrap();
+        assert_eq!(sheet.files[0].name, "Loveless.flac");
+        assert_eq!(sheet.files[0].format, FileFormat::Wave);
+        assert_eq!(sheet.files[0].tracks.len(), 1);
+
+        options.strict = true;
+        assert!(CueSheet::parse_with_options(source, &options).is_err());
+    }
+
+    #[test]
+    fn parse_options_match_tracklist() {
+        let source =
+            "TITLE \" Loveless \"\nFILE a.wav WAVE\nTRACK 01 AUDIO\nTITLE \"Only Shallow \"\n\
+                      INDEX 01 00:00:00\nPOSTGAP 00:02:00\nTRACK 02 AUDIO\nINDEX 01 04:17:52";
+        let mut options = ParseOptions::new().duration_mode(DurationMode::WithPostgap);
+        options.metadata_cleanup.trim = true;
+
+        let sheet = CueSheet::parse_with_options(source, &options).unwrap();
+        let tracklist = Tracklist::parse_with_options(source, &options).unwrap();
+        assert_eq!(sheet.title, Some("Loveless".to_string()));
+        assert_eq!(sheet.title, tracklist.title);
+        let track = &sheet.files[0].tracks[0];
+        assert_eq!(track.title, Some("Only Shallow".to_string()));
+        assert_eq!(track.duration, Some(Time::new(4, 19, 52)));
+        assert_eq!(track.duration, tracklist.files[0].tracks[0].duration);
+    }
+}
@@ -0,0 +1,309 @@
+// cue_sheet
+// Copyright (C) 2017  Leonardo Schwarz <mail@leoschwarz.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Planning the splitting of audio files into tracks.
+//!
+//! Cue sheets describe positions in frames of 1/75 second. Depending on the sample rate of a
+//! file a frame boundary might not fall on a sample boundary, in which case the cut has to be
+//! rounded.
+
+use parser::frames_per_second;
+use std::fmt;
+use tracklist::{Track, TrackFile, Tracklist};
+
+/// How positions between two samples are rounded.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum Rounding {
+    /// Round to the preceding sample.
+    #[default]
+    Down,
+
+    /// Round to the nearest sample, halfway positions are rounded up.
+    Nearest,
+
+    /// Round to the following sample.
+    Up,
+}
+
+impl Rounding {
+    /// Divide `numerator` by `denominator`, rounding the result.
+    pub(crate) fn apply(self, numerator: i64, denominator: i64) -> i64 {
+        match self {
+            Rounding::Down => numerator.div_euclid(denominator),
+            Rounding::Nearest => (2 * numerator + denominator).div_euclid(2 * denominator),
+            Rounding::Up => -(-numerator).div_euclid(denominator),
+        }
+    }
+}
+
+/// One track to be cut out of a file.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Split {
+    /// Name of the file containing the track.
+    pub file: String,
+
+    /// Number of the track.
+    pub track: u32,
+
+    /// First sample of the track (per channel, relative to the start of the file).
+    pub start: i64,
+
+    /// Sample after the last sample of the track, `None` if the track extends to the end of
+    /// the file.
+    pub end: Option<i64>,
+}
+
+/// A warning about a cut which can't be made exactly.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum SplitWarning {
+    /// A boundary of the track doesn't fall on a sample boundary and had to be rounded.
+    NotSampleAligned {
+        /// Number of the track.
+        track: u32,
+        /// Position of the boundary in frames.
+        frames: i64,
+        /// Sample the boundary was rounded to.
+        sample: i64,
+    },
+}
+
+impl fmt::Display for SplitWarning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SplitWarning::NotSampleAligned {
+                track,
+                frames,
+                sample,
+            } => write!(
+                f,
+                "Boundary of track {:02} at frame {} was rounded to sample {}",
+                track, frames, sample
+            ),
+        }
+    }
+}
+
+/// The cuts required to split the files of a tracklist into tracks.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SplitPlan {
+    /// The tracks in the order they appear in the tracklist.
+    pub splits: Vec<Split>,
+
+    /// Warnings about boundaries which had to be rounded.
+    pub warnings: Vec<SplitWarning>,
+}
+
+impl SplitPlan {
+    /// Plan the splitting of `tracklist`, whose files have the specified `sample_rate`.
+    ///
+    /// Tracks start at their `INDEX 01`, so gaps are appended to the preceding track. The last
+    /// track of every file extends to the end of the file unless its duration is known.
+    ///
+    /// ```
+    /// use cue_sheet::split::{Rounding, SplitPlan};
+    /// use cue_sheet::tracklist::Tracklist;
+    ///
+    /// let tracklist = Tracklist::parse(
+    ///     "FILE a.wav WAVE\nTRACK 01 AUDIO\nINDEX 01 00:00:00\nTRACK 02 AUDIO\nINDEX 01 00:01:01",
+    /// )
+    /// .unwrap();
+    ///
+    /// let plan = SplitPlan::new(&tracklist, 44100, Rounding::Down);
+    /// assert_eq!(plan.splits[1].start, 44100 + 588);
+    /// assert!(plan.warnings.is_empty());
+    ///
+    /// let plan = SplitPlan::new(&tracklist, 32000, Rounding::Down);
+    /// assert_eq!(plan.splits[0].end, Some(32426));
+    /// // Both the end of track 1 and the start of track 2 were rounded.
+    /// assert_eq!(plan.warnings.len(), 2);
+    /// ```
+    pub fn new(tracklist: &Tracklist, sample_rate: u32, rounding: Rounding) -> SplitPlan {
+        let fps = frames_per_second(tracklist.frame_rate);
+        let mut warnings = Vec::new();
+        let mut to_sample = |track: u32, frames: i64| {
+            let exact = frames * i64::from(sample_rate);
+            let sample = rounding.apply(exact, i64::from(fps));
+            if exact % i64::from(fps) != 0 {
+                warnings.push(SplitWarning::NotSampleAligned {
+                    track,
+                    frames,
+                    sample,
+                });
+            }
+            sample
+        };
+
+        let mut splits = Vec::new();
+        for file in &tracklist.files {
+            for (track, start, end) in boundaries(file, fps) {
+                splits.push(Split {
+                    file: file.name.clone(),
+                    track: track.number,
+                    start: to_sample(track.number, start),
+                    end: end.map(|end| to_sample(track.number, end)),
+                });
+            }
+        }
+
+        SplitPlan { splits, warnings }
+    }
+}
+
+/// Returns the tracks of `file` which have an index, with their start and end in frames (at
+/// `fps` frames per second).
+///
+/// Tracks start at their `INDEX 01` and end at the start of the next track, the last track
+/// ends after its duration if known.
+fn boundaries(file: &TrackFile, fps: u8) -> Vec<(&Track, i64, Option<i64>)> {
+    let starts: Vec<Option<i64>> = file
+        .tracks
+        .iter()
+        .map(|t| t.start().map(|s| s.total_frames_at(fps)))
+        .collect();
+
+    let mut boundaries = Vec::new();
+    for (i, track) in file.tracks.iter().enumerate() {
+        let start = match starts[i] {
+            Some(start) => start,
+            None => continue,
+        };
+        let end = match starts[i + 1..].iter().flatten().next() {
+            Some(&end) => Some(end),
+            None => track
+                .duration
+                .as_ref()
+                .map(|d| start + d.total_frames_at(fps)),
+        };
+        boundaries.push((track, start, end));
+    }
+    boundaries
+}
+
+/// The samples of a track for gapless playback, see `gapless_ranges`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct GaplessRange {
+    /// Index of the file containing the track in `Tracklist::files`.
+    pub file: usize,
+
+    /// Number of the track.
+    pub track: u32,
+
+    /// First sample of the track (per channel, relative to the start of the file).
+    pub start_sample: i64,
+
+    /// Sample after the last sample of the track, `None` if the track extends to the end of
+    /// the file.
+    pub end_sample: Option<i64>,
+
+    /// Length of the pregap (`INDEX 00` to `INDEX 01`) preceding the track in samples, which
+    /// is part of the previous range. Zero if the track has no pregap.
+    pub pregap_samples: i64,
+}
+
+/// Returns the sample ranges of all tracks of `tracklist`, whose files have the specified
+/// `sample_rate`.
+///
+/// The ranges of a file are contiguous like the splits of `SplitPlan::new`, so playing them
+/// one after another is gapless. Positions between two samples are rounded down.
+///
+/// ```
+/// use cue_sheet::split::gapless_ranges;
+/// use cue_sheet::tracklist::Tracklist;
+///
+/// let tracklist = Tracklist::parse(
+///     "FILE a.wav WAVE\nTRACK 01 AUDIO\nINDEX 01 00:00:00\n\
+///      TRACK 02 AUDIO\nINDEX 00 00:03:00\nINDEX 01 00:05:00",
+/// )
+/// .unwrap();
+///
+/// let ranges = gapless_ranges(&tracklist, 44100);
+/// assert_eq!(ranges[0].end_sample, Some(5 * 44100));
+/// assert_eq!(ranges[1].start_sample, 5 * 44100);
+/// assert_eq!(ranges[1].pregap_samples, 2 * 44100);
+/// assert_eq!(ranges[1].end_sample, None);
+/// ```
+pub fn gapless_ranges(tracklist: &Tracklist, sample_rate: u32) -> Vec<GaplessRange> {
+    let fps = frames_per_second(tracklist.frame_rate);
+    let to_sample =
+        |frames: i64| Rounding::Down.apply(frames * i64::from(sample_rate), i64::from(fps));
+
+    let mut ranges = Vec::new();
+    for (i, file) in tracklist.files.iter().enumerate() {
+        for (track, start, end) in boundaries(file, fps) {
+            let pregap = track
+                .pregap_start()
+                .map_or(0, |p| start - p.total_frames_at(fps));
+            ranges.push(GaplessRange {
+                file: i,
+                track: track.number,
+                start_sample: to_sample(start),
+                end_sample: end.map(to_sample),
+                pregap_samples: to_sample(pregap.max(0)),
+            });
+        }
+    }
+    ranges
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rounding() {
+        assert_eq!(Rounding::Down.apply(7, 2), 3);
+        assert_eq!(Rounding::Nearest.apply(7, 2), 4);
+        assert_eq!(Rounding::Nearest.apply(5, 3), 2);
+        assert_eq!(Rounding::Nearest.apply(4, 3), 1);
+        assert_eq!(Rounding::Up.apply(7, 2), 4);
+        assert_eq!(Rounding::Up.apply(6, 2), 3);
+    }
+
+    #[test]
+    fn split_plan() {
+        let src = r#"FILE "a.wav" WAVE
+                       TRACK 01 AUDIO
+                         INDEX 01 00:00:00
+                       TRACK 02 AUDIO
+                         INDEX 00 00:02:00
+                         INDEX 01 00:04:01
+                     FILE "b.wav" WAVE
+                       TRACK 03 AUDIO
+                         INDEX 01 00:00:00"#;
+        let tracklist = Tracklist::parse(src).unwrap();
+
+        let plan = SplitPlan::new(&tracklist, 8000, Rounding::Up);
+        assert_eq!(plan.splits.len(), 3);
+        assert_eq!(plan.splits[0].end, Some(32107));
+        assert_eq!(plan.splits[1].start, 32107);
+        assert_eq!(plan.splits[1].end, None);
+        assert_eq!(plan.splits[2].file, "b.wav");
+        assert_eq!(plan.splits[2].start, 0);
+        assert_eq!(plan.warnings.len(), 2);
+        assert_eq!(
+            plan.warnings[0],
+            SplitWarning::NotSampleAligned {
+                track: 1,
+                frames: 301,
+                sample: 32107,
+            }
+        );
+
+        let plan = SplitPlan::new(&tracklist, 48000, Rounding::Nearest);
+        assert_eq!(plan.splits[1].start, 301 * 640);
+        assert!(plan.warnings.is_empty());
+    }
+}
@@ -0,0 +1,181 @@
+// cue_sheet
+// Copyright (C) 2017  Leonardo Schwarz <mail@leoschwarz.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Human-readable summaries of tracklists.
+//!
+//! Every track is printed on one line like `01 Only Shallow - My Bloody Valentine 04:17`, which
+//! is also the format understood by the MusicBrainz tracklist parser.
+
+use export::{performer, title};
+use std::fmt;
+use tracklist::{Track, Tracklist};
+
+/// Layout of a tracklist summary.
+///
+/// ```
+/// use cue_sheet::summary::SummaryFormat;
+/// use cue_sheet::tracklist::Tracklist;
+///
+/// let tracklist = Tracklist::parse(
+///     "PERFORMER \"My Bloody Valentine\"\nFILE a.wav WAVE\n\
+///      TRACK 01 AUDIO\nTITLE \"Only Shallow\"\nINDEX 01 00:00:00\n\
+///      TRACK 02 AUDIO\nTITLE Loomer\nINDEX 01 04:17:52",
+/// )
+/// .unwrap();
+///
+/// assert_eq!(
+///     tracklist.to_string(),
+///     "01 Only Shallow - My Bloody Valentine 04:17\n02 Loomer - My Bloody Valentine ??:??\n"
+/// );
+///
+/// let format = SummaryFormat {
+///     title_width: Some(14),
+///     performer: false,
+///     ..Default::default()
+/// };
+/// assert_eq!(
+///     format.display(&tracklist).to_string(),
+///     "01 Only Shallow   04:17\n02 Loomer         ??:??\n"
+/// );
+/// ```
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SummaryFormat {
+    /// Pad titles to this many characters, to align the following columns.
+    pub title_width: Option<usize>,
+
+    /// Include the performer (falling back to the performer of the tracklist).
+    pub performer: bool,
+
+    /// Pad performers to this many characters, to align the following columns.
+    pub performer_width: Option<usize>,
+
+    /// Include the duration as `mm:ss`, or `??:??` if it is unknown.
+    pub duration: bool,
+}
+
+impl Default for SummaryFormat {
+    fn default() -> Self {
+        SummaryFormat {
+            title_width: None,
+            performer: true,
+            performer_width: None,
+            duration: true,
+        }
+    }
+}
+
+impl SummaryFormat {
+    /// Returns a value displaying `tracklist` in this format.
+    pub fn display<'a>(&'a self, tracklist: &'a Tracklist) -> Summary<'a> {
+        Summary {
+            format: self,
+            tracklist,
+        }
+    }
+
+    fn write_track(
+        &self,
+        f: &mut fmt::Formatter,
+        track: &Track,
+        performer: Option<&str>,
+    ) -> fmt::Result {
+        write!(f, "{:02} ", track.number)?;
+        write!(f, "{:<1$}", title(track), self.title_width.unwrap_or(0))?;
+        if self.performer {
+            if let Some(performer) = performer {
+                write!(f, " - ")?;
+                write!(f, "{:<1$}", performer, self.performer_width.unwrap_or(0))?;
+            }
+        }
+        if self.duration {
+            match track.duration {
+                Some(ref duration) => write!(f, " {}", duration.to_string_2())?,
+                None => write!(f, " ??:??")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A tracklist displayed in a `SummaryFormat`, see `SummaryFormat::display`.
+#[derive(Clone, Debug)]
+pub struct Summary<'a> {
+    format: &'a SummaryFormat,
+    tracklist: &'a Tracklist,
+}
+
+impl<'a> fmt::Display for Summary<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for file in &self.tracklist.files {
+            for track in &file.tracks {
+                let performer = performer(self.tracklist, track);
+                self.format.write_track(f, track, performer)?;
+                writeln!(f)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Formats the tracklist with the default `SummaryFormat`, one track per line.
+impl fmt::Display for Tracklist {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        SummaryFormat::default().display(self).fmt(f)
+    }
+}
+
+/// Formats the track like a line of `SummaryFormat`, e.g. `01 Only Shallow 04:17`.
+impl fmt::Display for Track {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        SummaryFormat::default().write_track(f, self, self.performer.as_deref())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parser::{FileFormat, Time};
+
+    #[test]
+    fn summary() {
+        let mut tracklist = Tracklist::single_file(
+            "a.wav",
+            FileFormat::Wave,
+            vec![
+                ("A".to_string(), Time::new(0, 0, 0)),
+                ("Long title".to_string(), Time::new(4, 17, 52)),
+            ],
+        );
+        tracklist.files[0].tracks[1].performer = Some("X".to_string());
+
+        assert_eq!(tracklist.files[0].tracks[0].to_string(), "01 A 04:17");
+        assert_eq!(
+            tracklist.files[0].tracks[1].to_string(),
+            "02 Long title - X ??:??"
+        );
+
+        let format = SummaryFormat {
+            title_width: Some(6),
+            performer_width: Some(3),
+            duration: false,
+            ..Default::default()
+        };
+        assert_eq!(
+            format.display(&tracklist).to_string(),
+            "01 A     \n02 Long title - X  \n"
+        );
+    }
+}
@@ -0,0 +1,265 @@
+// cue_sheet
+// Copyright (C) 2017  Leonardo Schwarz <mail@leoschwarz.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Mapping of tracks to the tags commonly used in audio files.
+//!
+//! Tools splitting a disc image into one file per track have to tag every file, this module
+//! provides the values in a form which can be passed to ID3 or Vorbis comment libraries.
+//!
+//! Besides the tags derived from the cue sheet, CUETools stores the tags of the ripped files as
+//! track level `REM` comments (e.g. `REM COMPOSER "Kevin Shields"`), see `rem_tags`.
+
+use parser::Token;
+use tracklist::{Track, Tracklist};
+
+/// A tag written to the file of one track.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash, Ord, PartialOrd)]
+pub enum TagKey {
+    /// Title of the track.
+    Title,
+
+    /// Performer of the track, falling back to the performer of the disc.
+    Artist,
+
+    /// Title of the disc.
+    Album,
+
+    /// Number of the track.
+    TrackNumber,
+
+    /// Release date, from the `REM DATE` comment.
+    Date,
+
+    /// Genre, from the `REM GENRE` comment.
+    Genre,
+
+    /// Number of tracks of the disc.
+    TotalTracks,
+
+    /// Number of the disc in a set, from the `REM DISCNUMBER` comment.
+    DiscNumber,
+}
+
+impl TagKey {
+    /// Returns the name of the Vorbis comment field, e.g. `TRACKNUMBER`.
+    pub fn vorbis_name(&self) -> &'static str {
+        match *self {
+            TagKey::Title => "TITLE",
+            TagKey::Artist => "ARTIST",
+            TagKey::Album => "ALBUM",
+            TagKey::TrackNumber => "TRACKNUMBER",
+            TagKey::Date => "DATE",
+            TagKey::Genre => "GENRE",
+            TagKey::TotalTracks => "TOTALTRACKS",
+            TagKey::DiscNumber => "DISCNUMBER",
+        }
+    }
+
+    /// Returns the ID of the ID3v2.4 frame, e.g. `TRCK`.
+    ///
+    /// ID3 has no frame for the number of tracks, it is written as `TRCK` in the form
+    /// `number/total` instead, so `TotalTracks` returns `None`.
+    pub fn id3_frame(&self) -> Option<&'static str> {
+        match *self {
+            TagKey::Title => Some("TIT2"),
+            TagKey::Artist => Some("TPE1"),
+            TagKey::Album => Some("TALB"),
+            TagKey::TrackNumber => Some("TRCK"),
+            TagKey::Date => Some("TDRC"),
+            TagKey::Genre => Some("TCON"),
+            TagKey::TotalTracks => None,
+            TagKey::DiscNumber => Some("TPOS"),
+        }
+    }
+}
+
+/// Returns the tags of `track`, which has to be one of the tracks of `tracklist`.
+///
+/// Tags without a value are left out, the order of the result follows `TagKey`.
+///
+/// ```
+/// use cue_sheet::tags::{track_tags, TagKey};
+/// use cue_sheet::tracklist::Tracklist;
+///
+/// let tracklist = Tracklist::parse(
+///     "REM DATE 1991\nPERFORMER \"My Bloody Valentine\"\nTITLE Loveless\nFILE a.wav WAVE\n\
+///      TRACK 01 AUDIO\nTITLE \"Only Shallow\"\nINDEX 01 00:00:00",
+/// )
+/// .unwrap();
+///
+/// let tags = track_tags(&tracklist, &tracklist.files[0].tracks[0]);
+/// assert_eq!(tags[0], (TagKey::Title, "Only Shallow".to_string()));
+/// assert_eq!(tags[1], (TagKey::Artist, "My Bloody Valentine".to_string()));
+/// assert_eq!(tags[3], (TagKey::TrackNumber, "1".to_string()));
+/// assert_eq!(tags.len(), 6);
+/// ```
+pub fn track_tags(tracklist: &Tracklist, track: &Track) -> Vec<(TagKey, String)> {
+    let values = vec![
+        (TagKey::Title, track.title.clone()),
+        (
+            TagKey::Artist,
+            track
+                .performer
+                .clone()
+                .or_else(|| tracklist.performer.clone()),
+        ),
+        (TagKey::Album, tracklist.title.clone()),
+        (TagKey::TrackNumber, Some(track.number.to_string())),
+        (TagKey::Date, tracklist.date()),
+        (TagKey::Genre, tracklist.rem_value("GENRE")),
+        (
+            TagKey::TotalTracks,
+            tracklist.last_track_number().map(|n| n.to_string()),
+        ),
+        (TagKey::DiscNumber, tracklist.rem_value("DISCNUMBER")),
+    ];
+
+    values
+        .into_iter()
+        .filter_map(|(key, value)| value.map(|value| (key, value)))
+        .collect()
+}
+
+/// Returns the tags of `track` as Vorbis comment fields, see `track_tags`.
+pub fn vorbis_comments(tracklist: &Tracklist, track: &Track) -> Vec<(&'static str, String)> {
+    track_tags(tracklist, track)
+        .into_iter()
+        .map(|(key, value)| (key.vorbis_name(), value))
+        .collect()
+}
+
+/// Returns the tags of `track` as ID3v2.4 text frames, see `track_tags`.
+///
+/// The track number is written as `number/total`, where the total is the highest track number
+/// (see `Tracklist::last_track_number`).
+pub fn id3_frames(tracklist: &Tracklist, track: &Track) -> Vec<(&'static str, String)> {
+    let total = tracklist.last_track_number().unwrap_or(0);
+    track_tags(tracklist, track)
+        .into_iter()
+        .filter_map(|(key, value)| match key {
+            TagKey::TrackNumber => Some(("TRCK", format!("{}/{}", value, total))),
+            _ => key.id3_frame().map(|frame| (frame, value)),
+        })
+        .collect()
+}
+
+/// Returns the track level `REM` comments of `track` as tags, with upper case keys like Vorbis
+/// comment field names, in the order they appear.
+///
+/// Comments without a key are skipped.
+///
+/// ```
+/// use cue_sheet::tags::rem_tags;
+/// use cue_sheet::tracklist::Tracklist;
+///
+/// let tracklist = Tracklist::parse(
+///     "FILE a.flac WAVE\nTRACK 01 AUDIO\nREM COMPOSER \"Kevin Shields\"\n\
+///      REM replaygain_track_gain -7.89 dB\nINDEX 01 00:00:00",
+/// )
+/// .unwrap();
+/// assert_eq!(
+///     rem_tags(&tracklist.files[0].tracks[0]),
+///     vec![
+///         ("COMPOSER".to_string(), "Kevin Shields".to_string()),
+///         ("REPLAYGAIN_TRACK_GAIN".to_string(), "-7.89 dB".to_string()),
+///     ]
+/// );
+/// ```
+pub fn rem_tags(track: &Track) -> Vec<(String, String)> {
+    track
+        .rem
+        .iter()
+        .filter(|(key, _)| !key.is_empty())
+        .map(|(key, value)| (key.to_uppercase(), value.text()))
+        .collect()
+}
+
+/// Set the tag `key` of `track` to `value`, stored as a track level `REM` comment.
+///
+/// An existing comment with the same key (compared case-insensitively) is replaced in place,
+/// otherwise a new comment is appended, so the order of the comments is kept when writing the
+/// cue sheet again.
+pub fn set_rem_tag(track: &mut Track, key: &str, value: &str) {
+    let value = Token::String(value.to_string());
+    match track
+        .rem
+        .iter_mut()
+        .find(|(k, _)| k.eq_ignore_ascii_case(key))
+    {
+        Some(rem) => rem.1 = value,
+        None => track.rem.push((key.to_uppercase(), value)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats() {
+        let tracklist = Tracklist::parse(
+            "REM GENRE Shoegaze\nREM DISCNUMBER 1\nTITLE Loveless\nFILE a.wav WAVE\n\
+             TRACK 01 AUDIO\nINDEX 01 00:00:00\nTRACK 02 AUDIO\nPERFORMER MBV\nINDEX 01 04:17:52",
+        )
+        .unwrap();
+        let track = &tracklist.files[0].tracks[1];
+
+        assert_eq!(
+            vorbis_comments(&tracklist, track),
+            vec![
+                ("ARTIST", "MBV".to_string()),
+                ("ALBUM", "Loveless".to_string()),
+                ("TRACKNUMBER", "2".to_string()),
+                ("GENRE", "Shoegaze".to_string()),
+                ("TOTALTRACKS", "2".to_string()),
+                ("DISCNUMBER", "1".to_string()),
+            ]
+        );
+        assert_eq!(
+            id3_frames(&tracklist, track),
+            vec![
+                ("TPE1", "MBV".to_string()),
+                ("TALB", "Loveless".to_string()),
+                ("TRCK", "2/2".to_string()),
+                ("TCON", "Shoegaze".to_string()),
+                ("TPOS", "1".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn rem_tags_round_trip() {
+        let source =
+            "FILE \"a.flac\" WAVE\n  TRACK 01 AUDIO\n    REM COMPOSER \"Kevin Shields\"\n    \
+                      REM LABEL Creation\n    INDEX 01 00:00:00\n";
+        let mut tracklist = Tracklist::parse(source).unwrap();
+        assert_eq!(tracklist.to_cue_string(), source);
+
+        let track = &mut tracklist.files[0].tracks[0];
+        set_rem_tag(track, "composer", "Kevin Shields, Bilinda Butcher");
+        set_rem_tag(track, "Mood", "loud");
+        let written = tracklist.to_cue_string();
+        assert!(written.contains(
+            "REM COMPOSER \"Kevin Shields, Bilinda Butcher\"\n    REM LABEL Creation\n    REM MOOD loud\n"
+        ));
+
+        let reparsed = Tracklist::parse(&written).unwrap();
+        assert_eq!(
+            rem_tags(&reparsed.files[0].tracks[0]),
+            rem_tags(&tracklist.files[0].tracks[0])
+        );
+    }
+}
@@ -18,11 +18,38 @@
 
 // TODO don't swallow errors in parsing but use Result and Option where appropriate.
 
-use errors::Error;
-use parser::{self, Command, FileFormat, Time, TrackType};
+use cleanup::{MetadataCleanup, RawMetadata, RawTrackMetadata};
+use date::Date;
+use encoding;
+use errors::{Error, ErrorKind, ResultExt};
+use filesystem::FileSystem;
+use generator::Generator;
+use msf::absolute_address;
+use musicbrainz::track_offsets;
+use parser::{
+    frames_per_second, Command, CommandLine, FileFormat, ParseOptions, Time, Token, TrackFlag,
+    TrackType, FPS,
+};
+use sha1_smol::Sha1;
+use sheet::CueSheet;
+use std::collections::HashMap;
+use std::fs;
+use std::iter::FlatMap;
+use std::ops::{self, Range};
+use std::path::{Path, PathBuf};
+use std::slice;
+use std::str::FromStr;
+use validation::MAX_TRACKS;
+use warning::{Warning, WarningKind};
+use writer::{self, LineEnding, WriteOptions};
+
+/// Name of the file inserted by `Tracklist::parse_embedded` if a cue sheet has no `FILE` command.
+pub const EMBEDDED_FILE_NAME: &str = "CDImage.wav";
 
 /// A tracklist provides a more useful representation of the information of a cue sheet.
-#[derive(Clone, Debug)]
+///
+/// The default is an empty tracklist without any files.
+#[derive(Clone, Debug, Default)]
 pub struct Tracklist {
     /// Files described by the tracklist.
     pub files: Vec<TrackFile>,
@@ -32,56 +59,1240 @@ pub struct Tracklist {
 
     /// Title of the tracklist.
     pub title: Option<String>,
+
+    /// Disc level `REM` comments as (key, value) pairs in the order they appeared.
+    pub rem: Vec<(String, Token)>,
+
+    /// How the durations of the tracks were computed.
+    pub duration_mode: DurationMode,
+
+    /// Nonstandard number of frames per second of the times, see `ParseOptions::frame_rate`.
+    ///
+    /// Durations, `Track::album_start`, `Tracklist::fill_durations`,
+    /// `Tracklist::apply_sample_offset` and the sample positions computed by the `split` module
+    /// take the rate into account. Methods of `Time` and `Track` without a rate parameter
+    /// assume 75 frames per second.
+    pub frame_rate: Option<u8>,
+
+    /// The metadata strings as they were read, if a cleanup changed them, see
+    /// `Tracklist::cleaned`.
+    pub raw_metadata: Option<RawMetadata>,
+}
+
+/// Flattens a `CueSheet`, dropping the disc level information a `Tracklist` doesn't represent.
+impl From<CueSheet> for Tracklist {
+    fn from(sheet: CueSheet) -> Tracklist {
+        Tracklist {
+            files: sheet.files,
+            performer: sheet.performer,
+            title: sheet.title,
+            rem: sheet.rem,
+            duration_mode: DurationMode::Content,
+            frame_rate: sheet.frame_rate,
+            raw_metadata: None,
+        }
+    }
+}
+
+/// How the durations of tracks are derived from their index positions.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum DurationMode {
+    /// A track lasts from its last index until the first index of the next track.
+    ///
+    /// This only covers the audio contained in the file, but not a `POSTGAP` which has to be
+    /// generated as silence.
+    #[default]
+    Content,
+
+    /// Like `Content`, but the `POSTGAP` of a track is added to its duration.
+    WithPostgap,
+}
+
+/// A position in the audio of a tracklist: a time within one of its files.
+///
+/// Positions are ordered by the index of the file in `Tracklist::files` and then by the time,
+/// which is the order in which they are played.
+#[derive(Clone, Debug, Eq, PartialEq, Hash, Ord, PartialOrd)]
+pub struct DiscPosition {
+    /// Index of the file in `Tracklist::files`.
+    pub file: usize,
+
+    /// Time relative to the start of the file.
+    pub time: Time,
+}
+
+/// Where the duration of a track comes from, see `Track::duration_provenance`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum DurationProvenance {
+    /// Computed from the index positions of the track and the one following it.
+    Derived,
+
+    /// Computed from the length of the file, as determined by a `DurationProvider`.
+    Probed,
+
+    /// Provided as it is, e.g. to `Tracklist::from_listing`.
+    Declared,
+}
+
+/// How the audio of a disc is distributed over files, named after the options of Exact Audio
+/// Copy, see `Tracklist::file_layout`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum FileLayout {
+    /// There is at most one file.
+    SingleFile,
+
+    /// Pregaps are placed at the end of the file of the preceding track ("noncompliant"), see
+    /// `Track::pregap_in_previous_file`.
+    GapsAppended,
+
+    /// Pregaps are placed at the start of the file of their track, marked by `INDEX 00`.
+    GapsPrepended,
+
+    /// There are multiple files, but no pregaps.
+    GapsLeftOut,
 }
 
 impl Tracklist {
     /// Parse a cue sheet (content provided as `source`) into a `Tracklist`.
     pub fn parse(source: &str) -> Result<Tracklist, Error> {
-        let mut commands = parser::parse_cue(source)?;
+        Tracklist::parse_with_options(source, ParseOptions::global_default())
+    }
+
+    /// Read and parse the cue sheet at `path`, detecting its encoding.
+    ///
+    /// Errors carry the path of the file as context.
+    ///
+    /// ```no_run
+    /// use cue_sheet::tracklist::Tracklist;
+    /// use std::path::Path;
+    ///
+    /// let tracklist = Tracklist::from_path(Path::new("Loveless.cue")).unwrap();
+    /// ```
+    pub fn from_path(path: &Path) -> Result<Tracklist, Error> {
+        let bytes = fs::read(path).chain_err(|| format!("Failed to read {}", path.display()))?;
+        Tracklist::parse_bytes(path, &bytes)
+    }
 
-        let mut performer = None;
-        let mut title = None;
+    /// Read and parse the cue sheet at `path`, detecting its encoding.
+    ///
+    /// Files compressed with gzip (e.g. `.cue.gz`) are recognized by their content and
+    /// decompressed first. Just like with `Tracklist::from_path` errors carry the path of the file.
+    #[cfg(feature = "flate2")]
+    pub fn parse_path<P: AsRef<Path>>(path: P) -> Result<Tracklist, Error> {
+        use flate2::read::GzDecoder;
+        use std::io::Read;
 
-        while commands.len() > 0 {
-            match commands[0].clone() {
-                Command::Performer(p) => {
-                    performer = Some(p);
-                    commands.remove(0);
+        let path = path.as_ref();
+        let mut bytes =
+            fs::read(path).chain_err(|| format!("Failed to read {}", path.display()))?;
+        if bytes.starts_with(&[0x1f, 0x8b]) {
+            let mut decompressed = Vec::new();
+            GzDecoder::new(&bytes[..])
+                .read_to_end(&mut decompressed)
+                .chain_err(|| format!("Failed to decompress {}", path.display()))?;
+            bytes = decompressed;
+        }
+        Tracklist::parse_bytes(path, &bytes)
+    }
+
+    /// Decodes and parses `bytes` which were read from `path`.
+    fn parse_bytes(path: &Path, bytes: &[u8]) -> Result<Tracklist, Error> {
+        Tracklist::parse(&encoding::decode(bytes))
+            .chain_err(|| format!("Failed to parse {}", path.display()))
+    }
+
+    /// Parse a cue sheet (content provided as `source`) into a `Tracklist` using the provided
+    /// `options`.
+    pub fn parse_with_options(source: &str, options: &ParseOptions) -> Result<Tracklist, Error> {
+        Ok(Tracklist::parse_with_warnings(source, options)?.0)
+    }
+
+    /// Parse a cue sheet (content provided as `source`) into a `Tracklist` using the provided
+    /// `options`, also returning warnings about the problems which were recovered from.
+    pub fn parse_with_warnings(
+        source: &str,
+        options: &ParseOptions,
+    ) -> Result<(Tracklist, Vec<Warning>), Error> {
+        let mut warnings = Vec::new();
+        let sheet = CueSheet::parse_collecting(source, options, &mut warnings)?;
+
+        let mut tracklist = Tracklist::from(sheet);
+        tracklist.duration_mode = options.duration_mode;
+        if tracklist.duration_mode == DurationMode::WithPostgap {
+            for file in &mut tracklist.files {
+                for track in &mut file.tracks {
+                    track.add_postgap()?;
                 }
-                Command::Title(t) => {
-                    title = Some(t);
-                    commands.remove(0);
+            }
+        }
+        if options.metadata_cleanup != MetadataCleanup::default() {
+            tracklist = tracklist.cleaned(&options.metadata_cleanup);
+        }
+        Ok((tracklist, warnings))
+    }
+
+    /// Parse a cue sheet embedded in the tags of an audio file (e.g. the `CUESHEET` tag of
+    /// FLAC, APE or WavPack files) into a `Tracklist`.
+    ///
+    /// Embedded cue sheets describe the audio file containing them, so they often omit the
+    /// `FILE` command. In that case a file named `EMBEDDED_FILE_NAME` is inserted before the
+    /// first track, see `ParseOptions::implicit_file`. A `FILE` command referencing the
+    /// containing file itself is kept as it is.
+    ///
+    /// ```
+    /// use cue_sheet::tracklist::{Tracklist, EMBEDDED_FILE_NAME};
+    ///
+    /// let tracklist = Tracklist::parse_embedded(
+    ///     "TITLE \"Loveless\"\nTRACK 01 AUDIO\nINDEX 01 00:00:00",
+    /// )
+    /// .unwrap();
+    /// assert_eq!(tracklist.files[0].name, EMBEDDED_FILE_NAME);
+    /// assert_eq!(tracklist.files[0].tracks.len(), 1);
+    /// ```
+    pub fn parse_embedded(tag_value: &str) -> Result<Tracklist, Error> {
+        let options = ParseOptions {
+            implicit_file: Some(EMBEDDED_FILE_NAME.to_string()),
+            ..Default::default()
+        };
+        Tracklist::parse_with_options(tag_value, &options)
+    }
+
+    /// Create a tracklist for a single file from a listing of track titles and durations.
+    ///
+    /// The `INDEX 01` positions of the tracks are computed from the durations, which makes this
+    /// useful for creating cue sheets of continuous mixes. An error is returned if a position
+    /// is out of the range of `Time`.
+    ///
+    /// ```
+    /// use cue_sheet::parser::{FileFormat, Time};
+    /// use cue_sheet::tracklist::Tracklist;
+    ///
+    /// let listing = vec![
+    ///     ("Only Shallow".to_string(), Time::new(4, 17, 52)),
+    ///     ("Loomer".to_string(), Time::new(2, 38, 23)),
+    /// ];
+    /// let tracklist = Tracklist::from_listing(
+    ///     "My Bloody Valentine",
+    ///     "Loveless",
+    ///     "Loveless.wav",
+    ///     FileFormat::Wave,
+    ///     &listing,
+    /// )
+    /// .unwrap();
+    /// assert_eq!(tracklist.files[0].tracks[1].index, vec![(1, Time::new(4, 17, 52))]);
+    /// ```
+    pub fn from_listing(
+        performer: &str,
+        title: &str,
+        file_name: &str,
+        format: FileFormat,
+        listing: &[(String, Time)],
+    ) -> Result<Tracklist, Error> {
+        let mut tracks = Vec::with_capacity(listing.len());
+        let mut start = Some(0);
+
+        for (i, (track_title, duration)) in listing.iter().enumerate() {
+            let time = start
+                .and_then(Time::checked_from_frames)
+                .ok_or_else(|| format!("Track {} starts out of range.", i + 1))?;
+            tracks.push(Track {
+                title: Some(track_title.clone()),
+                track_type: TrackType::Audio,
+                duration: Some(duration.clone()),
+                duration_provenance: Some(DurationProvenance::Declared),
+                index: vec![(1, time.clone())],
+                number: i as u32 + 1,
+                performer: Some(performer.to_string()),
+                album_start: Some(time),
+                flags: Vec::new(),
+                isrc: None,
+                pregap: None,
+                postgap: None,
+                songwriter: None,
+                rem: Vec::new(),
+                pregap_in_previous_file: None,
+            });
+            start = start.and_then(|s| s.checked_add(duration.total_frames()));
+        }
+
+        Ok(Tracklist {
+            files: vec![TrackFile {
+                tracks,
+                name: file_name.to_string(),
+                format,
+                arguments: Vec::new(),
+            }],
+            performer: Some(performer.to_string()),
+            title: Some(title.to_string()),
+            rem: Vec::new(),
+            duration_mode: DurationMode::Content,
+            frame_rate: None,
+            raw_metadata: None,
+        })
+    }
+
+    /// Create a tracklist for a single file from a list of track titles and start times.
+    ///
+    /// Tracks are numbered in the order of their start times, the duration of every but the
+    /// last track follows from the start of the next one. This is the common case of a single
+    /// recording with a list of chapters.
+    ///
+    /// ```
+    /// use cue_sheet::parser::{FileFormat, Time};
+    /// use cue_sheet::tracklist::Tracklist;
+    ///
+    /// let tracklist = Tracklist::single_file(
+    ///     "Loveless.flac",
+    ///     FileFormat::Wave,
+    ///     vec![
+    ///         ("Only Shallow".to_string(), Time::new(0, 0, 0)),
+    ///         ("Loomer".to_string(), Time::new(4, 17, 52)),
+    ///     ],
+    /// );
+    /// let tracks = &tracklist.files[0].tracks;
+    /// assert_eq!(tracks[1].number, 2);
+    /// assert_eq!(tracks[0].duration, Some(Time::new(4, 17, 52)));
+    /// ```
+    pub fn single_file<P: AsRef<Path>>(
+        path: P,
+        format: FileFormat,
+        mut tracks: Vec<(String, Time)>,
+    ) -> Tracklist {
+        tracks.sort_by(|a, b| a.1.cmp(&b.1));
+
+        let mut result: Vec<Track> = Vec::with_capacity(tracks.len());
+        for (i, (title, start)) in tracks.into_iter().enumerate() {
+            if let Some(previous) = result.last_mut() {
+                previous.duration = Some(start.clone() - previous.index[0].1.clone());
+                previous.duration_provenance = Some(DurationProvenance::Derived);
+            }
+            result.push(Track {
+                title: Some(title),
+                track_type: TrackType::Audio,
+                duration: None,
+                duration_provenance: None,
+                index: vec![(1, start.clone())],
+                number: i as u32 + 1,
+                performer: None,
+                album_start: Some(start),
+                flags: Vec::new(),
+                isrc: None,
+                pregap: None,
+                postgap: None,
+                songwriter: None,
+                rem: Vec::new(),
+                pregap_in_previous_file: None,
+            });
+        }
+
+        Tracklist {
+            files: vec![TrackFile {
+                tracks: result,
+                name: path.as_ref().to_string_lossy().into_owned(),
+                format,
+                arguments: Vec::new(),
+            }],
+            performer: None,
+            title: None,
+            rem: Vec::new(),
+            duration_mode: DurationMode::Content,
+            frame_rate: None,
+            raw_metadata: None,
+        }
+    }
+
+    /// Returns the total length of the tracklist, if the end of its last track is known.
+    pub(crate) fn total_length(&self) -> Option<Time> {
+        let track = self.files.last()?.tracks.last()?;
+        let (_, last) = track.index.last()?;
+        let end =
+            track.file_offset()? + last.total_frames() + track.duration.as_ref()?.total_frames();
+        Time::checked_from_frames(end)
+    }
+
+    /// Append the tracks of `other` to this tracklist, e.g. to join the two discs of an album.
+    ///
+    /// The tracks of `other` are renumbered to follow the last track of this tracklist. Disc
+    /// level information (performer, title, `REM` comments) of `other` is dropped.
+    ///
+    /// If `gap` is `None` the files of `other` are appended as they are. Otherwise both
+    /// tracklists have to describe a single file, and the result describes the concatenation of
+    /// both files with `gap` of silence in between. This requires the length of this
+    /// tracklist to be known, see `Tracklist::fill_durations`. Both tracklists are left
+    /// unchanged, a new one is returned.
+    pub fn concat(&self, other: &Tracklist, gap: Option<Time>) -> Result<Tracklist, Error> {
+        let mut result = self.clone();
+        let mut other = other.clone();
+        let overflow = || Error::from("The concatenated tracklist is too long.");
+        let length = self.total_length();
+        let last_number = self
+            .files
+            .iter()
+            .flat_map(|f| f.tracks.last())
+            .last()
+            .map_or(0, |t| t.number);
+        for file in &mut other.files {
+            for track in &mut file.tracks {
+                track.number += last_number;
+            }
+        }
+
+        if let Some(gap) = gap {
+            if result.files.len() != 1 || other.files.len() != 1 {
+                return Err(
+                    "Concatenating into a single file requires single file tracklists.".into(),
+                );
+            }
+            let length = length.ok_or(
+                "Length of the first tracklist is unknown, see `Tracklist::fill_durations`.",
+            )?;
+            let offset = length.checked_add(&gap).ok_or_else(overflow)?;
+            let shift = |time: &Time| time.checked_add(&offset).ok_or_else(overflow);
+
+            for mut track in other.files.remove(0).tracks {
+                for index in &mut track.index {
+                    index.1 = shift(&index.1)?;
                 }
-                Command::Rem(_, _) => {
-                    commands.remove(0);
+                track.album_start = track.album_start.as_ref().map(shift).transpose()?;
+                result.files[0].tracks.push(track);
+            }
+        } else {
+            for mut file in other.files {
+                for track in &mut file.tracks {
+                    track.album_start = match (length.as_ref(), track.album_start.as_ref()) {
+                        (Some(length), Some(start)) => {
+                            Some(length.checked_add(start).ok_or_else(overflow)?)
+                        }
+                        _ => None,
+                    };
                 }
-                _ => {
-                    break;
+                result.files.push(file);
+            }
+        }
+
+        Ok(result)
+    }
+
+    /// Renumber the tracks consecutively starting from 1, in the order they appear.
+    ///
+    /// This fixes cue sheets with out-of-order or duplicate track numbers, e.g. some programs
+    /// start every `FILE` with `TRACK 01`. The changed track numbers are returned, the
+    /// tracklist is left unchanged if it has more than `validation::MAX_TRACKS` tracks.
+    ///
+    /// ```
+    /// use cue_sheet::tracklist::{Renumbering, Tracklist};
+    ///
+    /// let mut tracklist = Tracklist::parse(
+    ///     "FILE a.wav WAVE\nTRACK 01 AUDIO\nINDEX 01 00:00:00\n\
+    ///      FILE b.wav WAVE\nTRACK 01 AUDIO\nINDEX 01 00:00:00",
+    /// )
+    /// .unwrap();
+    ///
+    /// let changes = tracklist.normalize().unwrap();
+    /// assert_eq!(changes, vec![Renumbering { file: 1, old: 1, new: 2 }]);
+    /// assert_eq!(tracklist.files[1].tracks[0].number, 2);
+    /// ```
+    pub fn normalize(&mut self) -> Result<Vec<Renumbering>, Error> {
+        let count = self.len();
+        if count > MAX_TRACKS {
+            return Err(ErrorKind::TooManyTracks(count).into());
+        }
+
+        let mut changes = Vec::new();
+        let mut number = 0;
+        for (i, file) in self.files.iter_mut().enumerate() {
+            for track in &mut file.tracks {
+                number += 1;
+                if track.number != number {
+                    changes.push(Renumbering {
+                        file: i,
+                        old: track.number,
+                        new: number,
+                    });
+                    track.number = number;
                 }
             }
         }
+        Ok(changes)
+    }
+
+    /// Build a tracklist from a list of commands, e.g. obtained with `parser::parse_cue` and
+    /// edited afterwards.
+    ///
+    /// Commands are not checked for their placement like in strict parsing, and errors cite the
+    /// position of the offending command in `commands` (starting at 1) as its line.
+    ///
+    /// ```
+    /// use cue_sheet::parser::{parse_cue, Command};
+    /// use cue_sheet::tracklist::Tracklist;
+    ///
+    /// let mut commands = parse_cue("FILE \"Loveless.wav\" WAVE\nTRACK 01 AUDIO\nINDEX 01 00:00:00").unwrap();
+    /// commands.insert(0, Command::Title("Loveless".to_string()));
+    ///
+    /// let tracklist = Tracklist::from_commands(commands).unwrap();
+    /// assert_eq!(tracklist.title, Some("Loveless".to_string()));
+    /// assert_eq!(tracklist.to_commands().len(), 4);
+    /// ```
+    pub fn from_commands(commands: Vec<Command>) -> Result<Tracklist, Error> {
+        Ok(Tracklist::from(CueSheet::from_commands(commands)?))
+    }
 
-        let mut files = Vec::new();
-        while commands.len() > 0 {
-            if let Ok(file) = TrackFile::consume(&mut commands) {
-                files.push(file);
-            } else {
-                break;
+    /// Returns the commands describing this tracklist.
+    pub fn to_commands(&self) -> Vec<Command> {
+        let mut commands = Vec::new();
+
+        for (key, value) in &self.rem {
+            commands.push(Command::Rem(key.clone(), value.clone()));
+        }
+        if let Some(ref performer) = self.performer {
+            commands.push(Command::Performer(performer.clone()));
+        }
+        if let Some(ref title) = self.title {
+            commands.push(Command::Title(title.clone()));
+        }
+
+        push_file_commands(&self.files, &mut commands);
+
+        commands
+    }
+
+    /// Format the tracklist as a cue sheet.
+    pub fn to_cue_string(&self) -> String {
+        let mut s = String::new();
+        writer::write_commands(&self.to_commands(), &mut s, WriteOptions::global_default())
+            .unwrap();
+        s
+    }
+
+    /// Format the tracklist as text using the provided `options`.
+    pub fn to_cue_string_with_options(&self, options: &WriteOptions) -> String {
+        let mut s = String::new();
+        writer::write_commands(&self.to_commands(), &mut s, options).unwrap();
+        s
+    }
+
+    /// Format the tracklist in a normalized form, suitable for hashing or comparing cue sheets.
+    ///
+    /// The output only depends on the content of the tracklist: keywords are upper case, flags
+    /// and indexes sorted, commands indented by level and lines end with CRLF.
+    ///
+    /// ```
+    /// use cue_sheet::tracklist::Tracklist;
+    ///
+    /// let a = Tracklist::parse("file a.wav wave\ntrack 01 audio\nflags pre dcp\nindex 01 00:00:00")
+    ///     .unwrap();
+    /// let b = Tracklist::parse(
+    ///     "FILE \"a.wav\" WAVE\r\n  TRACK 01 AUDIO\r\n    FLAGS DCP PRE\r\n    INDEX 01 00:00:00",
+    /// )
+    /// .unwrap();
+    /// assert_eq!(a.to_canonical_string(), b.to_canonical_string());
+    /// assert!(a.to_canonical_string().ends_with("INDEX 01 00:00:00\r\n"));
+    /// ```
+    pub fn to_canonical_string(&self) -> String {
+        let mut tracklist = self.clone();
+        for file in &mut tracklist.files {
+            for track in &mut file.tracks {
+                track.index.sort_by_key(|&(n, _)| n);
             }
         }
+        let options = WriteOptions {
+            line_ending: LineEnding::CrLf,
+            ..Default::default()
+        };
+        tracklist.to_cue_string_with_options(&options)
+    }
 
-        Ok(Tracklist {
-            files: files,
-            performer: performer,
-            title: title,
+    /// Returns a copy of the tracklist with `cleanup` applied to all metadata strings.
+    ///
+    /// If this changes any string, the values from before the cleanup are kept in
+    /// `Tracklist::raw_metadata`. Once set, they are kept by later cleanups.
+    pub fn cleaned(&self, cleanup: &MetadataCleanup) -> Tracklist {
+        let mut tracklist = self.clone();
+        cleanup.apply_opt(&mut tracklist.performer);
+        cleanup.apply_opt(&mut tracklist.title);
+        for file in &mut tracklist.files {
+            for track in &mut file.tracks {
+                cleanup.apply_opt(&mut track.title);
+                cleanup.apply_opt(&mut track.performer);
+                cleanup.apply_opt(&mut track.songwriter);
+            }
+        }
+
+        let raw = self.metadata();
+        if tracklist.raw_metadata.is_none() && tracklist.metadata() != raw {
+            tracklist.raw_metadata = Some(raw);
+        }
+        tracklist
+    }
+
+    /// Returns the current metadata strings.
+    fn metadata(&self) -> RawMetadata {
+        RawMetadata {
+            title: self.title.clone(),
+            performer: self.performer.clone(),
+            tracks: self
+                .files
+                .iter()
+                .flat_map(|file| &file.tracks)
+                .map(|track| RawTrackMetadata {
+                    number: track.number,
+                    title: track.title.clone(),
+                    performer: track.performer.clone(),
+                    songwriter: track.songwriter.clone(),
+                })
+                .collect(),
+        }
+    }
+
+    /// Guess which program generated the cue sheet, see `Generator::detect`.
+    pub fn detect_generator(&self) -> Generator {
+        Generator::detect(&self.rem)
+    }
+
+    /// Returns the value of the first `REM` comment with the specified key.
+    ///
+    /// Keys are compared case-insensitively.
+    pub fn rem_value(&self, key: &str) -> Option<String> {
+        self.rem
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(key))
+            .map(|(_, value)| value.text())
+    }
+
+    /// Returns the release date as stated in the `REM DATE` comment.
+    pub fn date(&self) -> Option<String> {
+        self.rem_value("DATE")
+    }
+
+    /// Returns the release date stated in the `REM DATE` comment, if it is in a known format.
+    ///
+    /// See `Date` for the supported formats, the raw value is available through
+    /// `Tracklist::date`.
+    pub fn date_parsed(&self) -> Option<Date> {
+        self.date().and_then(|date| date.parse().ok())
+    }
+
+    /// Detect how the audio is distributed over files.
+    ///
+    /// Cue sheets with the pregaps appended to the previous files are read like all other
+    /// cue sheets: the tracks are assigned to the file containing their `INDEX 01`.
+    ///
+    /// ```
+    /// use cue_sheet::tracklist::{FileLayout, Tracklist};
+    ///
+    /// let tracklist = Tracklist::parse(
+    ///     "FILE 01.wav WAVE\nTRACK 01 AUDIO\nINDEX 01 00:00:00\nTRACK 02 AUDIO\nINDEX 00 04:15:00\n\
+    ///      FILE 02.wav WAVE\nINDEX 01 00:00:00",
+    /// )
+    /// .unwrap();
+    /// assert_eq!(tracklist.file_layout(), FileLayout::GapsAppended);
+    ///
+    /// let track = &tracklist.files[1].tracks[0];
+    /// assert_eq!(track.number, 2);
+    /// assert_eq!(track.pregap_in_previous_file, Some("04:15:00".parse().unwrap()));
+    /// ```
+    pub fn file_layout(&self) -> FileLayout {
+        if self.files.len() <= 1 {
+            FileLayout::SingleFile
+        } else if self
+            .tracks()
+            .any(|(_, t)| t.pregap_in_previous_file.is_some())
+        {
+            FileLayout::GapsAppended
+        } else if self.tracks().any(|(_, t)| t.index.iter().any(|i| i.0 == 0)) {
+            FileLayout::GapsPrepended
+        } else {
+            FileLayout::GapsLeftOut
+        }
+    }
+
+    /// Returns a hash of the positions of the tracks, ignoring all metadata.
+    ///
+    /// Two tracklists with the same fingerprint describe the same disc layout, e.g. the same
+    /// pressing ripped by different programs. The positions are taken relative to the first
+    /// track, and like for `musicbrainz::toc_string` they all have to be known: for a rip with
+    /// one file per track this is an error unless `Tracklist::fill_durations` was called.
+    ///
+    /// If the end of the last track is known, the position of the lead-out is included too, so
+    /// discs differing only in the length of the last track are told apart. A tracklist whose
+    /// lead-out is unknown therefore has a different fingerprint than the same tracklist after
+    /// `Tracklist::fill_durations`.
+    ///
+    /// ```
+    /// use cue_sheet::tracklist::Tracklist;
+    ///
+    /// let a = Tracklist::parse(
+    ///     "TITLE Loveless\nFILE a.wav WAVE\nTRACK 01 AUDIO\nINDEX 01 00:00:00\n\
+    ///      TRACK 02 AUDIO\nINDEX 01 04:17:52",
+    /// )
+    /// .unwrap();
+    /// let b = Tracklist::parse(
+    ///     "FILE b.flac WAVE\nTRACK 01 AUDIO\nTITLE x\nINDEX 01 00:00:00\n\
+    ///      TRACK 02 AUDIO\nINDEX 01 04:17:52",
+    /// )
+    /// .unwrap();
+    /// assert_eq!(a.toc_fingerprint().unwrap(), b.toc_fingerprint().unwrap());
+    /// assert_eq!(a.toc_fingerprint().unwrap().len(), 40);
+    /// ```
+    pub fn toc_fingerprint(&self) -> Result<String, Error> {
+        let offsets = track_offsets(self)?;
+        let first = offsets.first().cloned().unwrap_or(0);
+        let mut toc: Vec<String> = offsets
+            .iter()
+            .map(|offset| (offset - first).to_string())
+            .collect();
+        if let Some(length) = self.total_length() {
+            toc.push(format!("L{}", absolute_address(&length) - first));
+        }
+        Ok(Sha1::from(toc.join(" ")).digest().to_string())
+    }
+
+    /// Returns the hidden track one audio (HTOA) if there is any.
+    ///
+    /// This is audio located in the pregap of the first track, i.e. the first track has an
+    /// `INDEX 00` at `00:00:00` and its `INDEX 01` only later.
+    pub fn hidden_track(&self) -> Option<HiddenTrack> {
+        let file = self.files.first()?;
+        let track = file.tracks.first()?;
+
+        let pregap = track.index.iter().find(|&&(n, _)| n == 0)?;
+        let start = track.index.iter().find(|&&(n, _)| n == 1)?;
+        if pregap.1.total_frames() != 0 || start.1.total_frames() == 0 {
+            return None;
+        }
+
+        Some(HiddenTrack {
+            file: file.name.clone(),
+            duration: start.1.clone(),
+        })
+    }
+
+    /// Returns a tracklist containing only the track with the specified `number`, e.g. to
+    /// accompany the file of a track split from a disc image.
+    ///
+    /// The times of the track are rebased so that it starts (`INDEX 01`) at `00:00:00`, indexes
+    /// before the start are dropped. Disc level metadata is copied, the file keeps its name
+    /// and format and usually has to be renamed afterwards.
+    ///
+    /// ```
+    /// use cue_sheet::parser::Time;
+    /// use cue_sheet::tracklist::Tracklist;
+    ///
+    /// let tracklist = Tracklist::parse(
+    ///     "TITLE Loveless\nFILE a.wav WAVE\nTRACK 01 AUDIO\nINDEX 01 00:00:00\n\
+    ///      TRACK 02 AUDIO\nINDEX 00 04:15:40\nINDEX 01 04:17:52\nINDEX 02 05:00:00",
+    /// )
+    /// .unwrap();
+    ///
+    /// let track = tracklist.extract_track(2).unwrap();
+    /// assert_eq!(track.title, Some("Loveless".to_string()));
+    /// assert_eq!(
+    ///     track.files[0].tracks[0].index,
+    ///     vec![(1, Time::new(0, 0, 0)), (2, Time::new(0, 42, 23))]
+    /// );
+    /// assert!(tracklist.extract_track(3).is_none());
+    /// ```
+    pub fn extract_track(&self, number: u32) -> Option<Tracklist> {
+        let file = self
+            .files
+            .iter()
+            .find(|f| f.tracks.iter().any(|t| t.number == number))?;
+        let mut track = file.tracks.iter().find(|t| t.number == number)?.clone();
+
+        let start = track.start().map_or(0, |s| s.total_frames());
+        track.index = track
+            .index
+            .iter()
+            .filter(|(_, time)| time.total_frames() >= start)
+            .map(|(n, time)| Some((*n, Time::checked_from_frames(time.total_frames() - start)?)))
+            .collect::<Option<_>>()?;
+        track.album_start = Some(Time::new(0, 0, 0));
+
+        Some(Tracklist {
+            files: vec![TrackFile {
+                tracks: vec![track],
+                name: file.name.clone(),
+                format: file.format.clone(),
+                arguments: file.arguments.clone(),
+            }],
+            performer: self.performer.clone(),
+            title: self.title.clone(),
+            rem: self.rem.clone(),
+            duration_mode: self.duration_mode,
+            frame_rate: self.frame_rate,
+            raw_metadata: None,
         })
     }
+
+    /// Returns an iterator over all tracks of all files, together with the index of the file
+    /// containing them in `Tracklist::files`.
+    ///
+    /// ```
+    /// use cue_sheet::tracklist::Tracklist;
+    ///
+    /// let tracklist = Tracklist::parse(
+    ///     "FILE a.wav WAVE\nTRACK 01 AUDIO\nINDEX 01 00:00:00\n\
+    ///      FILE b.wav WAVE\nTRACK 02 AUDIO\nINDEX 01 00:00:00",
+    /// )
+    /// .unwrap();
+    ///
+    /// let numbers: Vec<(usize, u32)> = tracklist.tracks().map(|(f, t)| (f, t.number)).collect();
+    /// assert_eq!(numbers, vec![(0, 1), (1, 2)]);
+    /// assert_eq!(tracklist.len(), 2);
+    /// assert_eq!(tracklist.get_track(2).unwrap().number, 2);
+    /// ```
+    pub fn tracks(&self) -> impl Iterator<Item = (usize, &Track)> + '_ {
+        self.files
+            .iter()
+            .enumerate()
+            .flat_map(|(i, file)| file.tracks.iter().map(move |track| (i, track)))
+    }
+
+    /// Returns the total number of tracks of all files.
+    pub fn len(&self) -> usize {
+        self.files.iter().map(|f| f.tracks.len()).sum()
+    }
+
+    /// Returns the lowest track number, which isn't necessarily 1, e.g. for the second session
+    /// of a disc or if a leading data track was left out.
+    ///
+    /// ```
+    /// use cue_sheet::tracklist::Tracklist;
+    ///
+    /// let tracklist = Tracklist::parse(
+    ///     "FILE a.wav WAVE\nTRACK 02 AUDIO\nINDEX 01 00:00:00\nTRACK 03 AUDIO\nINDEX 01 04:17:52",
+    /// )
+    /// .unwrap();
+    /// assert_eq!(tracklist.first_track_number(), Some(2));
+    /// assert_eq!(tracklist.last_track_number(), Some(3));
+    /// assert_eq!(Tracklist::default().first_track_number(), None);
+    /// ```
+    pub fn first_track_number(&self) -> Option<u32> {
+        self.tracks().map(|(_, track)| track.number).min()
+    }
+
+    /// Returns the highest track number, see `Tracklist::first_track_number`.
+    pub fn last_track_number(&self) -> Option<u32> {
+        self.tracks().map(|(_, track)| track.number).max()
+    }
+
+    /// True if the tracklist doesn't contain any tracks.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the track with the number `number`.
+    pub fn get_track(&self, number: u32) -> Option<&Track> {
+        self.tracks()
+            .map(|(_, track)| track)
+            .find(|track| track.number == number)
+    }
+
+    /// Returns the position of the start of the track with the number `number`, see
+    /// `Track::start`.
+    pub fn track_position(&self, number: u32) -> Option<DiscPosition> {
+        self.tracks()
+            .find(|&(_, track)| track.number == number)
+            .and_then(|(file, track)| {
+                Some(DiscPosition {
+                    file,
+                    time: track.start()?,
+                })
+            })
+    }
+
+    /// Returns the position of the index `index` of the track with the number `track`.
+    ///
+    /// A pregap in the previous file (see `Track::pregap_in_previous_file`) isn't found as
+    /// `INDEX 00`, since it belongs to another file.
+    ///
+    /// ```
+    /// use cue_sheet::tracklist::Tracklist;
+    ///
+    /// let tracklist = Tracklist::parse(
+    ///     "FILE a.wav WAVE\nTRACK 01 AUDIO\nINDEX 01 00:00:00\n\
+    ///      FILE b.wav WAVE\nTRACK 02 AUDIO\nINDEX 00 00:00:00\nINDEX 01 00:02:00",
+    /// )
+    /// .unwrap();
+    ///
+    /// let pregap = tracklist.index_position(2, 0).unwrap();
+    /// assert!(tracklist.track_position(1).unwrap() < pregap);
+    /// assert!(pregap < tracklist.track_position(2).unwrap());
+    /// ```
+    pub fn index_position(&self, track: u32, index: u32) -> Option<DiscPosition> {
+        self.tracks()
+            .find(|&(_, t)| t.number == track)
+            .and_then(|(file, t)| {
+                Some(DiscPosition {
+                    file,
+                    time: t.index_time(index)?,
+                })
+            })
+    }
+
+    /// Returns the track a playback position falls into.
+    ///
+    /// The position is relative to the start of the first file, so for tracklists with multiple
+    /// files `Tracklist::fill_durations` has to be called before tracks of later files can be
+    /// found. The pregap (`INDEX 00`) of a track is considered part of the track.
+    pub fn track_at(&self, time: Time) -> Option<(&TrackFile, &Track)> {
+        self.track_at_frame(time.total_frames())
+    }
+
+    /// Returns the track a playback position (in frames/sectors) falls into.
+    ///
+    /// See `Tracklist::track_at`.
+    pub fn track_at_frame(&self, frame: i64) -> Option<(&TrackFile, &Track)> {
+        let mut found = None;
+
+        for file in &self.files {
+            for track in &file.tracks {
+                let offset = match track.file_offset() {
+                    Some(offset) => offset,
+                    None => continue,
+                };
+                let begin = match track.index.first() {
+                    Some((_, time)) => offset + time.total_frames(),
+                    None => continue,
+                };
+
+                if begin > frame {
+                    return found;
+                }
+                found = Some((file, track));
+            }
+        }
+
+        // Positions after the end of the last track don't belong to any track.
+        if let Some((_, track)) = found {
+            if let (Some(offset), Some((_, last)), Some(duration)) = (
+                track.file_offset(),
+                track.index.last(),
+                track.duration.as_ref(),
+            ) {
+                if frame >= offset + last.total_frames() + duration.total_frames() {
+                    return None;
+                }
+            }
+        }
+
+        found
+    }
+
+    /// Use `provider` to determine the lengths of all files in the tracklist.
+    ///
+    /// This fills in the duration of the last track of every file and makes it possible to
+    /// compute `Track::album_start` for tracks which are not located in the first file.
+    pub fn fill_durations<P: DurationProvider + ?Sized>(
+        &mut self,
+        provider: &P,
+    ) -> Result<(), Error> {
+        let fps = frames_per_second(self.frame_rate);
+        let mut lengths = Vec::with_capacity(self.files.len());
+        for i in 0..self.files.len() {
+            // The provider measures real time, which has to be converted to the frame rate.
+            let length = provider.file_length(&self.files[i])?;
+            let length =
+                Time::checked_from_frames_at(length.total_frames() * i64::from(fps) / FPS, fps)
+                    .ok_or("The length of a file overflowed.")?;
+            // A pregap appended to the end of this file doesn't belong to its last track.
+            let end = self
+                .files
+                .get(i + 1)
+                .and_then(|next| next.tracks.first())
+                .and_then(|track| track.pregap_in_previous_file.clone())
+                .unwrap_or_else(|| length.clone());
+            if let Some(last_track) = self.files[i].tracks.last_mut() {
+                if let Some((_, time)) = last_track.index.last() {
+                    let frames = end.total_frames_at(fps) - time.total_frames_at(fps);
+                    last_track.duration = Time::checked_from_frames_at(frames.max(0), fps);
+                    last_track.duration_provenance = Some(DurationProvenance::Probed);
+                    if self.duration_mode == DurationMode::WithPostgap {
+                        last_track.add_postgap()?;
+                    }
+                }
+            }
+            lengths.push(length);
+        }
+
+        update_album_starts(&mut self.files, &lengths, self.frame_rate)?;
+        Ok(())
+    }
+
+    /// Shift all indexes by `samples` audio samples (per channel) at `sample_rate`, to correct
+    /// a rip made without read offset correction.
+    ///
+    /// The offset is rounded to the nearest frame, halves away from zero. A positive offset
+    /// moves the indexes later. A track whose start moves over the start or the end of its file
+    /// is moved into the adjacent file, which needs the length of the file it crosses into or
+    /// out of, i.e. the duration of its last track (see `Tracklist::fill_durations`).
+    ///
+    /// The durations are computed again like when parsing, so the last track of a file ends at
+    /// the end of the file. A file left without tracks loses its length, the duration of a
+    /// track moved back into it stays unknown until `Tracklist::fill_durations` is called.
+    ///
+    /// An error is returned, leaving the tracklist unchanged, if `sample_rate` is zero, if an
+    /// index would be moved before the start or after the end of the audio, or if the indexes of
+    /// a track would end up in different files.
+    ///
+    /// ```
+    /// use cue_sheet::parser::Time;
+    /// use cue_sheet::tracklist::Tracklist;
+    ///
+    /// let mut tracklist = Tracklist::parse(
+    ///     "FILE a.wav WAVE\nTRACK 01 AUDIO\nINDEX 01 00:00:00\nTRACK 02 AUDIO\nINDEX 01 04:17:52",
+    /// )
+    /// .unwrap();
+    /// tracklist.apply_sample_offset(1176, 44100).unwrap();
+    /// assert_eq!(tracklist.files[0].tracks[1].index[0].1, Time::new(4, 17, 54));
+    /// ```
+    pub fn apply_sample_offset(&mut self, samples: i32, sample_rate: u32) -> Result<(), Error> {
+        if sample_rate == 0 {
+            return Err("The sample rate has to be positive.".into());
+        }
+        let fps = frames_per_second(self.frame_rate);
+        let rate = i64::from(sample_rate);
+        let scaled = 2 * i64::from(samples) * i64::from(fps);
+        let frames = (scaled + scaled.signum() * rate) / (2 * rate);
+
+        let with_postgap = self.duration_mode == DurationMode::WithPostgap;
+        let lengths: Vec<Option<i64>> = self
+            .files
+            .iter()
+            .map(|file| file_length(file, with_postgap, fps))
+            .collect();
+
+        let mut files: Vec<Vec<Track>> = vec![Vec::new(); self.files.len()];
+        for (i, file) in self.files.iter().enumerate() {
+            for track in &file.tracks {
+                let mut track = track.clone();
+                let start = match track.start() {
+                    Some(start) => start.total_frames_at(fps) + frames,
+                    None => {
+                        files[i].push(track);
+                        continue;
+                    }
+                };
+
+                // Follow the start of the track over file boundaries.
+                let (mut target, mut shift) = (i, frames);
+                let mut position = start;
+                while position < 0 && target > 0 {
+                    let length = lengths[target - 1].ok_or_else(|| {
+                        format!(
+                            "Track {:02} moves into a file of unknown length.",
+                            track.number
+                        )
+                    })?;
+                    target -= 1;
+                    position += length;
+                    shift += length;
+                }
+                while let (Some(length), true) = (lengths[target], target + 1 < files.len()) {
+                    if position < length {
+                        break;
+                    }
+                    target += 1;
+                    position -= length;
+                    shift -= length;
+                }
+
+                let number = track.number;
+                for &mut (_, ref mut time) in &mut track.index {
+                    let moved = time.total_frames_at(fps) + shift;
+                    if moved < 0 || lengths[target].is_some_and(|length| moved >= length) {
+                        return Err(format!(
+                            "Track {:02} would be moved out of the audio or split across files.",
+                            number
+                        )
+                        .into());
+                    }
+                    *time = Time::checked_from_frames_at(moved, fps)
+                        .ok_or_else(|| format!("Track {:02} overflowed.", number))?;
+                }
+                files[target].push(track);
+            }
+        }
+
+        for (tracks, length) in files.iter_mut().zip(&lengths) {
+            update_durations(tracks, *length, with_postgap, fps)?;
+        }
+        for (file, tracks) in self.files.iter_mut().zip(files) {
+            file.tracks = tracks;
+        }
+
+        let known: Vec<Time> = lengths
+            .iter()
+            .map_while(|length| Time::checked_from_frames_at((*length)?, fps))
+            .collect();
+        update_album_starts(&mut self.files, &known, self.frame_rate)?;
+        Ok(())
+    }
 }
 
-/// One file described by a tracklist.
+/// Sorts the `tracks` of a file by their start and computes their durations again, except for
+/// the last track whose end is unknown. Tracks without index stay behind the preceding track.
+fn sort_by_start(tracks: &mut Vec<Track>, fps: u8) -> Result<(), Error> {
+    let mut key = 0;
+    let mut keyed: Vec<(i64, Track)> = tracks
+        .drain(..)
+        .map(|track| {
+            if let Some(start) = track.start() {
+                key = start.total_frames_at(fps);
+            }
+            (key, track)
+        })
+        .collect();
+    keyed.sort_by_key(|&(key, _)| key);
+    tracks.extend(keyed.into_iter().map(|(_, track)| track));
+
+    update_durations(tracks, None, false, fps)
+}
+
+/// Computes the durations of `tracks` of a file from their indexes, like they are computed
+/// when parsing, and for the last track from the `length` of the file in frames (at `fps`
+/// frames per second). The durations are then `DurationProvenance::Derived`, or unknown.
+fn update_durations(
+    tracks: &mut [Track],
+    length: Option<i64>,
+    with_postgap: bool,
+    fps: u8,
+) -> Result<(), Error> {
+    for i in 0..tracks.len() {
+        let end = match tracks.get(i + 1) {
+            Some(next) => next
+                .index
+                .first()
+                .map(|(_, time)| time.total_frames_at(fps)),
+            None => length,
+        };
+        let track = &mut tracks[i];
+        if let Some((_, last)) = track.index.last() {
+            let frames = end.map(|end| (end - last.total_frames_at(fps)).max(0));
+            track.duration = frames.and_then(|frames| Time::checked_from_frames_at(frames, fps));
+            track.duration_provenance =
+                track.duration.as_ref().map(|_| DurationProvenance::Derived);
+            if with_postgap {
+                track.add_postgap()?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Length of `file` in frames (at `fps` frames per second), if the duration of its last track
+/// is known.
+///
+/// The postgap is not part of the file, so it is subtracted if it was included in the duration.
+fn file_length(file: &TrackFile, with_postgap: bool, fps: u8) -> Option<i64> {
+    let track = file.tracks.last()?;
+    let (_, last) = track.index.last()?;
+    let mut length = last.total_frames_at(fps) + track.duration.as_ref()?.total_frames_at(fps);
+    if let (true, Some(postgap)) = (with_postgap, track.postgap.as_ref()) {
+        length -= postgap.total_frames_at(fps);
+    }
+    Some(length)
+}
+
+/// Appends the commands describing `files` to `commands`.
+pub(crate) fn push_file_commands(files: &[TrackFile], commands: &mut Vec<Command>) {
+    for (i, file) in files.iter().enumerate() {
+        let continued = i > 0
+            && file
+                .tracks
+                .first()
+                .is_some_and(|t| t.pregap_in_previous_file.is_some());
+        let next = files.get(i + 1).and_then(|f| f.tracks.first());
+        file.push_commands(continued, next, commands);
+    }
+}
+
+/// Sets `Track::album_start` for all tracks whose position can be determined given the
+/// lengths of the first `lengths.len()` files, with times counting `frame_rate` frames per
+/// second (see `ParseOptions::frame_rate`).
+///
+/// An error is returned if a position doesn't fit into a `Time`.
+pub(crate) fn update_album_starts(
+    files: &mut [TrackFile],
+    lengths: &[Time],
+    frame_rate: Option<u8>,
+) -> Result<(), Error> {
+    let fps = frames_per_second(frame_rate);
+    let overflow = || Error::from("The position of a track overflowed.");
+    let mut offset = Some(0i64);
+    for (i, file) in files.iter_mut().enumerate() {
+        for track in &mut file.tracks {
+            track.album_start = match (offset, track.start()) {
+                (Some(offset), Some(start)) => {
+                    let frames = offset
+                        .checked_add(start.total_frames_at(fps))
+                        .ok_or_else(overflow)?;
+                    Some(Time::checked_from_frames_at(frames, fps).ok_or_else(overflow)?)
+                }
+                _ => None,
+            };
+        }
+
+        offset = match (offset, lengths.get(i)) {
+            (Some(offset), Some(length)) => Some(
+                offset
+                    .checked_add(length.total_frames_at(fps))
+                    .ok_or_else(overflow)?,
+            ),
+            _ => None,
+        };
+    }
+    Ok(())
+}
+
+/// Returns the last component of `name`, treating both `/` and `\\` as separators.
+pub(crate) fn normalize_file_name(name: &str) -> String {
+    name.rsplit(['/', '\\']).next().unwrap_or(name).to_string()
+}
+
+/// Audio hidden in the pregap of the first track, see `Tracklist::hidden_track`.
 #[derive(Clone, Debug, Eq, PartialEq)]
+pub struct HiddenTrack {
+    /// The file containing the hidden track, it always starts at `00:00:00`.
+    pub file: String,
+
+    /// Duration of the hidden track.
+    pub duration: Time,
+}
+
+/// Provides the lengths of the files referenced by a tracklist.
+///
+/// A cue sheet only describes where tracks start, so the total length of a file has to be
+/// determined by other means (e.g. by inspecting the audio file itself).
+pub trait DurationProvider {
+    /// Returns the total length of `file`.
+    fn file_length(&self, file: &TrackFile) -> Result<Time, Error>;
+}
+
+impl<F> DurationProvider for F
+where
+    F: Fn(&TrackFile) -> Result<Time, Error>,
+{
+    fn file_length(&self, file: &TrackFile) -> Result<Time, Error> {
+        self(file)
+    }
+}
+
+/// Lengths keyed by the file name as it appears in the cue sheet.
+impl DurationProvider for HashMap<String, Time> {
+    fn file_length(&self, file: &TrackFile) -> Result<Time, Error> {
+        self.get(&file.name)
+            .cloned()
+            .ok_or_else(|| format!("No length known for file {:?}", file.name).into())
+    }
+}
+
+/// One file described by a tracklist.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
 pub struct TrackFile {
     /// List of tracks contained in the file.
+    ///
+    /// Tracks read from a cue sheet are sorted by `Track::start`.
     pub tracks: Vec<Track>,
 
     /// The filename.
@@ -89,127 +1300,918 @@ pub struct TrackFile {
 
     /// The format of the file.
     pub format: FileFormat,
+
+    /// Nonstandard arguments following the file format, see `Command::File`.
+    pub arguments: Vec<Token>,
 }
 
 impl TrackFile {
-    fn consume(commands: &mut Vec<Command>) -> Result<Self, Error> {
-        if let Command::File(name, format) = commands.remove(0) {
+    /// Create a file named `name` of the given `format`, without any tracks.
+    pub fn new<S: Into<String>>(name: S, format: FileFormat) -> TrackFile {
+        TrackFile {
+            tracks: Vec::new(),
+            name: name.into(),
+            format,
+            arguments: Vec::new(),
+        }
+    }
+
+    /// Returns the file format inferred from the extension of the file name.
+    pub fn inferred_format(&self) -> Option<FileFormat> {
+        FileFormat::from_path(Path::new(&self.name))
+    }
+
+    /// Returns only the file name of the file, without any directories.
+    ///
+    /// Both `/` and `\\` are treated as separators, since cue sheets are often created on
+    /// Windows.
+    ///
+    /// ```
+    /// use cue_sheet::tracklist::Tracklist;
+    ///
+    /// let tracklist = Tracklist::parse("FILE \"C:\\Music\\Loveless.wav\" WAVE").unwrap();
+    /// assert_eq!(tracklist.files[0].file_name_normalized(), "Loveless.wav");
+    /// ```
+    pub fn file_name_normalized(&self) -> String {
+        normalize_file_name(&self.name)
+    }
+
+    /// Returns the path of the file, resolved relative to `base` (usually the directory
+    /// containing the cue sheet).
+    ///
+    /// Separators are normalized like in `TrackFile::file_name_normalized`. Absolute paths
+    /// (including Windows drive letters) are almost never valid on another machine, so only
+    /// their file name is used. `..` components are resolved against the path of the file, an
+    /// error is returned if the path would leave `base`.
+    ///
+    /// ```
+    /// use cue_sheet::tracklist::Tracklist;
+    /// use std::path::Path;
+    ///
+    /// let tracklist = Tracklist::parse(
+    ///     "FILE \"CD1\\01.wav\" WAVE\nFILE \"D:\\02.wav\" WAVE\nFILE \"CD1/../03.wav\" WAVE\n\
+    ///      FILE \"../04.wav\" WAVE",
+    /// )
+    /// .unwrap();
+    /// let base = Path::new("/music");
+    /// assert_eq!(tracklist.files[0].relative_to(base).unwrap(), base.join("CD1").join("01.wav"));
+    /// assert_eq!(tracklist.files[1].relative_to(base).unwrap(), base.join("02.wav"));
+    /// assert_eq!(tracklist.files[2].relative_to(base).unwrap(), base.join("03.wav"));
+    /// assert!(tracklist.files[3].relative_to(base).is_err());
+    /// ```
+    pub fn relative_to<P: AsRef<Path>>(&self, base: P) -> Result<PathBuf, Error> {
+        let name = self.name.replace('\\', "/");
+        let bytes = name.as_bytes();
+        let has_drive = bytes.len() >= 2 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':';
+
+        let mut path = base.as_ref().to_path_buf();
+        if name.starts_with('/') || has_drive {
+            path.push(normalize_file_name(&name));
+            return Ok(path);
+        }
+
+        let mut components = Vec::new();
+        for component in name.split('/').filter(|c| !c.is_empty() && *c != ".") {
+            if component != ".." {
+                components.push(component);
+            } else if components.pop().is_none() {
+                return Err(
+                    format!("File {:?} is outside of the base directory", self.name).into(),
+                );
+            }
+        }
+        path.extend(components);
+        Ok(path)
+    }
+
+    /// Returns the path of the file in `fs`, resolved relative to `base`.
+    ///
+    /// This tries `TrackFile::relative_to` first and then only the file name in `base`, since
+    /// files are often moved into the directory of the cue sheet. `None` is returned if neither
+    /// exists. Paths which would leave `base` are skipped, only their file name is tried.
+    ///
+    /// ```
+    /// use cue_sheet::filesystem::MemoryFileSystem;
+    /// use cue_sheet::tracklist::Tracklist;
+    /// use std::path::Path;
+    ///
+    /// let tracklist = Tracklist::parse("FILE \"CD1\\01.wav\" WAVE").unwrap();
+    /// let mut fs = MemoryFileSystem::new();
+    /// fs.insert(Path::new("rip").join("01.wav"), Vec::new());
+    /// assert_eq!(
+    ///     tracklist.files[0].resolve("rip", &fs),
+    ///     Some(Path::new("rip").join("01.wav"))
+    /// );
+    /// assert_eq!(tracklist.files[0].resolve("other", &fs), None);
+    /// ```
+    pub fn resolve<P: AsRef<Path>, F: FileSystem + ?Sized>(
+        &self,
+        base: P,
+        fs: &F,
+    ) -> Option<PathBuf> {
+        if let Ok(path) = self.relative_to(base.as_ref()) {
+            if fs.is_file(&path) {
+                return Some(path);
+            }
+        }
+        let path = base.as_ref().join(self.file_name_normalized());
+        if fs.is_file(&path) {
+            Some(path)
+        } else {
+            None
+        }
+    }
+
+    /// Returns the byte offset and length of every track within the file.
+    ///
+    /// This is intended for `BINARY` images of data or mixed-mode discs, where the tracks can
+    /// have different sector sizes. The length of every track is its `Track::byte_length`, the
+    /// first track starts at its first index, assuming that any sectors before it have the
+    /// sector size of the first track. Offsets are only known up to the first track of unknown
+    /// length, e.g. the last track unless `Tracklist::fill_durations` was called.
+    ///
+    /// ```
+    /// use cue_sheet::tracklist::Tracklist;
+    ///
+    /// let tracklist = Tracklist::parse(
+    ///     "FILE game.bin BINARY\nTRACK 01 MODE1/2048\nINDEX 01 00:00:00\n\
+    ///      TRACK 02 AUDIO\nINDEX 00 00:10:00\nINDEX 01 00:12:00",
+    /// )
+    /// .unwrap();
+    ///
+    /// let layout = tracklist.files[0].layout();
+    /// assert_eq!(layout[0].length, Some(750 * 2048));
+    /// assert_eq!(layout[1].offset, 750 * 2048);
+    /// assert_eq!(layout[1].length, None);
+    /// ```
+    pub fn layout(&self) -> Vec<TrackLayout> {
+        let mut layout = Vec::with_capacity(self.tracks.len());
+        let mut offset = match self.tracks.first() {
+            Some(track) => {
+                track.first_index_frames().max(0) as u64 * u64::from(track.sector_size())
+            }
+            None => 0,
+        };
+        for track in &self.tracks {
+            let length = track.byte_length();
+            layout.push(TrackLayout {
+                number: track.number,
+                offset,
+                length,
+            });
+            match length {
+                Some(length) => offset += length,
+                None => break,
+            }
+        }
+        layout
+    }
+
+    /// Returns the declared and the inferred file format if they differ.
+    ///
+    /// This is useful to find (and correct) cue sheets declaring the wrong format for a file.
+    pub fn format_mismatch(&self) -> Option<(FileFormat, FileFormat)> {
+        match self.inferred_format() {
+            Some(ref inferred) if *inferred != self.format => {
+                Some((self.format.clone(), inferred.clone()))
+            }
+            _ => None,
+        }
+    }
+
+    /// Appends the commands describing this file to `commands`.
+    ///
+    /// If the first track continues a track of the previous file, its `TRACK` command was
+    /// already written with that file. Likewise the `TRACK` command of `next`, the first track
+    /// of the next file, is written here if its pregap is at the end of this file.
+    fn push_commands(&self, continued: bool, next: Option<&Track>, commands: &mut Vec<Command>) {
+        commands.push(Command::File(
+            self.name.clone(),
+            self.format.clone(),
+            self.arguments.clone(),
+        ));
+        for (i, track) in self.tracks.iter().enumerate() {
+            if i > 0 || !continued {
+                track.push_header(commands);
+            }
+            for &(num, ref time) in &track.index {
+                commands.push(Command::Index(num, time.clone()));
+            }
+            if let Some(ref postgap) = track.postgap {
+                commands.push(Command::Postgap(postgap.clone()));
+            }
+        }
+        if let Some(track) = next {
+            if let Some(ref pregap) = track.pregap_in_previous_file {
+                track.push_header(commands);
+                commands.push(Command::Index(0, pregap.clone()));
+            }
+        }
+    }
+
+    /// Consumes a `FILE` command and all tracks following it.
+    ///
+    /// `INDEX` commands directly following the `FILE` continue the last track of `previous`,
+    /// which is moved into this file, see `FileLayout::GapsAppended`.
+    ///
+    /// A track starting before the previous one is an error in strict mode, otherwise it is
+    /// reported in `warnings` and the tracks are sorted by their start, with the durations
+    /// computed again for the new order.
+    pub(crate) fn consume(
+        commands: &mut Vec<CommandLine>,
+        previous: Option<&mut TrackFile>,
+        options: &ParseOptions,
+        warnings: &mut Vec<Warning>,
+    ) -> Result<Self, Error> {
+        let fps = frames_per_second(options.frame_rate);
+        if let Command::File(name, format, arguments) = commands[0].0.clone() {
+            commands.remove(0);
             let mut tracks: Vec<Track> = Vec::new();
             let mut last_time: Option<Time> = None;
 
-            while commands.len() > 0 {
-                if let Ok(track) = Track::consume(commands) {
-                    if track.index.len() > 0 {
-                        let time = track.index[track.index.len() - 1].clone();
-
-                        if let Some(start) = last_time {
-                            let stop = track.index[0].clone().1;
-                            let duration = stop - start;
+            if let Some(&(Command::Index(..), line)) = commands.first() {
+                let mut track = previous
+                    .filter(|p| {
+                        p.tracks
+                            .last()
+                            .is_some_and(|t| t.index.iter().all(|i| i.0 == 0))
+                    })
+                    .and_then(|p| p.tracks.pop())
+                    .ok_or_else(|| format!("INDEX on line {} doesn't belong to a track", line))?;
+                track.pregap_in_previous_file = track.index.first().map(|i| i.1.clone());
+                track.index.clear();
+                while let Some(&(Command::Index(num, ref time), _)) = commands.first() {
+                    track.index.push((num, time.clone()));
+                    commands.remove(0);
+                }
+                last_time = track.index.last().map(|i| i.1.clone());
+                tracks.push(track);
+            }
 
-                            let track_n = tracks.len();
-                            if let Some(last_track) = tracks.get_mut(track_n - 1) {
-                                (*last_track).duration = Some(duration);
-                            }
+            let mut out_of_order = false;
+            while let Some(&(Command::Track(..), line)) = commands.first() {
+                let track = Track::consume(commands)?;
+                if let Some(previous) = tracks.last().and_then(|t| t.start()) {
+                    if track.start().is_some_and(|start| start < previous) {
+                        if options.strict {
+                            return Err(ErrorKind::TracksOutOfOrder(track.number, line).into());
                         }
+                        warnings.push(Warning {
+                            line,
+                            kind: WarningKind::TracksOutOfOrder(track.number),
+                        });
+                        out_of_order = true;
+                    }
+                }
 
-                        last_time = Some(time.1);
-                    } else {
-                        last_time = None;
+                if !track.index.is_empty() {
+                    let time = track.index[track.index.len() - 1].clone();
+
+                    if let Some(start) = last_time {
+                        let stop = &track.index[0].1;
+                        let frames = stop.total_frames_at(fps) - start.total_frames_at(fps);
+                        let duration = Time::checked_from_frames_at(frames.max(0), fps)
+                            .ok_or("The duration of a track overflowed.")?;
+
+                        let track_n = tracks.len();
+                        if let Some(last_track) = tracks.get_mut(track_n - 1) {
+                            last_track.duration = Some(duration);
+                            last_track.duration_provenance = Some(DurationProvenance::Derived);
+                        }
                     }
 
-                    tracks.push(track);
+                    last_time = Some(time.1);
                 } else {
-                    break;
+                    last_time = None;
                 }
+
+                tracks.push(track);
+            }
+
+            if out_of_order {
+                sort_by_start(&mut tracks, fps)?;
             }
             Ok(TrackFile {
-                tracks: tracks,
-                name: name,
-                format: format,
+                tracks,
+                name,
+                format,
+                arguments,
             })
         } else {
-            Err("TrackFile::consume called but no Track command found.".into())
+            Err("TrackFile::consume called but no File command found.".into())
+        }
+    }
+}
+
+type FileTracks<'a> = fn(&'a TrackFile) -> &'a Vec<Track>;
+type FileTracksMut<'a> = fn(&'a mut TrackFile) -> &'a mut Vec<Track>;
+
+/// Iterates over the tracks of all files, like `Tracklist::tracks` without the file index.
+impl<'a> IntoIterator for &'a Tracklist {
+    type Item = &'a Track;
+    type IntoIter = FlatMap<slice::Iter<'a, TrackFile>, &'a Vec<Track>, FileTracks<'a>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.files.iter().flat_map(|f| &f.tracks)
+    }
+}
+
+impl<'a> IntoIterator for &'a mut Tracklist {
+    type Item = &'a mut Track;
+    type IntoIter = FlatMap<slice::IterMut<'a, TrackFile>, &'a mut Vec<Track>, FileTracksMut<'a>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.files.iter_mut().flat_map(|f| &mut f.tracks)
+    }
+}
+
+/// Returns the track at a position (starting at 0) counted over all files.
+///
+/// Use `Tracklist::get_track` to find a track by its number.
+///
+/// ```
+/// use cue_sheet::tracklist::Tracklist;
+///
+/// let mut tracklist = Tracklist::parse(
+///     "FILE a.wav WAVE\nTRACK 01 AUDIO\nINDEX 01 00:00:00\n\
+///      FILE b.wav WAVE\nTRACK 02 AUDIO\nINDEX 01 00:00:00",
+/// )
+/// .unwrap();
+///
+/// tracklist[1].title = Some("Loomer".to_string());
+/// assert_eq!(tracklist.files[1].tracks[0].title, Some("Loomer".to_string()));
+/// assert_eq!(tracklist[0].number, 1);
+/// ```
+///
+/// # Panics
+///
+/// If the position is out of range.
+impl ops::Index<usize> for Tracklist {
+    type Output = Track;
+
+    fn index(&self, position: usize) -> &Track {
+        self.into_iter()
+            .nth(position)
+            .expect("track position out of range")
+    }
+}
+
+impl ops::IndexMut<usize> for Tracklist {
+    fn index_mut(&mut self, position: usize) -> &mut Track {
+        self.into_iter()
+            .nth(position)
+            .expect("track position out of range")
+    }
+}
+
+/// Parses a cue sheet with the default `ParseOptions`, see `Tracklist::parse`.
+///
+/// ```
+/// use cue_sheet::tracklist::Tracklist;
+///
+/// let tracklist: Tracklist = "FILE a.wav WAVE\nTRACK 01 AUDIO\nINDEX 01 00:00:00"
+///     .parse()
+///     .unwrap();
+/// assert_eq!(tracklist.files[0].tracks.len(), 1);
+/// ```
+impl FromStr for Tracklist {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Tracklist::parse(s)
+    }
+}
+
+impl<'a> IntoIterator for &'a TrackFile {
+    type Item = &'a Track;
+    type IntoIter = slice::Iter<'a, Track>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.tracks.iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a mut TrackFile {
+    type Item = &'a mut Track;
+    type IntoIter = slice::IterMut<'a, Track>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.tracks.iter_mut()
+    }
+}
+
+/// Returns the track at a position (starting at 0) in the file.
+impl ops::Index<usize> for TrackFile {
+    type Output = Track;
+
+    fn index(&self, position: usize) -> &Track {
+        &self.tracks[position]
+    }
+}
+
+impl ops::IndexMut<usize> for TrackFile {
+    fn index_mut(&mut self, position: usize) -> &mut Track {
+        &mut self.tracks[position]
+    }
+}
+
+/// A track number changed by `Tracklist::normalize`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Renumbering {
+    /// Index of the file containing the track in `Tracklist::files`.
+    pub file: usize,
+
+    /// Previous number of the track.
+    pub old: u32,
+
+    /// New number of the track.
+    pub new: u32,
+}
+
+/// Position of a track within a `BINARY` image, see `TrackFile::layout`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TrackLayout {
+    /// Number of the track.
+    pub number: u32,
+
+    /// Offset of the start of the track from the start of the file in bytes.
+    pub offset: u64,
+
+    /// Length of the track in bytes, if known.
+    pub length: Option<u64>,
+}
+
+/// One track described by a tracklist.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Track {
+    /// Title of the track.
+    pub title: Option<String>,
+
+    /// Type of the track.
+    pub track_type: TrackType,
+
+    /// Duration of the track, if it was possible to determine it.
+    ///
+    /// This is only possible if tracks have index commands attached to them.
+    /// Also note that with just a cue file it is usually not possible to determine the duration of
+    /// the last track in the list.
+    pub duration: Option<Time>,
+
+    /// Where `Track::duration` comes from, `None` if the duration is unknown.
+    pub duration_provenance: Option<DurationProvenance>,
+
+    /// Index commands attached to this track (if any).
+    pub index: Vec<Index>,
+
+    /// Track number as provided in the cue sheet.
+    pub number: u32,
+
+    /// The performer of the track if any was stated.
+    pub performer: Option<String>,
+
+    /// Start of the track relative to the start of the first file, see `Track::album_start`.
+    pub(crate) album_start: Option<Time>,
+
+    /// Subcode flags of the track.
+    ///
+    /// Flags read from a cue sheet are sorted and without duplicates, even if they were spread
+    /// over multiple `FLAGS` commands.
+    pub flags: Vec<TrackFlag>,
+
+    /// The International Standard Recording Code of the track if any was stated.
+    pub isrc: Option<String>,
+
+    /// Amount of silence to add before the track, if a `PREGAP` was stated.
+    ///
+    /// Unlike a pregap marked by `INDEX 00` this isn't part of the file, so it doesn't shorten
+    /// the previous track.
+    pub pregap: Option<Time>,
+
+    /// Amount of silence to add after the track, if any was stated.
+    pub postgap: Option<Time>,
+
+    /// The songwriter of the track if any was stated.
+    pub songwriter: Option<String>,
+
+    /// Track level `REM` comments as (key, value) pairs in the order they appeared.
+    pub rem: Vec<(String, Token)>,
+
+    /// Start of the pregap at the end of the previous file, for a track which is the first in
+    /// its file, see `FileLayout::GapsAppended`.
+    ///
+    /// The pregap is not part of `index` then, since index positions are relative to the file
+    /// containing the track.
+    pub pregap_in_previous_file: Option<Time>,
+}
+
+type Index = (u32, Time);
+
+/// A section of a track marked by an index, see `Track::subindexes`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Subindex {
+    /// Number of the index.
+    pub number: u32,
+
+    /// Position of the index relative to the start of the file.
+    pub time: Time,
+
+    /// Time until the next index or the end of the track, if known.
+    pub duration: Option<Time>,
+}
+
+/// The sections of a track, see `Track::timing`.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct TrackTiming {
+    /// Length of the pregap, from `INDEX 00` to `INDEX 01`.
+    pub pregap: Option<Time>,
+
+    /// Length of the track from `INDEX 01` to the start of the next track or the end of the file,
+    /// if known.
+    pub body: Option<Time>,
+
+    /// Length of the silence to be generated after the track, from `POSTGAP`.
+    pub postgap: Option<Time>,
+}
+
+impl Track {
+    /// Create an audio track numbered `number`, with its `INDEX 01` at `start` and nothing else
+    /// set.
+    ///
+    /// ```
+    /// use cue_sheet::parser::Time;
+    /// use cue_sheet::tracklist::{TrackFile, Tracklist, Track};
+    ///
+    /// let mut file = TrackFile::new("Loveless.wav", Default::default());
+    /// file.tracks.push(Track::audio(1, Time::new(0, 0, 0)));
+    /// let tracklist = Tracklist {
+    ///     files: vec![file],
+    ///     ..Default::default()
+    /// };
+    /// assert!(tracklist.to_cue_string().contains("TRACK 01 AUDIO"));
+    /// ```
+    pub fn audio(number: u32, start: Time) -> Track {
+        Track {
+            number,
+            index: vec![(1, start)],
+            ..Default::default()
         }
     }
-}
 
-/// One track described by a tracklist.
-#[derive(Clone, Debug, Eq, PartialEq)]
-pub struct Track {
-    /// Title of the track.
-    pub title: Option<String>,
+    /// Returns the flags of the track sorted and without duplicates, the way they are written
+    /// to a cue sheet.
+    pub fn canonical_flags(&self) -> Vec<TrackFlag> {
+        let mut flags = self.flags.clone();
+        flags.sort();
+        flags.dedup();
+        flags
+    }
+
+    /// Returns the start of the track relative to the start of the first file.
+    ///
+    /// For tracks in the first file this is just `Track::start`, for tracks in later files the
+    /// lengths of the preceding files have to be known, see `Tracklist::fill_durations`.
+    ///
+    /// ```
+    /// use cue_sheet::tracklist::{TrackFile, Tracklist};
+    /// use cue_sheet::errors::Error;
+    /// use cue_sheet::parser::Time;
+    ///
+    /// let mut tracklist = Tracklist::parse(
+    ///     "FILE a.wav WAVE\nTRACK 01 AUDIO\nINDEX 01 00:00:00\n\
+    ///      FILE b.wav WAVE\nTRACK 02 AUDIO\nINDEX 01 00:00:10",
+    /// )
+    /// .unwrap();
+    /// assert_eq!(tracklist.files[1].tracks[0].album_start(), None);
+    ///
+    /// let length = |_: &TrackFile| -> Result<Time, Error> { Ok(Time::new(3, 0, 0)) };
+    /// tracklist.fill_durations(&length).unwrap();
+    /// assert_eq!(tracklist.files[1].tracks[0].album_start(), Some(Time::new(3, 0, 10)));
+    /// ```
+    pub fn album_start(&self) -> Option<Time> {
+        self.album_start.clone()
+    }
+
+    /// Returns the start of the track, i.e. the time of `INDEX 01`, falling back to the first
+    /// index if there is none.
+    ///
+    /// The tracks of a `TrackFile` read from a cue sheet are sorted by their start, outside of
+    /// strict mode after reporting a `WarningKind::TracksOutOfOrder`.
+    pub fn start(&self) -> Option<Time> {
+        self.index01()
+            .or_else(|| self.index.first().map(|(_, time)| time.clone()))
+    }
+
+    /// Returns the start of the pregap of the track, i.e. the time of `INDEX 00`.
+    pub fn pregap_start(&self) -> Option<Time> {
+        self.index00()
+    }
+
+    /// Returns the time of the index with the specified number.
+    pub fn index_time(&self, number: u32) -> Option<Time> {
+        self.index
+            .iter()
+            .find(|&&(n, _)| n == number)
+            .map(|(_, time)| time.clone())
+    }
+
+    /// Returns the time of `INDEX 00`, the start of the pregap.
+    ///
+    /// ```
+    /// use cue_sheet::parser::Time;
+    /// use cue_sheet::tracklist::Tracklist;
+    ///
+    /// let tracklist = Tracklist::parse(
+    ///     "FILE a.wav WAVE\nTRACK 01 AUDIO\nINDEX 00 00:00:00\nINDEX 01 00:00:32",
+    /// )
+    /// .unwrap();
+    /// let track = &tracklist.files[0].tracks[0];
+    /// assert_eq!(track.index00(), Some(Time::new(0, 0, 0)));
+    /// assert_eq!(track.index01(), Some(Time::new(0, 0, 32)));
+    /// ```
+    pub fn index00(&self) -> Option<Time> {
+        self.index_time(0)
+    }
+
+    /// Returns the time of `INDEX 01`, the start of the track proper.
+    ///
+    /// Unlike `Track::start` this doesn't fall back to another index.
+    pub fn index01(&self) -> Option<Time> {
+        self.index_time(1)
+    }
+
+    /// Returns the indexes from `INDEX 01` on, which mark the sections of a track (e.g. the
+    /// movements of a classical piece), together with their duration.
+    ///
+    /// The duration of the last section is the remaining duration of the track, if known.
+    ///
+    /// ```
+    /// use cue_sheet::parser::Time;
+    /// use cue_sheet::tracklist::Tracklist;
+    ///
+    /// let tracklist = Tracklist::parse(
+    ///     "FILE a.wav WAVE\nTRACK 01 AUDIO\nINDEX 01 00:00:00\nINDEX 02 03:00:00\n\
+    ///      TRACK 02 AUDIO\nINDEX 01 05:30:00",
+    /// )
+    /// .unwrap();
+    ///
+    /// let sections = tracklist.files[0].tracks[0].subindexes();
+    /// assert_eq!(sections.len(), 2);
+    /// assert_eq!(sections[0].duration, Some(Time::new(3, 0, 0)));
+    /// assert_eq!(sections[1].number, 2);
+    /// assert_eq!(sections[1].duration, Some(Time::new(2, 30, 0)));
+    /// ```
+    pub fn subindexes(&self) -> Vec<Subindex> {
+        let indexes: Vec<&Index> = self.index.iter().filter(|&&(n, _)| n >= 1).collect();
+        indexes
+            .iter()
+            .enumerate()
+            .map(|(i, &&(number, ref time))| {
+                let duration = match indexes.get(i + 1) {
+                    Some((_, next)) => Some(next.clone() - time.clone()),
+                    None => self.duration.clone(),
+                };
+                Subindex {
+                    number,
+                    time: time.clone(),
+                    duration,
+                }
+            })
+            .collect()
+    }
+
+    /// Returns the lengths of the pregap, the body and the postgap of the track.
+    ///
+    /// A pregap stored at the end of the previous file (see `Track::pregap_in_previous_file`)
+    /// has no known length here. Like `Track::duration` the body includes the postgap with
+    /// `DurationMode::WithPostgap`.
+    ///
+    /// ```
+    /// use cue_sheet::parser::Time;
+    /// use cue_sheet::tracklist::Tracklist;
+    ///
+    /// let tracklist = Tracklist::parse(
+    ///     "FILE a.wav WAVE\nTRACK 01 AUDIO\nINDEX 01 00:00:00\nTRACK 02 AUDIO\n\
+    ///      INDEX 00 04:15:00\nINDEX 01 04:17:52\nPOSTGAP 00:02:00\nTRACK 03 AUDIO\n\
+    ///      INDEX 01 08:30:00",
+    /// )
+    /// .unwrap();
+    /// let timing = tracklist.files[0].tracks[1].timing();
+    /// assert_eq!(timing.pregap, Some(Time::new(0, 2, 52)));
+    /// assert_eq!(timing.body, Some(Time::new(4, 12, 23)));
+    /// assert_eq!(timing.postgap, Some(Time::new(0, 2, 0)));
+    /// assert_eq!(tracklist.files[0].tracks[0].timing().pregap, None);
+    /// ```
+    pub fn timing(&self) -> TrackTiming {
+        let pregap = match (self.index00(), self.index01()) {
+            (Some(pregap), Some(start)) => start.checked_sub(&pregap),
+            _ => None,
+        };
+        let body = self
+            .start_end()
+            .and_then(|(start, end)| end.checked_sub(&start));
+        TrackTiming {
+            pregap,
+            body,
+            postgap: self.postgap.clone(),
+        }
+    }
+
+    /// Returns the number of bytes per sector of the track, as determined by its type.
+    pub fn sector_size(&self) -> u16 {
+        self.track_type.sector_size()
+    }
+
+    /// Returns the length of the track in bytes, from its first index (including a pregap
+    /// marked by `INDEX 00`) to its end, if the end of the track is known.
+    ///
+    /// Every frame corresponds to one sector of `Track::sector_size` bytes, this is the length
+    /// of the track in `TrackFile::layout`. For data tracks this is usually more meaningful
+    /// than the duration itself.
+    pub fn byte_length(&self) -> Option<u64> {
+        let (_, end) = self.start_end()?;
+        let sectors = end.total_frames() - self.first_index_frames();
+        Some(sectors.max(0) as u64 * u64::from(self.sector_size()))
+    }
+
+    /// Returns the position of the first index of the track in frames, 0 without indexes.
+    fn first_index_frames(&self) -> i64 {
+        self.index
+            .first()
+            .map_or(0, |(_, time)| time.total_frames())
+    }
 
-    /// Type of the track.
-    pub track_type: TrackType,
+    /// Returns `Track::duration` in audio samples (per channel) at the specified sample rate,
+    /// if it is known.
+    ///
+    /// Like the duration this counts from the last index of the track, so for tracks with an
+    /// `INDEX 00` or `INDEX 02` and later it is shorter than `Track::byte_range_in_file`. Times
+    /// are assumed to count 75 frames per second, for a nonstandard `Tracklist::frame_rate`
+    /// use `split::gapless_ranges`.
+    pub fn duration_samples(&self, sample_rate: u32) -> Option<i64> {
+        self.duration.as_ref()?.checked_to_samples(sample_rate)
+    }
 
-    /// Duration of the track, if it was possible to determine it.
+    /// Returns the range of bytes the track occupies in the PCM data decoded from its file, if
+    /// the end of the track is known.
     ///
-    /// This is only possible if tracks have index commands attached to them.
-    /// Also note that with just a cue file it is usually not possible to determine the duration of
-    /// the last track in the list.
-    pub duration: Option<Time>,
+    /// Like `Track::byte_length` the range starts at the first index of the track, so a pregap
+    /// marked by `INDEX 00` is part of it, and ends where the next track starts. The ranges of
+    /// the tracks of a file therefore cover it without gaps. `split::SplitPlan` instead starts
+    /// tracks at `INDEX 01` and appends the pregap to the preceding track.
+    ///
+    /// This applies to audio files (anything but `BINARY` and `MOTOROLA`) once decoded, e.g.
+    /// the data chunk of a WAVE file. Splitters can slice the decoded stream without any
+    /// conversions of their own. For binary files see `TrackFile::layout`.
+    ///
+    /// ```
+    /// use cue_sheet::tracklist::Tracklist;
+    ///
+    /// let tracklist = Tracklist::parse(
+    ///     "FILE a.flac WAVE\nTRACK 01 AUDIO\nINDEX 01 00:00:00\nTRACK 02 AUDIO\nINDEX 01 00:01:00",
+    /// )
+    /// .unwrap();
+    /// let track = &tracklist.files[0].tracks[0];
+    /// assert_eq!(track.duration_samples(44100), Some(44100));
+    /// assert_eq!(track.byte_range_in_file(44100, 2, 2), Some(0..176400));
+    /// assert_eq!(tracklist.files[0].tracks[1].byte_range_in_file(44100, 2, 2), None);
+    /// ```
+    pub fn byte_range_in_file(
+        &self,
+        sample_rate: u32,
+        bytes_per_sample: u16,
+        channels: u16,
+    ) -> Option<Range<u64>> {
+        let (start, end) = self.index_span()?;
+        let frame_bytes = u64::from(bytes_per_sample) * u64::from(channels);
+        let offset = |time: &Time| {
+            let samples = time.checked_to_samples(sample_rate)?.max(0) as u64;
+            samples.checked_mul(frame_bytes)
+        };
+        Some(offset(&start)?..offset(&end)?)
+    }
 
-    /// Index commands attached to this track (if any).
-    pub index: Vec<Index>,
+    /// Returns the start (`INDEX 01`) and end of the track relative to the start of its file.
+    fn start_end(&self) -> Option<(Time, Time)> {
+        let (_, last) = self.index.last()?;
+        let end = last.checked_add(self.duration.as_ref()?)?;
+        Some((self.start()?, end))
+    }
 
-    /// Track number as provided in the cue sheet.
-    pub number: u32,
+    /// Returns the first index and the end of the track relative to the start of its file.
+    fn index_span(&self) -> Option<(Time, Time)> {
+        let (_, end) = self.start_end()?;
+        Some((self.index.first()?.1.clone(), end))
+    }
 
-    /// The performer of the track if any was stated.
-    pub performer: Option<String>,
-}
+    /// Appends the `TRACK` command and the commands describing the track except for its
+    /// indexes and postgap to `commands`.
+    fn push_header(&self, commands: &mut Vec<Command>) {
+        commands.push(Command::Track(self.number, self.track_type.clone()));
+        if let Some(ref title) = self.title {
+            commands.push(Command::Title(title.clone()));
+        }
+        if let Some(ref performer) = self.performer {
+            commands.push(Command::Performer(performer.clone()));
+        }
+        if let Some(ref songwriter) = self.songwriter {
+            commands.push(Command::Songwriter(songwriter.clone()));
+        }
+        for (key, value) in &self.rem {
+            commands.push(Command::Rem(key.clone(), value.clone()));
+        }
+        if !self.flags.is_empty() {
+            commands.push(Command::Flags(self.canonical_flags()));
+        }
+        if let Some(ref isrc) = self.isrc {
+            commands.push(Command::Isrc(isrc.clone()));
+        }
+        if let Some(ref pregap) = self.pregap {
+            commands.push(Command::Pregap(pregap.clone()));
+        }
+    }
 
-type Index = (u32, Time);
+    /// Adds the postgap (if any) to the duration (if known).
+    pub(crate) fn add_postgap(&mut self) -> Result<(), Error> {
+        let number = self.number;
+        if let (Some(duration), Some(postgap)) = (self.duration.as_mut(), self.postgap.as_ref()) {
+            *duration = duration
+                .checked_add(postgap)
+                .ok_or_else(|| format!("Duration of track {:02} overflowed", number))?;
+        }
+        Ok(())
+    }
 
-impl Track {
-    fn consume(commands: &mut Vec<Command>) -> Result<Track, Error> {
-        if let Command::Track(track_num, track_type) = commands.remove(0) {
+    /// Position of the start of the file containing this track relative to the start of the
+    /// first file in frames.
+    pub(crate) fn file_offset(&self) -> Option<i64> {
+        match (self.album_start.as_ref(), self.start()) {
+            (Some(album_start), Some(start)) => {
+                Some(album_start.total_frames() - start.total_frames())
+            }
+            _ => None,
+        }
+    }
+
+    /// Consumes a `TRACK` command and all commands belonging to it.
+    ///
+    /// The commands of a track block may appear in any order.
+    fn consume(commands: &mut Vec<CommandLine>) -> Result<Track, Error> {
+        if let (Command::Track(track_num, track_type), line) = commands[0].clone() {
+            commands.remove(0);
             let mut title = None;
             let mut performer = None;
+            let mut songwriter = None;
+            let mut rem = Vec::new();
             let mut index = Vec::new();
+            let mut flags = Vec::new();
+            let mut isrc = None;
+            let mut pregap = None;
+            let mut postgap = None;
 
-            while commands.len() > 0 {
-                match commands[0].clone() {
-                    Command::Performer(p) => {
-                        performer = Some(p);
-                        commands.remove(0);
-                    }
-                    Command::Title(t) => {
-                        title = Some(t);
-                        commands.remove(0);
-                    }
-                    Command::Pregap(time) => {
-                        let next_command = commands
-                            .get(1)
-                            .ok_or("Pregap is the last command in the track!".to_owned())?
-                            .to_owned();
-
-                        let first_index;
-                        match next_command {
-                            Command::Index(_, time) => first_index = time,
-                            _ => {
-                                return Err("Pregap is not followed by an index!".into());
-                            }
-                        }
-                        let diff = first_index.total_frames() - time.total_frames();
-                        index.push((0, Time::from_frames(diff)));
-                        commands.remove(0);
-                    }
-                    Command::Index(i, time) => {
-                        index.push((i, time));
-                        commands.remove(0);
-                    }
+            while !commands.is_empty() {
+                match commands[0].0.clone() {
+                    Command::Performer(p) => performer = Some(p),
+                    Command::Title(t) => title = Some(t),
+                    Command::Songwriter(s) => songwriter = Some(s),
+                    Command::Rem(key, value) => rem.push((key, value)),
+                    Command::Pregap(time) => pregap = Some(time),
+                    Command::Index(i, time) => index.push((i, time)),
+                    Command::Flags(f) => flags.extend(f),
+                    Command::Isrc(i) => isrc = Some(i),
+                    Command::Postgap(time) => postgap = Some(time),
                     _ => break,
                 }
+                commands.remove(0);
             }
+            index.sort_by_key(|&(num, _)| num);
+
+            if pregap.is_some() && index.is_empty() {
+                return Err(format!(
+                    "PREGAP of track {:02} on line {} is not followed by an INDEX",
+                    track_num, line
+                )
+                .into());
+            }
+
+            flags.sort();
+            flags.dedup();
 
             Ok(Track {
-                title: title,
-                track_type: track_type,
+                title,
+                track_type,
                 duration: None,
-                index: index,
+                duration_provenance: None,
+                index,
                 number: track_num,
-                performer: performer,
+                performer,
+                album_start: None,
+                flags,
+                isrc,
+                pregap,
+                postgap,
+                songwriter,
+                rem,
+                pregap_in_previous_file: None,
             })
         } else {
             Err("Track::consume called but no Track command found.".into())
@@ -220,6 +2222,7 @@ impl Track {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use filesystem::MemoryFileSystem;
 
     #[test]
     fn sample() {
@@ -240,16 +2243,23 @@ mod tests {
                             INDEX 01 04:17:52"#;
 
         let tracklist = Tracklist::parse(source).unwrap();
+        assert_eq!(tracklist.rem.len(), 4);
+        assert_eq!(tracklist.date(), Some("1991".to_string()));
+        assert_eq!(tracklist.date_parsed(), Some(Date::new(1991, None, None)));
+        assert_eq!(
+            tracklist.rem_value("genre"),
+            Some("Alternative".to_string())
+        );
         assert_eq!(tracklist.title.unwrap(), "Loveless".to_string());
 
         let files = tracklist.files;
         assert_eq!(files.len(), 1);
 
-        let ref f = files[0];
+        let f = &files[0];
         assert_eq!(f.name, "My Bloody Valentine - Loveless.wav".to_string());
         assert_eq!(f.format, FileFormat::Wave);
 
-        let ref tracks = f.tracks;
+        let tracks = &f.tracks;
         assert_eq!(tracks.len(), 2);
 
         assert_eq!(tracks[0].clone().title.unwrap(), "Only Shallow".to_string());
@@ -266,6 +2276,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::zero_prefixed_literal)]
     fn pregap() {
         let src = r#"FILE "disc.img" BINARY
                        TRACK 01 MODE1/2352
@@ -279,13 +2290,946 @@ mod tests {
 
         let tracklist = Tracklist::parse(src).unwrap();
 
-        let ref f = tracklist.files[0];
-        let ref tracks = f.tracks;
+        let f = &tracklist.files[0];
+        let tracks = &f.tracks;
 
         assert_eq!(tracks[0].index[0], (1, Time::new(0, 0, 0)));
-        assert_eq!(tracks[1].index[0], (0, Time::new(58, 39, 36)));
-        assert_eq!(tracks[1].index[1], (1, Time::new(58, 41, 36)));
+        assert_eq!(tracks[0].duration, Some(Time::new(58, 41, 36)));
+        assert_eq!(tracks[1].pregap, Some(Time::new(0, 2, 0)));
+        assert_eq!(tracks[1].index, vec![(1, Time::new(58, 41, 36))]);
+        assert_eq!(tracks[1].pregap_start(), None);
         assert_eq!(tracks[2].index[0], (0, Time::new(61, 06, 08)));
         assert_eq!(tracks[2].index[1], (1, Time::new(61, 08, 08)));
     }
+
+    #[test]
+    fn reordered_track_commands() {
+        let src = r#"FILE "disc.wav" WAVE
+                       TRACK 01 AUDIO
+                         INDEX 01 00:00:00
+                         TITLE "Only Shallow"
+                       TRACK 02 AUDIO
+                         PREGAP 00:02:00
+                         REM COMPOSER "Kevin Shields"
+                         INDEX 01 04:17:52
+                         SONGWRITER "Kevin Shields"
+                         PERFORMER "My Bloody Valentine"
+                         TITLE "Loomer"
+                       TRACK 03 AUDIO
+                         INDEX 01 07:02:00
+                         INDEX 00 07:00:00"#;
+
+        let tracklist = Tracklist::parse(src).unwrap();
+        let tracks = &tracklist.files[0].tracks;
+        assert_eq!(tracks.len(), 3);
+        assert_eq!(tracks[0].title, Some("Only Shallow".to_string()));
+        assert_eq!(tracks[1].title, Some("Loomer".to_string()));
+        assert_eq!(tracks[1].performer, Some("My Bloody Valentine".to_string()));
+        assert_eq!(tracks[1].songwriter, Some("Kevin Shields".to_string()));
+        assert_eq!(tracks[1].rem.len(), 1);
+        assert_eq!(tracks[1].pregap, Some(Time::new(0, 2, 0)));
+        assert_eq!(tracks[1].index[0], (1, Time::new(4, 17, 52)));
+        assert_eq!(tracks[2].index[0], (0, Time::new(7, 0, 0)));
+        assert_eq!(tracks[2].index[1], (1, Time::new(7, 2, 0)));
+
+        let reparsed = Tracklist::parse(&tracklist.to_cue_string()).unwrap();
+        assert_eq!(reparsed.files, tracklist.files);
+    }
+
+    #[test]
+    fn track_order() {
+        let src = r#"FILE "disc.wav" WAVE
+                       TRACK 01 AUDIO
+                         INDEX 01 00:00:00
+                       TRACK 02 AUDIO
+                         INDEX 00 02:58:00
+                         INDEX 01 03:00:00
+                       TRACK 03 AUDIO
+                         INDEX 01 01:00:00"#;
+        let strict = ParseOptions::new().strict(true);
+        match Tracklist::parse_with_options(src, &strict)
+            .unwrap_err()
+            .kind()
+        {
+            ErrorKind::TracksOutOfOrder(3, 7) => {}
+            e => panic!("unexpected error: {:?}", e),
+        }
+
+        let (tracklist, warnings) =
+            Tracklist::parse_with_warnings(src, &ParseOptions::new()).unwrap();
+        assert_eq!(
+            warnings,
+            vec![Warning {
+                line: 7,
+                kind: WarningKind::TracksOutOfOrder(3),
+            }]
+        );
+        let tracks = &tracklist.files[0].tracks;
+        let numbers: Vec<u32> = tracks.iter().map(|t| t.number).collect();
+        assert_eq!(numbers, vec![1, 3, 2]);
+        assert_eq!(tracks[0].duration, Some(Time::new(1, 0, 0)));
+        assert_eq!(tracks[1].duration, Some(Time::new(1, 58, 0)));
+        assert_eq!(tracks[2].duration, None);
+
+        let tracklist = Tracklist::parse(&src[..src.find("TRACK 03").unwrap()]).unwrap();
+        let track = &tracklist.files[0].tracks[1];
+        assert_eq!(track.start(), Some(Time::new(3, 0, 0)));
+        assert_eq!(track.pregap_start(), Some(Time::new(2, 58, 0)));
+        assert_eq!(tracklist.files[0].tracks[0].pregap_start(), None);
+    }
+
+    #[test]
+    fn commands() {
+        let src = "FILE a.wav WAVE\nTRACK 01 AUDIO\nINDEX 01 00:00:00\nTRACK 02 AUDIO\n\
+                   INDEX 01 04:17:52";
+        let tracklist = Tracklist::parse(src).unwrap();
+        let mut commands = tracklist.to_commands();
+        assert_eq!(commands.len(), 5);
+        let copy = Tracklist::from_commands(commands.clone()).unwrap();
+        assert_eq!(copy.files, tracklist.files);
+
+        commands[2] = Command::Index(1, Time::new(5, 0, 0));
+        match Tracklist::from_commands(commands).unwrap_err().kind() {
+            ErrorKind::TracksOutOfOrder(2, 4) => {}
+            e => panic!("unexpected error: {:?}", e),
+        }
+    }
+
+    #[test]
+    fn iteration() {
+        let src = "FILE a.wav WAVE\nTRACK 01 AUDIO\nINDEX 01 00:00:00\nTRACK 02 AUDIO\n\
+                   INDEX 01 04:17:52\nFILE b.wav WAVE\nTRACK 03 AUDIO\nINDEX 01 00:00:00";
+        let mut tracklist = Tracklist::parse(src).unwrap();
+        for track in &mut tracklist {
+            track.performer = Some("My Bloody Valentine".to_string());
+        }
+        for file in &mut tracklist.files {
+            for track in file {
+                track.number += 10;
+            }
+        }
+
+        let numbers: Vec<u32> = (&tracklist).into_iter().map(|t| t.number).collect();
+        assert_eq!(numbers, vec![11, 12, 13]);
+        assert!((&tracklist).into_iter().all(|t| t.performer.is_some()));
+        assert_eq!(tracklist[2].number, 13);
+        assert_eq!(tracklist.files[0][1].number, 12);
+    }
+
+    #[test]
+    fn normalize() {
+        let mut src = String::new();
+        for i in 0..100 {
+            src.push_str(&format!(
+                "FILE \"{}.wav\" WAVE\nTRACK 01 AUDIO\nINDEX 01 00:00:00\n",
+                i
+            ));
+        }
+        let mut tracklist = Tracklist::parse(&src).unwrap();
+        match tracklist.normalize().unwrap_err().kind() {
+            ErrorKind::TooManyTracks(100) => {}
+            e => panic!("unexpected error: {:?}", e),
+        }
+        assert!(tracklist.files.iter().all(|f| f.tracks[0].number == 1));
+
+        tracklist.files.truncate(99);
+        let changes = tracklist.normalize().unwrap();
+        assert_eq!(changes.len(), 98);
+        assert_eq!(
+            changes[97],
+            Renumbering {
+                file: 98,
+                old: 1,
+                new: 99,
+            }
+        );
+        assert!(tracklist.normalize().unwrap().is_empty());
+    }
+
+    #[test]
+    fn normalized_file_names() {
+        let tracklist = Tracklist::parse(
+            "FILE \"C:\\Rips\\a.wav\" WAVE\nTRACK 01 AUDIO\nINDEX 01 00:00:00\nFILE \"b c/d.wav\" WAVE",
+        )
+        .unwrap();
+        assert_eq!(tracklist.files[1].file_name_normalized(), "d.wav");
+        assert_eq!(
+            tracklist.files[1].relative_to("/x").unwrap(),
+            Path::new("/x/b c/d.wav")
+        );
+
+        let options = WriteOptions {
+            normalize_file_names: true,
+            ..Default::default()
+        };
+        let cue = tracklist.to_cue_string_with_options(&options);
+        let files: Vec<_> = cue.lines().filter(|l| l.starts_with("FILE")).collect();
+        assert_eq!(files, vec!["FILE \"a.wav\" WAVE", "FILE \"d.wav\" WAVE"]);
+    }
+
+    #[test]
+    fn escaping_file_names() {
+        let tracklist =
+            Tracklist::parse("FILE \"a/../../x.wav\" WAVE\nFILE \"..\\..\\y.wav\" WAVE").unwrap();
+        assert!(tracklist.files[0].relative_to("/m/cd").is_err());
+        assert!(tracklist.files[1].relative_to("/m/cd").is_err());
+
+        let mut fs = MemoryFileSystem::new();
+        fs.insert("/m/x.wav", Vec::new());
+        fs.insert("/m/cd/x.wav", Vec::new());
+        assert_eq!(
+            tracklist.files[0].resolve("/m/cd", &fs),
+            Some(PathBuf::from("/m/cd/x.wav"))
+        );
+        assert_eq!(tracklist.files[1].resolve("/m/cd", &fs), None);
+    }
+
+    #[test]
+    fn binary_layout() {
+        let src = "FILE \"game.bin\" BINARY
+                     TRACK 01 MODE2/2352
+                       INDEX 01 00:00:00
+                     TRACK 02 AUDIO
+                       INDEX 00 01:00:00
+                       INDEX 01 01:02:00
+                     TRACK 03 AUDIO
+                       INDEX 01 02:00:00";
+        let mut tracklist = Tracklist::parse(src).unwrap();
+        let length = |_: &TrackFile| -> Result<Time, Error> { Ok(Time::new(3, 0, 0)) };
+        tracklist.fill_durations(&length).unwrap();
+
+        let file = &tracklist.files[0];
+        assert_eq!(file.tracks[0].byte_length(), Some(4500 * 2352));
+        assert_eq!(file.tracks[2].byte_length(), Some(4500 * 2352));
+        assert_eq!(
+            file.layout(),
+            vec![
+                TrackLayout {
+                    number: 1,
+                    offset: 0,
+                    length: Some(4500 * 2352),
+                },
+                TrackLayout {
+                    number: 2,
+                    offset: 4500 * 2352,
+                    length: Some(4500 * 2352),
+                },
+                TrackLayout {
+                    number: 3,
+                    offset: 9000 * 2352,
+                    length: Some(4500 * 2352),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn frame_rate() {
+        let src = "FILE a.wav WAVE\nTRACK 01 AUDIO\nINDEX 01 00:00:00\nTRACK 02 AUDIO\n\
+                   INDEX 01 00:01:99";
+        let options = ParseOptions::new().frame_rate(100);
+        let mut tracklist = Tracklist::parse_with_options(src, &options).unwrap();
+        assert_eq!(tracklist.frame_rate, Some(100));
+        let tracks = &tracklist.files[0].tracks;
+        assert_eq!(tracks[0].duration, Some(Time::new(0, 1, 99)));
+        assert_eq!(tracks[1].album_start(), Some(Time::new(0, 1, 99)));
+
+        let ranges = ::split::gapless_ranges(&tracklist, 48000);
+        assert_eq!(ranges[1].start_sample, 95520);
+
+        let length = |_: &TrackFile| -> Result<Time, Error> { Ok(Time::new(0, 3, 0)) };
+        tracklist.fill_durations(&length).unwrap();
+        assert_eq!(
+            tracklist.files[0].tracks[1].duration,
+            Some(Time::new(0, 1, 1))
+        );
+
+        tracklist.apply_sample_offset(480, 48000).unwrap();
+        assert_eq!(tracklist.files[0].tracks[1].index[0].1, Time::new(0, 2, 0));
+
+        let options = ParseOptions::new().frame_rate(0);
+        assert!(Tracklist::parse_with_options(src, &options).is_err());
+        let options = ParseOptions::new().frame_rate(128);
+        assert!(Tracklist::parse_with_options(src, &options).is_err());
+
+        // Frames beyond the rate are normalized at that rate.
+        let options = ParseOptions::new().frame_rate(80);
+        let tracklist =
+            Tracklist::parse_with_options(&src.replace("00:01:99", "00:00:85"), &options).unwrap();
+        assert_eq!(tracklist.files[0].tracks[1].index[0].1, Time::new(0, 1, 5));
+    }
+
+    #[test]
+    fn byte_ranges_tile_file() {
+        let src = "FILE a.wav WAVE\nTRACK 01 AUDIO\nINDEX 01 00:00:00\n\
+                   TRACK 02 AUDIO\nINDEX 00 00:03:00\nINDEX 01 00:05:00\n\
+                   TRACK 03 AUDIO\nINDEX 00 00:09:00\nINDEX 01 00:10:00\nINDEX 02 00:11:00";
+        let mut tracklist = Tracklist::parse(src).unwrap();
+        let length = |_: &TrackFile| -> Result<Time, Error> { Ok(Time::new(0, 12, 0)) };
+        tracklist.fill_durations(&length).unwrap();
+
+        let mut end = 0;
+        for track in &tracklist.files[0].tracks {
+            let bytes = track.byte_range_in_file(44100, 2, 2).unwrap();
+            assert_eq!(bytes.start, end);
+            end = bytes.end;
+            assert_eq!(
+                track.duration_samples(44100),
+                Some(track.duration.as_ref().unwrap().to_samples(44100))
+            );
+        }
+        assert_eq!(end, 12 * 44100 * 4);
+        let track = &tracklist.files[0].tracks[2];
+        assert_eq!(track.duration_samples(44100), Some(44100));
+    }
+
+    #[test]
+    fn binary_layout_with_pregap() {
+        let src = "FILE \"game.bin\" BINARY
+                     TRACK 01 MODE1/2048
+                       INDEX 01 00:02:00
+                     TRACK 02 AUDIO
+                       INDEX 00 00:12:00
+                       INDEX 01 00:14:00
+                     TRACK 03 AUDIO
+                       INDEX 01 00:20:00";
+        let tracklist = Tracklist::parse(src).unwrap();
+        let file = &tracklist.files[0];
+        let layout = file.layout();
+
+        assert_eq!(layout[0].offset, 150 * 2048);
+        assert_eq!(layout[0].length, Some(750 * 2048));
+        assert_eq!(layout[1].offset, 150 * 2048 + 750 * 2048);
+        assert_eq!(layout[1].length, Some(600 * 2352));
+        assert_eq!(layout[2].offset, 150 * 2048 + 750 * 2048 + 600 * 2352);
+        assert_eq!(layout[2].length, None);
+        for (track, layout) in file.tracks.iter().zip(&layout) {
+            assert_eq!(track.byte_length(), layout.length);
+        }
+    }
+
+    #[test]
+    fn metadata_cleanup() {
+        let src = "TITLE \"Loveless \"
+                   FILE \"a.wav\" WAVE
+                     TRACK 01 AUDIO
+                       TITLE \" Only\u{1b} Shallow\"";
+        let mut options = ParseOptions::default();
+
+        let raw = Tracklist::parse_with_options(src, &options).unwrap();
+        assert_eq!(raw.title, Some("Loveless ".to_string()));
+
+        options.metadata_cleanup.trim = true;
+        options.metadata_cleanup.strip_control = true;
+        let tracklist = Tracklist::parse_with_options(src, &options).unwrap();
+        assert_eq!(tracklist.title, Some("Loveless".to_string()));
+        assert_eq!(
+            tracklist.files[0].tracks[0].title,
+            Some("Only Shallow".to_string())
+        );
+        assert_eq!(
+            raw.cleaned(&options.metadata_cleanup).title,
+            tracklist.title
+        );
+
+        let kept = tracklist.raw_metadata.as_ref().unwrap();
+        assert_eq!(kept.title, Some("Loveless ".to_string()));
+        assert_eq!(
+            kept.tracks[0].title,
+            Some(" Only\u{1b} Shallow".to_string())
+        );
+        assert_eq!(raw.raw_metadata, None);
+        assert_eq!(raw.cleaned(&Default::default()).raw_metadata, None);
+        let again = tracklist.cleaned(&MetadataCleanup::all());
+        assert_eq!(again.raw_metadata.as_ref(), Some(kept));
+    }
+
+    #[test]
+    fn format_mismatch() {
+        let tracklist = Tracklist::parse(
+            r#"FILE "a.mp3" WAVE
+               FILE "b.wav" WAVE
+               FILE "c.bin""#,
+        )
+        .unwrap();
+        let f = &tracklist.files;
+        assert_eq!(
+            f[0].format_mismatch(),
+            Some((FileFormat::Wave, FileFormat::Mp3))
+        );
+        assert_eq!(f[1].format_mismatch(), None);
+        assert_eq!(f[2].format, FileFormat::Binary);
+    }
+
+    #[test]
+    fn track_flags() {
+        let src = r#"CATALOG 0724384264525
+                     PERFORMER "Various"
+                     TITLE "Compilation"
+                     FILE "CDImage.wav" WAVE
+                       TRACK 01 AUDIO
+                         TITLE "First"
+                         FLAGS DCP PRE
+                         ISRC GBAYE6700149
+                         INDEX 01 00:00:00
+                         POSTGAP 00:02:00
+                       TRACK 02 AUDIO
+                         TITLE "Second"
+                         INDEX 01 03:12:40"#;
+
+        let tracklist = Tracklist::parse(src).unwrap();
+        let tracks = &tracklist.files[0].tracks;
+        assert_eq!(tracks.len(), 2);
+        assert_eq!(tracks[0].flags, vec![TrackFlag::Dcp, TrackFlag::Pre]);
+        assert_eq!(tracks[0].isrc, Some("GBAYE6700149".to_string()));
+        assert_eq!(tracks[0].postgap, Some(Time::new(0, 2, 0)));
+        assert_eq!(tracks[0].duration, Some(Time::new(3, 12, 40)));
+        assert_eq!(tracks[1].title, Some("Second".to_string()));
+        assert!(tracks[1].flags.is_empty());
+
+        let reparsed = Tracklist::parse(&tracklist.to_cue_string()).unwrap();
+        assert_eq!(reparsed.files, tracklist.files);
+    }
+
+    #[test]
+    fn flag_order() {
+        let src =
+            "FILE a.wav WAVE\nTRACK 01 AUDIO\nFLAGS PRE DCP\nFLAGS DCP SCMS\nINDEX 01 00:00:00";
+        let mut tracklist = Tracklist::parse(src).unwrap();
+        let expected = vec![TrackFlag::Dcp, TrackFlag::Pre, TrackFlag::Scms];
+        assert_eq!(tracklist.files[0].tracks[0].flags, expected);
+
+        tracklist.files[0].tracks[0].flags = vec![TrackFlag::Scms, TrackFlag::Dcp, TrackFlag::Scms];
+        assert!(tracklist.to_cue_string().contains("FLAGS DCP SCMS\n"));
+    }
+
+    #[test]
+    fn postgap_duration() {
+        let src = r#"FILE "a.wav" WAVE
+                       TRACK 01 AUDIO
+                         INDEX 01 00:00:00
+                         POSTGAP 00:02:00
+                       TRACK 02 AUDIO
+                         INDEX 01 03:00:00
+                         POSTGAP 00:01:00"#;
+
+        let tracklist = Tracklist::parse(src).unwrap();
+        assert_eq!(
+            tracklist.files[0].tracks[0].duration,
+            Some(Time::new(3, 0, 0))
+        );
+
+        let options = ParseOptions {
+            duration_mode: DurationMode::WithPostgap,
+            ..Default::default()
+        };
+        let mut tracklist = Tracklist::parse_with_options(src, &options).unwrap();
+        assert_eq!(
+            tracklist.files[0].tracks[0].duration,
+            Some(Time::new(3, 2, 0))
+        );
+        assert_eq!(tracklist.files[0].tracks[1].duration, None);
+
+        let length = |_: &TrackFile| -> Result<Time, Error> { Ok(Time::new(4, 0, 0)) };
+        tracklist.fill_durations(&length).unwrap();
+        assert_eq!(
+            tracklist.files[0].tracks[1].duration,
+            Some(Time::new(1, 1, 0))
+        );
+
+        let src = "FILE a.wav WAVE\nTRACK 01 AUDIO\nINDEX 01 00:00:00\nPOSTGAP 2147483647:00:00\n\
+                   TRACK 02 AUDIO\nINDEX 01 2147483647:00:00";
+        assert!(Tracklist::parse_with_options(src, &options).is_err());
+    }
+
+    #[test]
+    fn embedded() {
+        let src = "REM GENRE Shoegaze\nPERFORMER \"My Bloody Valentine\"\n\
+                   TRACK 01 AUDIO\nINDEX 01 00:00:00\nTRACK 02 AUDIO\nINDEX 01 04:17:52";
+        let tracklist = Tracklist::parse_embedded(src).unwrap();
+        assert_eq!(tracklist.files.len(), 1);
+        assert_eq!(tracklist.files[0].format, FileFormat::Wave);
+        assert_eq!(tracklist.files[0].tracks.len(), 2);
+        assert_eq!(
+            tracklist.files[0].tracks[0].duration,
+            Some(Time::new(4, 17, 52))
+        );
+        assert_eq!(tracklist.performer, Some("My Bloody Valentine".to_string()));
+
+        let src = "FILE \"Loveless.flac\" WAVE\nTRACK 01 AUDIO\nINDEX 01 00:00:00";
+        let tracklist = Tracklist::parse_embedded(src).unwrap();
+        assert_eq!(tracklist.files[0].name, "Loveless.flac");
+        assert_eq!(tracklist.files, Tracklist::parse(src).unwrap().files);
+    }
+
+    #[test]
+    fn single_file() {
+        let tracklist = Tracklist::single_file(
+            "rip.opus",
+            FileFormat::Wave,
+            vec![
+                ("Second".to_string(), Time::new(3, 0, 0)),
+                ("First".to_string(), Time::new(0, 0, 0)),
+                ("Third".to_string(), Time::new(125, 30, 0)),
+            ],
+        );
+
+        let cue = tracklist.to_cue_string();
+        let parsed = Tracklist::parse(&cue).unwrap();
+        assert_eq!(parsed.files, tracklist.files);
+
+        let tracks = &parsed.files[0].tracks;
+        assert_eq!(tracks[0].title, Some("First".to_string()));
+        assert_eq!(tracks[2].number, 3);
+        assert_eq!(tracks[1].duration, Some(Time::new(122, 30, 0)));
+        assert_eq!(tracks[2].duration, None);
+    }
+
+    #[test]
+    fn gaps_appended() {
+        let src = "FILE 01.wav WAVE\nTRACK 01 AUDIO\nINDEX 01 00:00:00\nTRACK 02 AUDIO\n\
+                   TITLE Loomer\nINDEX 00 04:15:00\nFILE 02.wav WAVE\nINDEX 01 00:00:00\n\
+                   TRACK 03 AUDIO\nINDEX 00 02:36:00\nFILE 03.wav WAVE\nINDEX 01 00:00:00";
+        let mut tracklist = Tracklist::parse(src).unwrap();
+        assert_eq!(tracklist.len(), 3);
+        assert_eq!(tracklist.files[0].tracks.len(), 1);
+        assert_eq!(
+            tracklist.files[1].tracks[0].title,
+            Some("Loomer".to_string())
+        );
+        assert_eq!(
+            tracklist.files[1].tracks[0].duration,
+            Some(Time::new(2, 36, 0))
+        );
+
+        let reparsed = Tracklist::parse(&tracklist.to_cue_string()).unwrap();
+        assert_eq!(reparsed.files, tracklist.files);
+
+        let length = |file: &TrackFile| -> Result<Time, Error> {
+            Ok(match file.name.as_str() {
+                "01.wav" => Time::new(4, 17, 52),
+                _ => Time::new(3, 0, 0),
+            })
+        };
+        tracklist.fill_durations(&length).unwrap();
+        let track = &tracklist.files[0].tracks[0];
+        assert_eq!(track.duration, Some(Time::new(4, 15, 0)));
+        assert_eq!(
+            tracklist.files[2].tracks[0].duration,
+            Some(Time::new(3, 0, 0))
+        );
+
+        let src = "FILE 01.wav WAVE\nTRACK 01 AUDIO\nINDEX 01 00:00:00\nFILE 02.wav WAVE\nINDEX 02 00:00:00";
+        assert!(Tracklist::parse(src).is_err());
+        assert_eq!(
+            Tracklist::parse("FILE 01.wav WAVE\nFILE 02.wav WAVE")
+                .unwrap()
+                .file_layout(),
+            FileLayout::GapsLeftOut
+        );
+    }
+
+    #[test]
+    fn concat() {
+        let disc = |file: &str| {
+            Tracklist::single_file(
+                file,
+                FileFormat::Wave,
+                vec![
+                    ("A".to_string(), Time::new(0, 0, 0)),
+                    ("B".to_string(), Time::new(3, 0, 0)),
+                ],
+            )
+        };
+        let mut first = disc("cd1.wav");
+        first.files[0].tracks[1].duration = Some(Time::new(2, 0, 0));
+
+        let joined = first.concat(&disc("cd2.wav"), None).unwrap();
+        assert_eq!(joined.files.len(), 2);
+        let tracks = &joined.files[1].tracks;
+        assert_eq!(tracks[0].number, 3);
+        assert_eq!(tracks[1].index, vec![(1, Time::new(3, 0, 0))]);
+        assert_eq!(tracks[1].album_start(), Some(Time::new(8, 0, 0)));
+
+        let joined = first
+            .concat(&disc("cd2.wav"), Some(Time::new(0, 2, 0)))
+            .unwrap();
+        assert_eq!(joined.files.len(), 1);
+        let tracks = &joined.files[0].tracks;
+        assert_eq!(tracks.len(), 4);
+        assert_eq!(tracks[3].number, 4);
+        assert_eq!(tracks[2].index, vec![(1, Time::new(5, 2, 0))]);
+        assert_eq!(tracks[3].album_start(), Some(Time::new(8, 2, 0)));
+
+        assert!(disc("cd1.wav")
+            .concat(&disc("cd2.wav"), Some(Time::new(0, 0, 0)))
+            .is_err());
+
+        // Positions which don't fit into a `Time` are errors instead of panics.
+        let huge = Some(Time::new(i32::MAX, 0, 0));
+        assert!(first.concat(&disc("cd2.wav"), huge.clone()).is_err());
+        assert_eq!(first.files.len(), 1);
+        assert_eq!(first.files[0].tracks.len(), 2);
+        let mut long = first.clone();
+        long.files[0].tracks[1].duration = huge;
+        let joined = long.concat(&disc("cd2.wav"), None).unwrap();
+        assert_eq!(joined.files[1].tracks[0].album_start(), None);
+
+        let mut files = first.concat(&disc("cd2.wav"), None).unwrap();
+        let length = |_: &TrackFile| -> Result<Time, Error> { Ok(Time::new(i32::MAX, 0, 0)) };
+        assert!(files.fill_durations(&length).is_err());
+    }
+
+    #[test]
+    fn listing_round_trip() {
+        let listing = vec![
+            ("Only Shallow".to_string(), Time::new(4, 17, 52)),
+            ("Loomer".to_string(), Time::new(2, 38, 23)),
+            ("Touched".to_string(), Time::new(0, 56, 0)),
+        ];
+        let tracklist = Tracklist::from_listing(
+            "My Bloody Valentine",
+            "Loveless",
+            "Loveless.wav",
+            FileFormat::Wave,
+            &listing,
+        )
+        .unwrap();
+
+        let cue = tracklist.to_cue_string();
+        assert_eq!(
+            cue.lines().take(6).collect::<Vec<_>>(),
+            vec![
+                "PERFORMER \"My Bloody Valentine\"",
+                "TITLE \"Loveless\"",
+                "FILE \"Loveless.wav\" WAVE",
+                "  TRACK 01 AUDIO",
+                "    TITLE \"Only Shallow\"",
+                "    PERFORMER \"My Bloody Valentine\"",
+            ]
+        );
+
+        let parsed = Tracklist::parse(&cue).unwrap();
+        let tracks = &parsed.files[0].tracks;
+        assert_eq!(tracks.len(), 3);
+        assert_eq!(tracks[2].index, vec![(1, Time::new(6, 56, 0))]);
+        assert_eq!(tracks[0].duration, Some(Time::new(4, 17, 52)));
+        assert_eq!(tracks[1].duration, Some(Time::new(2, 38, 23)));
+    }
+
+    #[test]
+    fn hidden_track() {
+        let src = r#"FILE "disc.wav" WAVE
+                       TRACK 01 AUDIO
+                         INDEX 00 00:00:00
+                         INDEX 01 01:23:45
+                       TRACK 02 AUDIO
+                         INDEX 01 05:00:00"#;
+        let tracklist = Tracklist::parse(src).unwrap();
+        assert_eq!(
+            tracklist.hidden_track(),
+            Some(HiddenTrack {
+                file: "disc.wav".to_string(),
+                duration: Time::new(1, 23, 45),
+            })
+        );
+
+        let src = r#"FILE "disc.wav" WAVE
+                       TRACK 01 AUDIO
+                         INDEX 01 00:00:00"#;
+        let tracklist = Tracklist::parse(src).unwrap();
+        assert_eq!(tracklist.hidden_track(), None);
+    }
+
+    #[test]
+    fn track_at() {
+        let src = r#"FILE "disc.wav" WAVE
+                       TRACK 01 AUDIO
+                         INDEX 01 00:10:00
+                       TRACK 02 AUDIO
+                         INDEX 00 04:00:00
+                         INDEX 01 04:02:00
+                     FILE "bonus.wav" WAVE
+                       TRACK 03 AUDIO
+                         INDEX 01 00:00:00"#;
+        let mut tracklist = Tracklist::parse(src).unwrap();
+        let number = |tracklist: &Tracklist, time| tracklist.track_at(time).map(|(_, t)| t.number);
+
+        assert_eq!(number(&tracklist, Time::new(0, 0, 0)), None);
+        assert_eq!(number(&tracklist, Time::new(0, 10, 0)), Some(1));
+        assert_eq!(number(&tracklist, Time::new(3, 59, 74)), Some(1));
+        assert_eq!(number(&tracklist, Time::new(4, 1, 0)), Some(2));
+        assert_eq!(number(&tracklist, Time::new(9, 0, 0)), Some(2));
+
+        let lengths = |file: &TrackFile| -> Result<Time, Error> {
+            match file.name.as_str() {
+                "disc.wav" => Ok(Time::new(8, 0, 0)),
+                _ => Ok(Time::new(1, 0, 0)),
+            }
+        };
+        tracklist.fill_durations(&lengths).unwrap();
+        assert_eq!(number(&tracklist, Time::new(7, 59, 74)), Some(2));
+        assert_eq!(number(&tracklist, Time::new(8, 0, 0)), Some(3));
+        assert_eq!(number(&tracklist, Time::new(8, 59, 74)), Some(3));
+        assert_eq!(number(&tracklist, Time::new(9, 0, 0)), None);
+        assert_eq!(
+            tracklist
+                .track_at_frame(Time::new(4, 1, 0).total_frames())
+                .map(|(f, _)| &f.name[..]),
+            Some("disc.wav")
+        );
+    }
+
+    #[test]
+    fn album_start() {
+        let src = r#"FILE "01.wav" WAVE
+                       TRACK 01 AUDIO
+                         INDEX 01 00:00:00
+                     FILE "02.wav" WAVE
+                       TRACK 02 AUDIO
+                         INDEX 01 00:00:00
+                       TRACK 03 AUDIO
+                         INDEX 01 01:00:00
+                     FILE "03.wav" WAVE
+                       TRACK 04 AUDIO
+                         INDEX 00 00:00:00
+                         INDEX 01 00:02:00"#;
+
+        let mut tracklist = Tracklist::parse(src).unwrap();
+        assert_eq!(
+            tracklist.files[0].tracks[0].album_start(),
+            Some(Time::new(0, 0, 0))
+        );
+        assert_eq!(tracklist.files[1].tracks[0].album_start(), None);
+
+        let mut lengths = HashMap::new();
+        lengths.insert("01.wav".to_string(), Time::new(3, 0, 10));
+        lengths.insert("02.wav".to_string(), Time::new(4, 0, 0));
+        lengths.insert("03.wav".to_string(), Time::new(2, 0, 0));
+        tracklist.fill_durations(&lengths).unwrap();
+
+        let f = &tracklist.files;
+        assert_eq!(f[0].tracks[0].duration, Some(Time::new(3, 0, 10)));
+        assert_eq!(f[1].tracks[0].album_start(), Some(Time::new(3, 0, 10)));
+        assert_eq!(f[1].tracks[1].album_start(), Some(Time::new(4, 0, 10)));
+        assert_eq!(f[1].tracks[1].duration, Some(Time::new(3, 0, 0)));
+        assert_eq!(f[2].tracks[0].album_start(), Some(Time::new(7, 2, 10)));
+        assert_eq!(f[2].tracks[0].duration, Some(Time::new(1, 58, 0)));
+
+        lengths.remove("03.wav");
+        assert!(tracklist.fill_durations(&lengths).is_err());
+    }
+
+    #[test]
+    fn apply_sample_offset() {
+        let src = "FILE a.wav WAVE\nTRACK 01 AUDIO\nINDEX 01 00:00:10\n\
+                   TRACK 02 AUDIO\nINDEX 00 04:15:00\nINDEX 01 04:17:52";
+        let mut tracklist = Tracklist::parse(src).unwrap();
+        // Half a frame is rounded away from zero.
+        tracklist.apply_sample_offset(294, 44100).unwrap();
+        let tracks = &tracklist.files[0].tracks;
+        assert_eq!(tracks[0].index[0].1, Time::new(0, 0, 11));
+        assert_eq!(tracks[0].duration, Some(Time::new(4, 14, 65)));
+        assert_eq!(tracks[1].index[0].1, Time::new(4, 15, 1));
+        assert_eq!(tracks[1].index[1].1, Time::new(4, 17, 53));
+
+        tracklist.apply_sample_offset(-294 - 5880, 44100).unwrap();
+        assert_eq!(tracklist.files[0].tracks[0].index[0].1, Time::new(0, 0, 0));
+        tracklist.apply_sample_offset(-1, 44100).unwrap();
+        assert!(tracklist.apply_sample_offset(-294, 44100).is_err());
+        assert_eq!(tracklist.files[0].tracks[0].index[0].1, Time::new(0, 0, 0));
+        assert!(tracklist.apply_sample_offset(588, 0).is_err());
+    }
+
+    #[test]
+    fn apply_sample_offset_across_files() {
+        let src = "FILE 01.wav WAVE\nTRACK 01 AUDIO\nINDEX 01 00:00:10\n\
+                   FILE 02.wav WAVE\nTRACK 02 AUDIO\nINDEX 01 00:00:00\n\
+                   FILE 03.wav WAVE\nTRACK 03 AUDIO\nINDEX 01 00:00:00";
+        let mut tracklist = Tracklist::parse(src).unwrap();
+        assert!(tracklist.clone().apply_sample_offset(-588, 44100).is_err());
+
+        let length = |_: &TrackFile| -> Result<Time, Error> { Ok(Time::new(3, 0, 0)) };
+        tracklist.fill_durations(&length).unwrap();
+        let mut earlier = tracklist.clone();
+        earlier.apply_sample_offset(-588, 44100).unwrap();
+        let f = &earlier.files;
+        assert_eq!(f[0].tracks.len(), 2);
+        assert_eq!(f[0].tracks[1].number, 2);
+        assert_eq!(f[0].tracks[1].index[0].1, Time::new(2, 59, 74));
+        assert_eq!(f[0].tracks[1].album_start(), Some(Time::new(2, 59, 74)));
+        assert_eq!(f[1].tracks[0].number, 3);
+        assert!(f[2].tracks.is_empty());
+        assert_eq!(f[0].tracks[0].duration, Some(Time::new(2, 59, 65)));
+        assert_eq!(f[1].tracks[0].duration, Some(Time::new(0, 0, 1)));
+
+        // Moving the tracks back leaves every track in its own file again.
+        earlier.apply_sample_offset(588, 44100).unwrap();
+        let numbers: Vec<Vec<u32>> = earlier
+            .files
+            .iter()
+            .map(|f| f.tracks.iter().map(|t| t.number).collect())
+            .collect();
+        assert_eq!(numbers, vec![vec![1], vec![2], vec![3]]);
+        assert_eq!(earlier.files[2].tracks[0].index[0].1, Time::new(0, 0, 0));
+        assert_eq!(
+            earlier.files[1].tracks[0].duration,
+            Some(Time::new(3, 0, 0))
+        );
+        assert_eq!(earlier.files[2].tracks[0].duration, None);
+    }
+
+    #[cfg(feature = "flate2")]
+    #[test]
+    fn parse_gzip_path() {
+        use flate2::write::GzEncoder;
+        use flate2::Compression;
+        use std::env;
+        use std::io::Write;
+
+        let dir = env::temp_dir().join(format!("cue_sheet_gzip_{}", ::std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let source = b"TITLE \"Caf\xe9\"\nFILE \"a.wav\" WAVE\nTRACK 01 AUDIO\nINDEX 01 00:00:00\n";
+
+        let plain = dir.join("plain.cue");
+        fs::write(&plain, &source[..]).unwrap();
+        let compressed = dir.join("compressed.cue.gz");
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(source).unwrap();
+        fs::write(&compressed, encoder.finish().unwrap()).unwrap();
+
+        let tracklist = Tracklist::parse_path(&plain).unwrap();
+        assert_eq!(tracklist.title, Some("Café".to_string()));
+        assert_eq!(
+            Tracklist::parse_path(&compressed).unwrap().files,
+            tracklist.files
+        );
+        assert!(Tracklist::parse_path(dir.join("missing.cue")).is_err());
+        fs::write(&plain, b"TRACK 01").unwrap();
+        let error = Tracklist::parse_path(&plain).unwrap_err();
+        assert!(error.to_string().contains("plain.cue"));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn from_path() {
+        let dir = ::std::env::temp_dir().join(format!("cue_sheet_path_{}", ::std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("disc.cue");
+
+        fs::write(&path, b"TITLE \"Caf\xe9\"\nFILE \"a.wav\" WAVE\n").unwrap();
+        assert_eq!(
+            Tracklist::from_path(&path).unwrap().title,
+            Some("Café".to_string())
+        );
+
+        fs::write(&path, b"FILE \"a.wav\" WAVE\nINDEX\n").unwrap();
+        let error = Tracklist::from_path(&path).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            format!("Failed to parse {}", path.display())
+        );
+        assert!(error.iter().count() > 1);
+
+        let missing = dir.join("missing.cue");
+        let error = Tracklist::from_path(&missing).unwrap_err();
+        assert!(error.to_string().contains("missing.cue"));
+        let cause = fs::read(&missing).unwrap_err();
+        assert_eq!(error.iter().nth(1).unwrap().to_string(), cause.to_string());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn first_track_not_one() {
+        let source = "FILE disc.bin BINARY\nTRACK 02 AUDIO\nINDEX 01 00:00:00\n\
+                      TRACK 03 AUDIO\nINDEX 00 04:00:00\nINDEX 01 04:02:00\n\
+                      TRACK 04 AUDIO\nINDEX 01 08:00:00";
+        let tracklist = Tracklist::parse(source).unwrap();
+        assert_eq!(tracklist.first_track_number(), Some(2));
+        assert_eq!(tracklist.last_track_number(), Some(4));
+        assert_eq!(
+            tracklist.get_track(2).unwrap().duration,
+            Some(Time::new(4, 0, 0))
+        );
+        assert_eq!(
+            tracklist.get_track(3).unwrap().duration,
+            Some(Time::new(3, 58, 0))
+        );
+        assert!(tracklist.get_track(1).is_none());
+    }
+
+    #[test]
+    fn toc_fingerprint() {
+        let source = "FILE a.wav WAVE\nTRACK 01 AUDIO\nINDEX 01 00:00:00\nTRACK 02 AUDIO\n\
+                      INDEX 00 04:15:00\nINDEX 01 04:17:52";
+        let tracklist = Tracklist::parse(source).unwrap();
+        let fingerprint = tracklist.toc_fingerprint().unwrap();
+
+        let mut renamed = tracklist.clone();
+        renamed.title = Some("Loveless".to_string());
+        renamed.files[0].name = "b.wav".to_string();
+        assert_eq!(renamed.toc_fingerprint().unwrap(), fingerprint);
+
+        let moved = Tracklist::parse(&source.replace("04:17:52", "04:17:53")).unwrap();
+        assert_ne!(moved.toc_fingerprint().unwrap(), fingerprint);
+
+        let lengths = |length: Time| {
+            let mut filled = tracklist.clone();
+            let provider = |_: &TrackFile| -> Result<Time, Error> { Ok(length.clone()) };
+            filled.fill_durations(&provider).unwrap();
+            filled.toc_fingerprint().unwrap()
+        };
+        let ten = lengths(Time::new(10, 0, 0));
+        assert_ne!(ten, fingerprint);
+        assert_eq!(lengths(Time::new(10, 0, 0)), ten);
+        assert_ne!(lengths(Time::new(10, 0, 1)), ten);
+
+        let split = Tracklist::parse(
+            "FILE a.wav WAVE\nTRACK 01 AUDIO\nINDEX 01 00:00:00\n\
+             FILE b.wav WAVE\nTRACK 02 AUDIO\nINDEX 01 00:00:00",
+        )
+        .unwrap();
+        assert!(split.toc_fingerprint().is_err());
+    }
+
+    #[test]
+    fn duration_provenance() {
+        let source = "FILE a.wav WAVE\nTRACK 01 AUDIO\nINDEX 01 00:00:00\n\
+                      TRACK 02 AUDIO\nINDEX 01 04:17:52";
+        let mut tracklist = Tracklist::parse(source).unwrap();
+        let provenance = |tracklist: &Tracklist| -> Vec<Option<DurationProvenance>> {
+            tracklist
+                .tracks()
+                .map(|(_, t)| t.duration_provenance)
+                .collect()
+        };
+        assert_eq!(
+            provenance(&tracklist),
+            vec![Some(DurationProvenance::Derived), None]
+        );
+
+        let mut length = HashMap::new();
+        length.insert("a.wav".to_string(), Time::new(10, 0, 0));
+        tracklist.fill_durations(&length).unwrap();
+        assert_eq!(provenance(&tracklist)[1], Some(DurationProvenance::Probed));
+
+        // Computing the durations again derives them from the indexes.
+        let mut shifted = tracklist.clone();
+        shifted.apply_sample_offset(588, 44100).unwrap();
+        assert_eq!(
+            provenance(&shifted),
+            vec![Some(DurationProvenance::Derived); 2]
+        );
+
+        let listing = vec![("Only Shallow".to_string(), Time::new(4, 17, 52))];
+        let tracklist =
+            Tracklist::from_listing("MBV", "Loveless", "a.wav", FileFormat::Wave, &listing)
+                .unwrap();
+        assert_eq!(
+            provenance(&tracklist),
+            vec![Some(DurationProvenance::Declared)]
+        );
+    }
 }
@@ -0,0 +1,358 @@
+// cue_sheet
+// Copyright (C) 2017  Leonardo Schwarz <mail@leoschwarz.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Validation of tracklists against the limits of the Red Book (audio CD) standard.
+//!
+//! These checks are useful before sending a cue sheet to a CD burner.
+
+use parser::Time;
+#[cfg(feature = "serde")]
+use serde::Serialize;
+use std::fmt;
+use tracklist::Tracklist;
+use warning::Warning;
+
+/// Maximum number of tracks on a disc.
+pub const MAX_TRACKS: usize = 99;
+
+/// Maximum number of indexes per track.
+pub const MAX_INDEXES: usize = 99;
+
+/// Returns whether `catalog` is a 13-digit EAN or 12-digit UPC code with a correct check digit.
+///
+/// ```
+/// use cue_sheet::validation::catalog_checksum_valid;
+///
+/// assert!(catalog_checksum_valid("0724384264529"));
+/// assert!(catalog_checksum_valid("724384264529"));
+/// assert!(!catalog_checksum_valid("0724384264525"));
+/// assert!(!catalog_checksum_valid("0000000000"));
+/// ```
+pub fn catalog_checksum_valid(catalog: &str) -> bool {
+    let digits: Option<Vec<u32>> = catalog.chars().map(|c| c.to_digit(10)).collect();
+    let digits = match digits {
+        Some(ref digits) if digits.len() == 12 || digits.len() == 13 => digits,
+        _ => return false,
+    };
+
+    // Digits are weighted 3 and 1 alternately, starting with 3 next to the check digit.
+    let sum: u32 = digits
+        .iter()
+        .rev()
+        .enumerate()
+        .map(|(i, d)| if i % 2 == 1 { d * 3 } else { *d })
+        .sum();
+    sum % 10 == 0
+}
+
+/// Capacity of the disc a tracklist is supposed to be burned to.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub enum DiscProfile {
+    /// A 74 minute disc.
+    Minutes74,
+
+    /// An 80 minute disc, the most common kind.
+    #[default]
+    Minutes80,
+
+    /// An overburned 99 minute disc.
+    Minutes99,
+
+    /// A disc of the specified capacity.
+    Custom(Time),
+}
+
+impl DiscProfile {
+    /// Returns the maximum length of a disc of this profile.
+    pub fn capacity(&self) -> Time {
+        match *self {
+            DiscProfile::Minutes74 => Time::new(74, 0, 0),
+            DiscProfile::Minutes80 => Time::new(79, 57, 74),
+            DiscProfile::Minutes99 => Time::new(99, 59, 74),
+            DiscProfile::Custom(ref time) => time.clone(),
+        }
+    }
+}
+
+/// A violation of a Red Book limit.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum RedbookWarning {
+    /// The total length exceeds the capacity of the disc.
+    DiscTooLong {
+        /// Total length of the tracklist.
+        length: Time,
+        /// Capacity of the disc.
+        capacity: Time,
+    },
+
+    /// There are more than `MAX_TRACKS` tracks.
+    TooManyTracks(usize),
+
+    /// The track with the specified number has more than `MAX_INDEXES` indexes.
+    TooManyIndexes {
+        /// Number of the track.
+        track: u32,
+        /// Number of indexes of the track.
+        count: usize,
+    },
+
+    /// The first index of the first track is not located at `00:00:00`.
+    FirstIndexNotAtStart(Time),
+}
+
+impl RedbookWarning {
+    /// Returns a stable identifier of the violation, e.g. `"disc-too-long"`.
+    pub fn code(&self) -> &'static str {
+        match *self {
+            RedbookWarning::DiscTooLong { .. } => "disc-too-long",
+            RedbookWarning::TooManyTracks(_) => "too-many-tracks",
+            RedbookWarning::TooManyIndexes { .. } => "too-many-indexes",
+            RedbookWarning::FirstIndexNotAtStart(_) => "first-index-not-at-start",
+        }
+    }
+
+    /// Returns the number of the track the violation concerns, if any.
+    pub fn track(&self) -> Option<u32> {
+        match *self {
+            RedbookWarning::TooManyIndexes { track, .. } => Some(track),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for RedbookWarning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            RedbookWarning::DiscTooLong {
+                ref length,
+                ref capacity,
+            } => write!(f, "Disc length {} exceeds capacity of {}", length, capacity),
+            RedbookWarning::TooManyTracks(count) => {
+                write!(f, "{} tracks exceed the limit of {}", count, MAX_TRACKS)
+            }
+            RedbookWarning::TooManyIndexes { track, count } => write!(
+                f,
+                "Track {:02} has {} indexes, exceeding the limit of {}",
+                track, count, MAX_INDEXES
+            ),
+            RedbookWarning::FirstIndexNotAtStart(ref time) => {
+                write!(f, "First index is at {} instead of 00:00:00", time)
+            }
+        }
+    }
+}
+
+/// Check `tracklist` against the Red Book limits for a disc of the specified `profile`.
+///
+/// The disc length can only be checked if the duration of the last track is known, see
+/// `Tracklist::fill_durations`.
+pub fn check_redbook(tracklist: &Tracklist, profile: &DiscProfile) -> Vec<RedbookWarning> {
+    let mut warnings = Vec::new();
+
+    if let Some(length) = tracklist.total_length() {
+        let capacity = profile.capacity();
+        if length > capacity {
+            warnings.push(RedbookWarning::DiscTooLong { length, capacity });
+        }
+    }
+
+    let tracks: Vec<_> = tracklist.tracks().map(|(_, t)| t).collect();
+    if tracks.len() > MAX_TRACKS {
+        warnings.push(RedbookWarning::TooManyTracks(tracks.len()));
+    }
+
+    for track in &tracks {
+        if track.index.len() > MAX_INDEXES {
+            warnings.push(RedbookWarning::TooManyIndexes {
+                track: track.number,
+                count: track.index.len(),
+            });
+        }
+    }
+
+    if let Some((_, time)) = tracks.first().and_then(|t| t.index.first()) {
+        if time.total_frames() != 0 {
+            warnings.push(RedbookWarning::FirstIndexNotAtStart(time.clone()));
+        }
+    }
+
+    warnings
+}
+
+/// How serious a problem in a `ValidationReport` is.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash, Ord, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(Serialize), serde(rename_all = "lowercase"))]
+pub enum Severity {
+    /// The cue sheet was read, but a problem was recovered from.
+    Warning,
+
+    /// The tracklist can't be burned to a disc as it is.
+    Error,
+}
+
+/// One problem listed in a `ValidationReport`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct Issue {
+    /// Stable identifier of the kind of problem, see `WarningKind::code` and
+    /// `RedbookWarning::code`.
+    pub code: &'static str,
+
+    /// How serious the problem is.
+    pub severity: Severity,
+
+    /// Line (starting at 1) of the cue sheet containing the problem, if it is known.
+    pub line: Option<usize>,
+
+    /// Number of the track the problem concerns, if any.
+    pub track: Option<u32>,
+
+    /// Human readable description of the problem.
+    pub message: String,
+}
+
+/// Collects the problems found while reading and validating a cue sheet in a form suited for
+/// scripts, e.g. to fail a build on specific codes.
+///
+/// With the `serde` feature enabled the report can be serialized, its JSON representation is
+/// an object with an `issues` array of objects with the fields of `Issue`.
+///
+/// ```
+/// use cue_sheet::tracklist::Tracklist;
+/// use cue_sheet::validation::{check_redbook, Severity, ValidationReport};
+///
+/// let source = "FILE a.wav WAVE\nTRACK 01 AUDIO\nINDEX 01 00:00:80";
+/// let (tracklist, warnings) = Tracklist::parse_with_warnings(source, &Default::default()).unwrap();
+///
+/// let mut report = ValidationReport::new();
+/// report.add_warnings(&warnings);
+/// report.add_redbook(&check_redbook(&tracklist, &Default::default()));
+///
+/// assert_eq!(report.issues[0].code, "time-out-of-range");
+/// assert_eq!(report.issues[0].line, Some(3));
+/// assert_eq!(report.issues[1].code, "first-index-not-at-start");
+/// assert_eq!(report.max_severity(), Some(Severity::Error));
+/// ```
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct ValidationReport {
+    /// The problems in the order they were added.
+    pub issues: Vec<Issue>,
+}
+
+impl ValidationReport {
+    /// Create an empty report.
+    pub fn new() -> ValidationReport {
+        ValidationReport::default()
+    }
+
+    /// Add the `warnings` returned by one of the `*_with_warnings` parse functions.
+    pub fn add_warnings(&mut self, warnings: &[Warning]) {
+        for warning in warnings {
+            self.issues.push(Issue {
+                code: warning.kind.code(),
+                severity: Severity::Warning,
+                line: Some(warning.line),
+                track: None,
+                message: warning.kind.to_string(),
+            });
+        }
+    }
+
+    /// Add the violations returned by `check_redbook`.
+    pub fn add_redbook(&mut self, warnings: &[RedbookWarning]) {
+        for warning in warnings {
+            self.issues.push(Issue {
+                code: warning.code(),
+                severity: Severity::Error,
+                line: None,
+                track: warning.track(),
+                message: warning.to_string(),
+            });
+        }
+    }
+
+    /// Returns the severity of the most serious issue, or `None` if there are no issues.
+    pub fn max_severity(&self) -> Option<Severity> {
+        self.issues.iter().map(|issue| issue.severity).max()
+    }
+
+    /// Returns whether the report contains an issue with the specified code.
+    pub fn contains(&self, code: &str) -> bool {
+        self.issues.iter().any(|issue| issue.code == code)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use errors::Error;
+    use tracklist::TrackFile;
+
+    #[test]
+    fn redbook() {
+        let src = r#"FILE "disc.wav" WAVE
+                       TRACK 01 AUDIO
+                         INDEX 01 00:00:32
+                       TRACK 02 AUDIO
+                         INDEX 01 40:00:00"#;
+        let mut tracklist = Tracklist::parse(src).unwrap();
+
+        assert_eq!(
+            check_redbook(&tracklist, &DiscProfile::default()),
+            vec![RedbookWarning::FirstIndexNotAtStart(Time::new(0, 0, 32))]
+        );
+
+        let length = |_: &TrackFile| -> Result<Time, Error> { Ok(Time::new(75, 0, 0)) };
+        tracklist.fill_durations(&length).unwrap();
+        assert_eq!(check_redbook(&tracklist, &DiscProfile::Minutes80).len(), 1);
+        assert_eq!(
+            check_redbook(&tracklist, &DiscProfile::Minutes74)[0],
+            RedbookWarning::DiscTooLong {
+                length: Time::new(75, 0, 0),
+                capacity: Time::new(74, 0, 0),
+            }
+        );
+    }
+
+    #[test]
+    fn too_many_tracks() {
+        let mut src = String::from("FILE \"disc.wav\" WAVE\n");
+        for i in 0..100 {
+            let (number, time) = (i % 99 + 1, Time::new(i, 0, 0));
+            src.push_str(&format!("TRACK {:02} AUDIO\nINDEX 01 {}\n", number, time));
+        }
+        let tracklist = Tracklist::parse(&src).unwrap();
+        assert_eq!(
+            check_redbook(&tracklist, &DiscProfile::Minutes99),
+            vec![RedbookWarning::TooManyTracks(100)]
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn report_json() {
+        let tracklist =
+            Tracklist::parse("FILE a.wav WAVE\nTRACK 01 AUDIO\nINDEX 01 00:00:32").unwrap();
+        let mut report = ValidationReport::new();
+        report.add_redbook(&check_redbook(&tracklist, &DiscProfile::default()));
+
+        assert_eq!(
+            ::serde_json::to_string(&report).unwrap(),
+            r#"{"issues":[{"code":"first-index-not-at-start","severity":"error","line":null,"track":null,"message":"First index is at 00:00:32 instead of 00:00:00"}]}"#
+        );
+    }
+}
@@ -0,0 +1,173 @@
+// cue_sheet
+// Copyright (C) 2017  Leonardo Schwarz <mail@leoschwarz.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Warnings about problems which were recovered from while parsing.
+//!
+//! Outside of strict mode the parser tolerates some common mistakes. The `*_with_warnings`
+//! parse functions report what was corrected, e.g. to show it to users.
+
+use parser::{FileFormat, Time, TrackFlag};
+use std::fmt;
+
+/// A problem the parser recovered from.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum WarningKind {
+    /// A line with an unknown command was skipped.
+    UnknownCommand(String),
+
+    /// A keyword, file format, track type or flag was not written in upper case.
+    NonstandardCase(String),
+
+    /// A time had seconds or frames out of range and was normalized to the contained value.
+    TimeOutOfRange(Time),
+
+    /// A time was written in a nonstandard form, see `ParseOptions::lenient_times`.
+    NonstandardTime(String),
+
+    /// The file format of a `FILE` was missing and inferred from its name.
+    InferredFileFormat(FileFormat),
+
+    /// The file format of a `FILE` was not one of the standard keywords, see
+    /// `FileFormat::Other`.
+    NonstandardFileFormat(String),
+
+    /// A track type was written with a nonstandard spelling (e.g. `MODE2_FORM1` of cdrdao) and
+    /// read as the corresponding `TrackType`.
+    NonstandardTrackType(String),
+
+    /// The name of a `FILE` contained spaces but wasn't quoted, all words up to the file format
+    /// were taken as the name.
+    UnquotedFileName(String),
+
+    /// Nonstandard arguments after the file format of a `FILE` were kept, see
+    /// `TrackFile::arguments`.
+    FileArguments(Vec<String>),
+
+    /// A disc level `CATALOG`, `CDTEXTFILE`, `TITLE`, `PERFORMER` or `SONGWRITER` appeared more
+    /// than once, the last one wins, see `CueSheet::superseded`.
+    DuplicateCommand(String),
+
+    /// A flag appeared more than once in a `FLAGS` command, only the first one was kept.
+    DuplicateFlag(TrackFlag),
+
+    /// Tokens after the end of a command were ignored.
+    TrailingTokens(String),
+
+    /// A `CATALOG` number is not a valid EAN-13 or UPC-A code, see `ParseOptions::check_catalog`.
+    InvalidCatalog(String),
+
+    /// Zero-width characters outside of quoted strings were removed or replaced.
+    ZeroWidthCharacters,
+
+    /// A line starting with `;` or `//` was read as a comment, see `ParseOptions::keep_comments`.
+    NonstandardComment(String),
+
+    /// The track with this number starts before the previous track of the same file, the
+    /// tracks were sorted by their start. Strict parsing rejects this with
+    /// `ErrorKind::TracksOutOfOrder`.
+    TracksOutOfOrder(u32),
+}
+
+/// A problem the parser recovered from, together with its location.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Warning {
+    /// Line (starting at 1) of the source containing the problem.
+    pub line: usize,
+
+    /// The kind of problem.
+    pub kind: WarningKind,
+}
+
+impl WarningKind {
+    /// Returns a stable identifier of the kind of problem, e.g. `"time-out-of-range"`.
+    pub fn code(&self) -> &'static str {
+        match *self {
+            WarningKind::UnknownCommand(_) => "unknown-command",
+            WarningKind::NonstandardCase(_) => "nonstandard-case",
+            WarningKind::TimeOutOfRange(_) => "time-out-of-range",
+            WarningKind::NonstandardTime(_) => "nonstandard-time",
+            WarningKind::InferredFileFormat(_) => "inferred-file-format",
+            WarningKind::NonstandardFileFormat(_) => "nonstandard-file-format",
+            WarningKind::NonstandardTrackType(_) => "nonstandard-track-type",
+            WarningKind::UnquotedFileName(_) => "unquoted-file-name",
+            WarningKind::FileArguments(_) => "file-arguments",
+            WarningKind::DuplicateCommand(_) => "duplicate-command",
+            WarningKind::DuplicateFlag(_) => "duplicate-flag",
+            WarningKind::TrailingTokens(_) => "trailing-tokens",
+            WarningKind::InvalidCatalog(_) => "invalid-catalog",
+            WarningKind::ZeroWidthCharacters => "zero-width-characters",
+            WarningKind::NonstandardComment(_) => "nonstandard-comment",
+            WarningKind::TracksOutOfOrder(_) => "tracks-out-of-order",
+        }
+    }
+}
+
+impl fmt::Display for WarningKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            WarningKind::UnknownCommand(ref command) => {
+                write!(f, "Skipped unknown command {:?}", command)
+            }
+            WarningKind::NonstandardCase(ref word) => {
+                write!(f, "{:?} is not written in upper case", word)
+            }
+            WarningKind::TimeOutOfRange(ref time) => {
+                write!(f, "Time out of range, normalized to {}", time)
+            }
+            WarningKind::NonstandardTime(ref time) => write!(f, "Nonstandard time {:?}", time),
+            WarningKind::InferredFileFormat(ref format) => {
+                write!(f, "Missing file format, assumed {}", format)
+            }
+            WarningKind::NonstandardFileFormat(ref keyword) => {
+                write!(f, "Nonstandard file format {:?}", keyword)
+            }
+            WarningKind::NonstandardTrackType(ref keyword) => {
+                write!(f, "Nonstandard track type {:?}", keyword)
+            }
+            WarningKind::UnquotedFileName(ref name) => {
+                write!(f, "Read unquoted file name {:?}", name)
+            }
+            WarningKind::FileArguments(ref arguments) => {
+                write!(
+                    f,
+                    "Kept nonstandard FILE arguments {:?}",
+                    arguments.join(" ")
+                )
+            }
+            WarningKind::DuplicateCommand(ref keyword) => {
+                write!(f, "{} repeated, superseding the earlier one", keyword)
+            }
+            WarningKind::DuplicateFlag(ref flag) => write!(f, "Ignored duplicate flag {}", flag),
+            WarningKind::TrailingTokens(ref text) => write!(f, "Ignored {:?}", text),
+            WarningKind::InvalidCatalog(ref catalog) => {
+                write!(f, "Catalog number {:?} has an invalid check digit", catalog)
+            }
+            WarningKind::ZeroWidthCharacters => f.write_str("Removed zero-width characters"),
+            WarningKind::NonstandardComment(ref comment) => {
+                write!(f, "Read nonstandard comment {:?}", comment)
+            }
+            WarningKind::TracksOutOfOrder(track) => {
+                write!(f, "Track {} starts before the previous track", track)
+            }
+        }
+    }
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Line {}: {}", self.line, self.kind)
+    }
+}
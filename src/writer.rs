@@ -0,0 +1,293 @@
+// cue_sheet
+// Copyright (C) 2017  Leonardo Schwarz <mail@leoschwarz.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Writing of cue sheets.
+
+use parser::{Command, Quoted, SpelledCommand};
+use std::fmt;
+use std::sync::OnceLock;
+use tracklist::normalize_file_name;
+
+/// Case in which keywords, file formats, track types and flags are written.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum KeywordCase {
+    /// Upper case, as required by the specification.
+    #[default]
+    Upper,
+
+    /// Lower case, for compatibility with tools which produce or expect it.
+    Lower,
+
+    /// The spelling of the source, see `write_spelled_commands`. Commands without a recorded
+    /// spelling are written in upper case.
+    Preserve,
+}
+
+/// Line ending written after every command.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum LineEnding {
+    /// `\n`, as used on Unix.
+    #[default]
+    Lf,
+
+    /// `\r\n`, as used on Windows and by most ripping software.
+    CrLf,
+}
+
+impl LineEnding {
+    /// Returns the characters of the line ending.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            LineEnding::Lf => "\n",
+            LineEnding::CrLf => "\r\n",
+        }
+    }
+}
+
+/// Options controlling how cue sheets are written.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct WriteOptions {
+    /// Write `FILE` entries with only their file name, dropping any directories, see
+    /// `TrackFile::file_name_normalized`.
+    pub normalize_file_names: bool,
+
+    /// Case of keywords, file formats, track types and flags. Values like titles are always
+    /// written as they are.
+    pub keyword_case: KeywordCase,
+
+    /// Line ending written after every command.
+    pub line_ending: LineEnding,
+}
+
+/// Builder-style setters, see `ParseOptions` for an example.
+impl WriteOptions {
+    /// Create the default options, same as `WriteOptions::default`.
+    pub fn new() -> WriteOptions {
+        WriteOptions::default()
+    }
+
+    /// Returns a shared instance of the default options, which `Tracklist::to_cue_string` and
+    /// `CueSheet::to_cue_string` use.
+    pub fn global_default() -> &'static WriteOptions {
+        static DEFAULT: OnceLock<WriteOptions> = OnceLock::new();
+        DEFAULT.get_or_init(WriteOptions::default)
+    }
+
+    /// Set `WriteOptions::normalize_file_names`.
+    pub fn normalize_file_names(mut self, normalize: bool) -> WriteOptions {
+        self.normalize_file_names = normalize;
+        self
+    }
+
+    /// Set `WriteOptions::keyword_case`.
+    pub fn keyword_case(mut self, case: KeywordCase) -> WriteOptions {
+        self.keyword_case = case;
+        self
+    }
+
+    /// Set `WriteOptions::line_ending`.
+    pub fn line_ending(mut self, line_ending: LineEnding) -> WriteOptions {
+        self.line_ending = line_ending;
+        self
+    }
+}
+
+/// Format `command` with its keywords in `case`, using the spellings in `keywords` for
+/// `KeywordCase::Preserve`.
+fn format_command(command: &Command, case: KeywordCase, keywords: &[String]) -> String {
+    let line = command.to_string();
+    if case == KeywordCase::Upper || (case == KeywordCase::Preserve && keywords.is_empty()) {
+        return line;
+    }
+
+    // Returns the `i`th keyword of the command, which is written as `word` in upper case.
+    let spell = |i: usize, word: &str| match case {
+        KeywordCase::Preserve => match keywords.get(i) {
+            Some(spelling) if spelling.eq_ignore_ascii_case(word) => spelling.clone(),
+            _ => word.to_string(),
+        },
+        _ => word.to_lowercase(),
+    };
+
+    match *command {
+        // These consist of keywords and numbers only.
+        Command::Flags(_) | Command::Track(_, _) => {
+            let mut i = 0;
+            let words: Vec<String> = line
+                .split(' ')
+                .map(|word| {
+                    if word.bytes().all(|b| b.is_ascii_digit()) {
+                        word.to_string()
+                    } else {
+                        i += 1;
+                        spell(i - 1, word)
+                    }
+                })
+                .collect();
+            words.join(" ")
+        }
+        Command::File(ref name, ref format, ref arguments) => {
+            let format = spell(1, &format.to_string());
+            let mut line = format!("{} {} {}", spell(0, "FILE"), Quoted(name), format);
+            for argument in arguments {
+                line.push_str(&format!(" {}", argument));
+            }
+            line
+        }
+        _ => match line.find(' ') {
+            Some(pos) => format!("{}{}", spell(0, &line[..pos]), &line[pos..]),
+            None => spell(0, &line),
+        },
+    }
+}
+
+/// Write `commands` as cue sheet text into `out` using the provided `options`.
+///
+/// Commands are indented according to the level they apply to, e.g. commands following a `TRACK`
+/// are indented further than the `TRACK` itself. The commands don't have to make up a whole cue
+/// sheet, which allows writing snippets like a `TRACK` to append to an existing file.
+///
+/// ```
+/// use cue_sheet::parser::{Command, Time, TrackType};
+/// use cue_sheet::writer::{write_commands, WriteOptions};
+///
+/// let commands = vec![
+///     Command::Track(3, TrackType::Audio),
+///     Command::Title("When You Sleep".to_string()),
+///     Command::Index(1, Time::new(9, 14, 20)),
+/// ];
+/// let mut text = String::new();
+/// write_commands(&commands, &mut text, &WriteOptions::default()).unwrap();
+/// assert_eq!(
+///     text,
+///     "  TRACK 03 AUDIO\n    TITLE \"When You Sleep\"\n    INDEX 01 09:14:20\n"
+/// );
+/// ```
+pub fn write_commands<W: fmt::Write>(
+    commands: &[Command],
+    out: &mut W,
+    options: &WriteOptions,
+) -> fmt::Result {
+    write_lines(commands.iter().map(|c| (c, &[][..])), out, options)
+}
+
+/// Write `commands` like `write_commands`, with `KeywordCase::Preserve` writing their keywords
+/// as they were spelled in the source, see `parser::parse_cue_spelled`.
+pub fn write_spelled_commands<W: fmt::Write>(
+    commands: &[SpelledCommand],
+    out: &mut W,
+    options: &WriteOptions,
+) -> fmt::Result {
+    write_lines(
+        commands.iter().map(|c| (&c.command, &c.keywords[..])),
+        out,
+        options,
+    )
+}
+
+/// Write `commands`, paired with the spelling of their keywords.
+fn write_lines<'a, W, I>(commands: I, out: &mut W, options: &WriteOptions) -> fmt::Result
+where
+    W: fmt::Write,
+    I: Iterator<Item = (&'a Command, &'a [String])>,
+{
+    let mut in_track = false;
+
+    for (command, keywords) in commands {
+        let indent = match *command {
+            Command::File(..) => {
+                in_track = false;
+                0
+            }
+            Command::Track(_, _) => {
+                in_track = true;
+                2
+            }
+            _ if in_track => 4,
+            _ => 0,
+        };
+        let line = match *command {
+            Command::File(ref name, ref format, ref arguments) if options.normalize_file_names => {
+                let file =
+                    Command::File(normalize_file_name(name), format.clone(), arguments.clone());
+                format_command(&file, options.keyword_case, keywords)
+            }
+            _ => format_command(command, options.keyword_case, keywords),
+        };
+        write!(
+            out,
+            "{:indent$}{}{}",
+            "",
+            line,
+            options.line_ending.as_str(),
+            indent = indent
+        )?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parser::{parse_cue, parse_cue_spelled, ParseOptions};
+
+    #[test]
+    fn keyword_case() {
+        let source = "REM GENRE Shoegaze\nTITLE \"TITLE\"\nFILE \"A B.WAV\" WAVE\n  TRACK 01 MODE1/2352\n    FLAGS DCP 4CH\n    INDEX 01 00:00:00\n";
+        let commands = parse_cue(source).unwrap();
+
+        let mut upper = String::new();
+        write_commands(&commands, &mut upper, &WriteOptions::default()).unwrap();
+        assert_eq!(upper, source);
+
+        let options = WriteOptions {
+            keyword_case: KeywordCase::Lower,
+            ..Default::default()
+        };
+        let mut lower = String::new();
+        write_commands(&commands, &mut lower, &options).unwrap();
+        assert_eq!(
+            lower,
+            "rem GENRE Shoegaze\ntitle \"TITLE\"\nfile \"A B.WAV\" wave\n  track 01 mode1/2352\n    flags dcp 4ch\n    index 01 00:00:00\n"
+        );
+
+        let mut preserved = String::new();
+        let options = WriteOptions::new().keyword_case(KeywordCase::Preserve);
+        write_commands(&commands, &mut preserved, &options).unwrap();
+        assert_eq!(preserved, source);
+
+        let mixed = "Rem GENRE Shoegaze\nFILE \"A B.WAV\" wave\n  Track 01 mode1/2352\n    \
+                     flags Dcp 4CH\n    index 01 00:00:00\n";
+        let (spelled, _) = parse_cue_spelled(mixed, &ParseOptions::default()).unwrap();
+        let mut preserved = String::new();
+        write_spelled_commands(&spelled, &mut preserved, &options).unwrap();
+        assert_eq!(preserved, mixed);
+
+        let options = WriteOptions::new().line_ending(LineEnding::CrLf);
+        let mut crlf = String::new();
+        write_commands(&commands, &mut crlf, &options).unwrap();
+        assert_eq!(crlf, source.replace('\n', "\r\n"));
+    }
+
+    #[test]
+    fn shared_options() {
+        fn assert_shareable<T: Send + Sync + Clone>() {}
+        assert_shareable::<WriteOptions>();
+        assert_shareable::<::parser::ParseOptions>();
+        assert_eq!(WriteOptions::global_default(), &WriteOptions::default());
+    }
+}
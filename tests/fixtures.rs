@@ -0,0 +1,122 @@
+// cue_sheet
+// Copyright (C) 2017  Leonardo Schwarz <mail@leoschwarz.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Golden file tests for the cue sheets in `tests/fixtures`.
+//!
+//! Every `<name>.cue` is parsed and a textual rendering of the resulting `CueSheet` is
+//! compared to `<name>.golden`. Run with `UPDATE_GOLDEN=1` to write the current output instead, and
+//! review the changes before committing them.
+
+extern crate cue_sheet;
+
+use cue_sheet::sheet::CueSheet;
+use std::env;
+use std::fmt::{Debug, Write};
+use std::fs;
+use std::path::Path;
+
+/// Render everything known about `sheet`, one item per line.
+fn render(sheet: &CueSheet) -> String {
+    fn field<T: Debug>(out: &mut String, indent: usize, name: &str, value: &Option<T>) {
+        if let Some(ref value) = *value {
+            writeln!(out, "{:indent$}{}: {:?}", "", name, value, indent = indent).unwrap();
+        }
+    }
+
+    let mut out = String::new();
+    field(&mut out, 0, "catalog", &sheet.catalog);
+    field(&mut out, 0, "cdtextfile", &sheet.cdtextfile);
+    field(&mut out, 0, "title", &sheet.title);
+    field(&mut out, 0, "performer", &sheet.performer);
+    field(&mut out, 0, "songwriter", &sheet.songwriter);
+    for (key, value) in &sheet.rem {
+        writeln!(out, "rem {:?}: {:?}", key, value).unwrap();
+    }
+
+    for file in &sheet.files {
+        writeln!(out, "file {:?} {}", file.name, file.format).unwrap();
+        for track in &file.tracks {
+            writeln!(out, "  track {:02} {}", track.number, track.track_type).unwrap();
+            field(&mut out, 4, "title", &track.title);
+            field(&mut out, 4, "performer", &track.performer);
+            field(&mut out, 4, "songwriter", &track.songwriter);
+            field(&mut out, 4, "isrc", &track.isrc);
+            if !track.flags.is_empty() {
+                writeln!(out, "    flags: {:?}", track.flags).unwrap();
+            }
+            for (key, value) in &track.rem {
+                writeln!(out, "    rem {:?}: {:?}", key, value).unwrap();
+            }
+            for (number, time) in &track.index {
+                writeln!(out, "    index {:02}: {}", number, time).unwrap();
+            }
+            let times = [
+                ("pregap", &track.pregap),
+                ("postgap", &track.postgap),
+                ("duration", &track.duration),
+                ("album_start", &track.album_start()),
+            ];
+            for (name, time) in times.iter() {
+                if let Some(ref time) = **time {
+                    writeln!(out, "    {}: {}", name, time).unwrap();
+                }
+            }
+        }
+    }
+    out
+}
+
+#[test]
+fn fixtures() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("fixtures");
+    let update = env::var_os("UPDATE_GOLDEN").is_some();
+
+    let mut paths: Vec<_> = fs::read_dir(&dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "cue"))
+        .collect();
+    paths.sort();
+    assert!(!paths.is_empty());
+
+    let mut failures = Vec::new();
+    for path in &paths {
+        let source = fs::read_to_string(path).unwrap();
+        let sheet = CueSheet::parse(&source).unwrap_or_else(|e| panic!("{:?}: {}", path, e));
+        assert_eq!(
+            CueSheet::parse(&sheet.to_cue_string()).unwrap(),
+            sheet,
+            "{:?} doesn't survive a round trip",
+            path
+        );
+
+        let actual = render(&sheet);
+        let golden = path.with_extension("golden");
+        if update {
+            fs::write(&golden, &actual).unwrap();
+        } else if fs::read_to_string(&golden).ok().as_ref() != Some(&actual) {
+            failures.push(golden);
+        }
+    }
+
+    assert!(
+        failures.is_empty(),
+        "Output differs from golden files (rerun with UPDATE_GOLDEN=1 to update): {:?}",
+        failures
+    );
+}
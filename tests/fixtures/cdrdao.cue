@@ -0,0 +1,10 @@
+FILE "data.bin" BINARY
+  TRACK 01 AUDIO
+    FLAGS PRE
+    INDEX 01 00:00:00
+  TRACK 02 AUDIO
+    PREGAP 00:02:00
+    INDEX 01 03:12:40
+  TRACK 03 AUDIO
+    INDEX 01 07:30:00
+    POSTGAP 00:02:00
@@ -0,0 +1,23 @@
+REM GENRE Alternative
+REM DATE 1991
+REM DISCID 860B640B
+REM COMMENT "ExactAudioCopy v0.99pb5"
+CATALOG 0724384264525
+PERFORMER "My Bloody Valentine"
+TITLE "Loveless"
+FILE "My Bloody Valentine - Loveless.wav" WAVE
+  TRACK 01 AUDIO
+    TITLE "Only Shallow"
+    PERFORMER "My Bloody Valentine"
+    ISRC GBAAA9100001
+    INDEX 01 00:00:00
+  TRACK 02 AUDIO
+    TITLE "Loomer"
+    PERFORMER "My Bloody Valentine"
+    INDEX 00 04:15:40
+    INDEX 01 04:17:52
+  TRACK 03 AUDIO
+    TITLE "Touched"
+    PERFORMER "My Bloody Valentine"
+    FLAGS DCP
+    INDEX 01 06:55:65
@@ -0,0 +1,11 @@
+PERFORMER "Various Artists"
+TITLE "Mixtape"
+FILE "01 - Intro.mp3" MP3
+  TRACK 01 AUDIO
+    TITLE "Intro"
+    INDEX 01 00:00:00
+FILE "02 - Outro.mp3"
+  TRACK 02 AUDIO
+    TITLE "Outro"
+    PERFORMER "Somebody"
+    INDEX 01 00:00:00
@@ -0,0 +1,17 @@
+REM DATE 2003
+REM COMMENT "XLD version 20191004 (153.0)"
+PERFORMER "Boards of Canada"
+TITLE "Geogaddi"
+REM DISCNUMBER 1
+REM TOTALDISCS 1
+FILE "01 Ready Lets Go.flac" WAVE
+  TRACK 01 AUDIO
+    TITLE "Ready Lets Go"
+    PERFORMER "Boards of Canada"
+    INDEX 01 00:00:00
+FILE "02 Music Is Math.flac" WAVE
+  TRACK 02 AUDIO
+    TITLE "Music Is Math"
+    PERFORMER "Boards of Canada"
+    INDEX 00 00:00:00
+    INDEX 01 00:00:37
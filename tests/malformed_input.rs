@@ -0,0 +1,56 @@
+// cue_sheet
+// Copyright (C) 2017  Leonardo Schwarz <mail@leoschwarz.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Property tests asserting that malformed input is rejected with an error instead of a panic.
+
+extern crate cue_sheet;
+extern crate proptest;
+
+use cue_sheet::errors::ErrorKind;
+use cue_sheet::parser::{parse_cue, parse_cue_with_options, ParseOptions, MAX_STRING_LENGTH};
+use proptest::prelude::*;
+
+#[test]
+fn unterminated_string_line() {
+    let source = "TITLE x\nFILE \"a.wav\" WAVE\n  TITLE \"Loveless";
+    match *parse_cue(source).unwrap_err().kind() {
+        ErrorKind::UnterminatedString(3, 9) => {}
+        ref kind => panic!("unexpected error {:?}", kind),
+    }
+}
+
+#[test]
+fn long_unterminated_string() {
+    let source = format!("TITLE \"{}", "x".repeat(4 * MAX_STRING_LENGTH));
+    match *parse_cue(&source).unwrap_err().kind() {
+        ErrorKind::StringTooLong(1, 7) => {}
+        ref kind => panic!("unexpected error {:?}", kind),
+    }
+}
+
+proptest! {
+    #[test]
+    fn quotes_never_panic(source in "([A-Z0-9: \t\"\n]|TRACK|INDEX|FILE|REM){0,64}") {
+        let _ = parse_cue(&source);
+        let strict = ParseOptions { strict: true, ..Default::default() };
+        let _ = parse_cue_with_options(&source, &strict);
+    }
+
+    #[test]
+    fn any_text_never_panics(source in "\\PC{0,128}") {
+        let _ = parse_cue(&source);
+    }
+}
@@ -0,0 +1,78 @@
+// cue_sheet
+// Copyright (C) 2017  Leonardo Schwarz <mail@leoschwarz.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Property tests for the conversions and arithmetic of `Time`.
+
+extern crate cue_sheet;
+extern crate proptest;
+
+use cue_sheet::parser::Time;
+use proptest::prelude::*;
+
+/// A time with all components in their valid ranges.
+fn time() -> impl Strategy<Value = Time> {
+    (0..1_000_000i32, 0..60i8, 0..75i8).prop_map(|(m, s, f)| Time::new(m, s, f))
+}
+
+/// Whether the components of `time` are in their valid ranges.
+fn is_valid(time: &Time) -> bool {
+    time.minutes() >= 0 && (0..60).contains(&time.seconds()) && (0..75).contains(&time.frames())
+}
+
+proptest! {
+    #[test]
+    fn frames_round_trip(m in 0..=i32::MAX, s in 0..60i8, f in 0..75i8) {
+        let time = Time::new(m, s, f);
+        prop_assert_eq!(Time::from_frames(time.total_frames()), time);
+    }
+
+    #[test]
+    fn from_frames_is_valid(frames in 0..i64::from(i32::MAX) * 60 * 75) {
+        let time = Time::from_frames(frames);
+        prop_assert!(is_valid(&time));
+        prop_assert_eq!(time.total_frames(), frames);
+    }
+
+    #[test]
+    fn display_round_trip(time in time()) {
+        prop_assert_eq!(time.to_string().parse::<Time>().unwrap(), time);
+    }
+
+    #[test]
+    fn checked_add_is_valid(a in time(), b in time()) {
+        let sum = a.checked_add(&b).unwrap();
+        prop_assert!(is_valid(&sum));
+        prop_assert_eq!(sum.total_frames(), a.total_frames() + b.total_frames());
+    }
+
+    #[test]
+    fn sub_is_valid(a in time(), b in time()) {
+        let (a, b) = if a >= b { (a, b) } else { (b, a) };
+        let diff = a.clone() - b.clone();
+        prop_assert!(is_valid(&diff));
+        prop_assert_eq!(diff.checked_add(&b), Some(a));
+    }
+
+    #[test]
+    fn sub_saturates(a in time(), b in time()) {
+        // Subtracting a later time has no valid representation, the difference is only
+        // available as a number of frames.
+        prop_assume!(a < b);
+        prop_assert_eq!(a.clone() - b.clone(), Time::new(0, 0, 0));
+        prop_assert_eq!(a.checked_sub(&b), None);
+        prop_assert_eq!(a.signed_diff(&b), a.total_frames() - b.total_frames());
+    }
+}
@@ -0,0 +1,127 @@
+// cue_sheet
+// Copyright (C) 2017  Leonardo Schwarz <mail@leoschwarz.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Property tests asserting that values are read back as they are displayed.
+//!
+//! Cue sheets are written with the `Display` implementations, so everything displayed has to be
+//! accepted by the `FromStr` implementations and the parser.
+
+extern crate cue_sheet;
+extern crate proptest;
+
+use cue_sheet::parser::{parse_cue, tokenize, Command, FileFormat, Token, TrackFlag, TrackType};
+use proptest::prelude::*;
+use proptest::sample::select;
+use std::fmt::Display;
+use std::str::FromStr;
+
+/// The file formats defined by the specification.
+fn file_format() -> impl Strategy<Value = FileFormat> {
+    select(vec![
+        FileFormat::Wave,
+        FileFormat::Mp3,
+        FileFormat::Aiff,
+        FileFormat::Binary,
+        FileFormat::Motorola,
+    ])
+}
+
+fn track_flag() -> impl Strategy<Value = TrackFlag> {
+    select(vec![
+        TrackFlag::Dcp,
+        TrackFlag::FourChannel,
+        TrackFlag::Pre,
+        TrackFlag::Scms,
+    ])
+}
+
+/// The track types defined by the specification.
+fn track_type() -> impl Strategy<Value = TrackType> {
+    select(vec![
+        TrackType::Audio,
+        TrackType::Cdg,
+        TrackType::Mode(1, 2048),
+        TrackType::Mode(1, 2352),
+        TrackType::Mode(2, 2048),
+        TrackType::Mode(2, 2324),
+        TrackType::Mode(2, 2336),
+        TrackType::Mode(2, 2352),
+        TrackType::Cdi(2336),
+        TrackType::Cdi(2352),
+    ])
+}
+
+/// Parse the displayed form of `value`, also in lower case like some tools write it.
+fn round_trip<T>(value: &T) -> Result<(), TestCaseError>
+where
+    T: Display + FromStr + PartialEq + std::fmt::Debug,
+    T::Err: std::fmt::Debug,
+{
+    let text = value.to_string();
+    prop_assert_eq!(&T::from_str(&text).unwrap(), value);
+    prop_assert_eq!(&T::from_str(&text.to_lowercase()).unwrap(), value);
+    Ok(())
+}
+
+proptest! {
+    #[test]
+    fn file_format_round_trip(format in file_format()) {
+        round_trip(&format)?;
+    }
+
+    #[test]
+    fn nonstandard_file_format_round_trip(keyword in "[A-Z][A-Z0-9]{0,7}") {
+        prop_assume!(keyword.parse::<FileFormat>().is_err());
+        // `FileFormat::Other` is not accepted by `FromStr`, but read back by the parser.
+        let command = Command::File("a.wav".to_string(), FileFormat::Other(keyword.clone()), Vec::new());
+        match parse_cue(&command.to_string()).unwrap().remove(0) {
+            Command::File(_, format, _) => prop_assert_eq!(format, FileFormat::Other(keyword)),
+            command => prop_assert!(false, "unexpected command {:?}", command),
+        }
+    }
+
+    #[test]
+    fn track_flag_round_trip(flag in track_flag()) {
+        round_trip(&flag)?;
+    }
+
+    #[test]
+    fn track_type_round_trip(track_type in track_type()) {
+        round_trip(&track_type)?;
+    }
+
+    #[test]
+    fn track_type_alias_round_trip(alias in select(vec![
+        "MODE1", "MODE1_RAW", "MODE2", "MODE2_FORM1", "MODE2_FORM2", "MODE2_FORM_MIX", "MODE2_RAW",
+    ])) {
+        // Aliases are written with the keyword of the specification.
+        let track_type = TrackType::from_str(alias).unwrap();
+        prop_assert_ne!(track_type.to_string(), alias);
+        round_trip(&track_type)?;
+    }
+
+    #[test]
+    fn number_token_round_trip(value in 0..100u32) {
+        let token = Token::number(value);
+        prop_assert_eq!(tokenize(&token.to_string()).unwrap(), vec![token]);
+    }
+
+    #[test]
+    fn string_token_round_trip(s in "[^\"\r\n]*") {
+        let token = Token::String(s);
+        prop_assert_eq!(tokenize(&token.to_string()).unwrap(), vec![token]);
+    }
+}